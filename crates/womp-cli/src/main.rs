@@ -1,13 +1,46 @@
-use clap::{Parser, Subcommand};
-use womp_core::{apply_display_layout, get_global_config, get_profiles, save_current_display_layout};
+use clap::{ArgGroup, Parser, Subcommand, ValueEnum};
+use serde::Serialize;
+use std::io;
+use std::path::PathBuf;
+use std::sync::mpsc;
+use std::thread;
+use std::time::Duration;
+use womp_core::bundle::BUNDLE_EXTENSION;
+use womp_core::comparison::ProfileMatch;
+use womp_core::config_manager::{
+    export_profile_bundle, import_profile_bundle, set_config_dir_override,
+};
+use womp_core::edid::EdidInfo;
+use womp_core::error::WompError;
+use womp_core::inheritance::{resolve_profile, unresolved_profile};
+use womp_core::inventory::Inventory;
+use womp_core::logging::{self, LogTarget};
+use womp_core::model::{EdidIds, Layout, Monitor, Rotation};
+use womp_core::profile_store::{FsProfileStore, ProfileStore};
+use womp_core::report::ApplyReport;
+use womp_core::schema::{SchemaKind, schema_json};
+use womp_core::serde_types::config::{Extra, Scope};
+use womp_core::serde_types::global_config::{GlobalConfig, LogLevel};
+use womp_core::snapshot::Snapshot;
+use womp_core::validation::{Issue, Severity, ValidationReport};
+use womp_core::{
+    apply_display_layout, apply_display_layout_unconfirmed, confirm_display_layout,
+    get_global_config, get_monitor_inventory, get_profiles, plan_display_layout, rank_profiles,
+    revert_display_layout, save_current_display_layout, save_scoped_display_layout,
+    set_monitor_alias, validate_profile,
+};
 
 #[derive(Parser)]
 #[command(author, version, about = "Windows Output Manager Protocol CLI")]
 struct Cli {
-    /// Enable debug mode
+    /// Log debug output, regardless of the `log_level` in settings.toml
     #[arg(short, long, global = true)]
     debug: bool,
 
+    /// Use <CONFIG_DIR> instead of the default config directory
+    #[arg(long, global = true, value_name = "CONFIG_DIR")]
+    config_dir: Option<PathBuf>,
+
     #[command(subcommand)]
     command: Commands,
 }
@@ -15,37 +48,239 @@ struct Cli {
 #[derive(Subcommand)]
 enum Commands {
     /// Save the current profile as <PROFILE_NAME>
+    ///
+    /// Without scope options, a profile that already has a scope keeps it.
     #[command(arg_required_else_help = true)]
-    Save { profile_name: String },
+    Save {
+        profile_name: String,
+        /// Only save the display called <NAME> (friendly name or device path), can be given
+        /// more than once. Applying leaves all other displays as they are
+        #[arg(long = "display", value_name = "NAME", conflicts_with_all = ["no_displays", "full"])]
+        displays: Vec<String>,
+        /// Don't save any displays, applying leaves the display layout alone
+        #[arg(long, conflicts_with = "full")]
+        no_displays: bool,
+        /// Only save these extras instead of the ones enabled in settings
+        #[arg(long, value_delimiter = ',', conflicts_with_all = ["no_extras", "full"])]
+        extras: Option<Vec<ExtraArg>>,
+        /// Don't save any extras
+        #[arg(long, conflicts_with = "full")]
+        no_extras: bool,
+        /// Save everything and drop the scope of the profile
+        #[arg(long)]
+        full: bool,
+    },
     /// Apply the profile named <PROFILE_NAME>
     #[command(arg_required_else_help = true)]
-    Apply { profile_name: String },
+    Apply {
+        profile_name: String,
+        /// Only print what applying the profile would change
+        #[arg(long)]
+        dry_run: bool,
+        /// Print the changes, or what was applied, as JSON
+        #[arg(long)]
+        json: bool,
+        /// Restore the previous layout unless Enter is pressed within <SECONDS>
+        #[arg(long, value_name = "SECONDS", conflicts_with = "dry_run")]
+        confirm_timeout: Option<u32>,
+    },
     /// List all profiles
     List,
+    /// Print the settings and displays of the profile named <PROFILE_NAME>
+    #[command(arg_required_else_help = true)]
+    Show {
+        profile_name: String,
+        /// Show the profile with everything it inherits through `extends` filled in
+        #[arg(long)]
+        resolved: bool,
+    },
+    /// Show the active profile, or the closest one and how the current layout differs from it
+    Status {
+        /// Rank every profile by how close it is
+        #[arg(long)]
+        all: bool,
+        /// Print the ranking as JSON
+        #[arg(long)]
+        json: bool,
+    },
+    /// List every monitor a profile has captured
+    Monitors {
+        #[command(subcommand)]
+        action: Option<MonitorsAction>,
+        /// Print the monitors as JSON
+        #[arg(long)]
+        json: bool,
+    },
+    /// Pack the profile named <PROFILE_NAME> and the files it uses into a single file
+    #[command(arg_required_else_help = true)]
+    Export {
+        profile_name: String,
+        /// Where to write the bundle [default: <PROFILE_NAME>.womp]
+        #[arg(short, long)]
+        output: Option<PathBuf>,
+    },
+    /// Add the profile packed in <FILE>, renaming it if the name is taken
+    #[command(arg_required_else_help = true)]
+    Import { file: PathBuf },
+    /// Check profiles for problems that would make applying them fail
+    #[command(group(ArgGroup::new("profiles").required(true).args(["profile_name", "all"])))]
+    Validate {
+        profile_name: Option<String>,
+        /// Check every profile
+        #[arg(long)]
+        all: bool,
+        /// Print the findings as JSON
+        #[arg(long)]
+        json: bool,
+    },
+    /// Upgrade all profiles to the current file format, keeping backups of the originals
+    Migrate,
+    /// Print the JSON Schema of a WOMP file
+    #[command(arg_required_else_help = true)]
+    Schema { kind: SchemaArg },
+}
+
+#[derive(Subcommand)]
+enum MonitorsAction {
+    /// Call the monitor <MONITOR> <ALIAS> wherever it's shown, "" removes the alias
+    #[command(arg_required_else_help = true)]
+    Alias {
+        /// Number, alias, name or device path of the monitor, see `womp-cli monitors`
+        monitor: String,
+        alias: String,
+    },
+}
+
+#[derive(Clone, Copy, ValueEnum)]
+enum SchemaArg {
+    /// displays.json of a profile
+    Displays,
+    /// layout.toml of a profile
+    Layout,
+    /// profile.toml of a profile
+    Profile,
+    /// settings.toml
+    Settings,
+    /// monitors.toml
+    Monitors,
+}
+
+// Same names as in profile.toml
+#[derive(Clone, Copy, ValueEnum)]
+#[value(rename_all = "snake_case")]
+enum ExtraArg {
+    DpiScale,
+    HdrState,
+    SdrWhiteLevel,
+    IconSize,
+    WallpaperInfo,
+    AudioOutput,
+}
+
+impl From<ExtraArg> for Extra {
+    fn from(extra: ExtraArg) -> Self {
+        match extra {
+            ExtraArg::DpiScale => Extra::DpiScale,
+            ExtraArg::HdrState => Extra::HdrState,
+            ExtraArg::SdrWhiteLevel => Extra::SdrWhiteLevel,
+            ExtraArg::IconSize => Extra::IconSize,
+            ExtraArg::WallpaperInfo => Extra::WallpaperInfo,
+            ExtraArg::AudioOutput => Extra::AudioOutput,
+        }
+    }
+}
+
+impl From<SchemaArg> for SchemaKind {
+    fn from(kind: SchemaArg) -> Self {
+        match kind {
+            SchemaArg::Displays => SchemaKind::DisplayLayout,
+            SchemaArg::Layout => SchemaKind::LayoutSpec,
+            SchemaArg::Profile => SchemaKind::Config,
+            SchemaArg::Settings => SchemaKind::GlobalConfig,
+            SchemaArg::Monitors => SchemaKind::Inventory,
+        }
+    }
 }
 
 fn main() {
     let cli = Cli::parse();
 
-    let global_config = get_global_config();
+    // Takes precedence over WOMP_CONFIG_DIR and portable mode
+    if let Some(config_dir) = &cli.config_dir {
+        set_config_dir_override(Some(config_dir.clone()));
+    }
+
+    let global_config = match get_global_config() {
+        Ok(global_config) => global_config,
+        Err(e) => {
+            eprintln!("Failed to load global config: {e}");
+            std::process::exit(1);
+        }
+    };
+
+    let log_level = if cli.debug {
+        LogLevel::Debug
+    } else {
+        global_config.log_level
+    };
+    if let Err(e) = logging::init(LogTarget::Stderr, log_level) {
+        eprintln!("Failed to set up logging: {e}");
+    }
 
     match &cli.command {
-        Commands::Save { profile_name } => {
-            match save_current_display_layout(profile_name, &global_config, cli.debug) {
+        Commands::Save {
+            profile_name,
+            displays,
+            no_displays,
+            extras,
+            no_extras,
+            full,
+        } => {
+            let scope = Scope {
+                displays: match (*no_displays, displays.is_empty()) {
+                    (true, _) => Some(vec![]),
+                    (false, false) => Some(displays.clone()),
+                    (false, true) => None,
+                },
+                extras: match (*no_extras, extras) {
+                    (true, _) => Some(vec![]),
+                    (false, Some(extras)) => Some(extras.iter().map(|&e| e.into()).collect()),
+                    (false, None) => None,
+                },
+            };
+            let saved = if *full || !scope.is_full() {
+                save_scoped_display_layout(profile_name, &global_config, &scope)
+            } else {
+                save_current_display_layout(profile_name, &global_config)
+            };
+            match saved {
                 Ok(_) => println!("Successfully saved display layout: {profile_name}"),
                 Err(e) => {
                     eprintln!("Failed to save display layout: {e}");
                 }
             }
         }
-        Commands::Apply { profile_name } => {
-            match apply_display_layout(profile_name, &global_config, cli.debug) {
-                Ok(_) => println!("Successfully applied display layout: {profile_name}"),
-                Err(e) => {
-                    eprintln!("Failed to apply display layout: {e}");
-                }
+        Commands::Apply {
+            profile_name,
+            dry_run: true,
+            json,
+            ..
+        } => show_plan(profile_name, &global_config, *json),
+        Commands::Apply {
+            profile_name,
+            confirm_timeout: Some(seconds),
+            json,
+            ..
+        } => apply_confirmed(profile_name, &global_config, *seconds, *json),
+        Commands::Apply {
+            profile_name, json, ..
+        } => match apply_display_layout(profile_name, &global_config) {
+            Ok(report) => print_apply_report(&report, *json),
+            Err(e) => {
+                print_apply_failure(&e, *json);
+                std::process::exit(1);
             }
-        }
+        },
         Commands::List => match get_profiles() {
             Ok(profiles) => {
                 for (name, profile) in profiles {
@@ -60,5 +295,548 @@ fn main() {
                 eprintln!("Failed to list profiles: {e}");
             }
         },
+        Commands::Show {
+            profile_name,
+            resolved,
+        } => show_profile(profile_name, *resolved),
+        Commands::Status { all, json } => show_status(&global_config, *all, *json),
+        Commands::Monitors { action, json } => match action {
+            None => list_monitors(*json),
+            Some(MonitorsAction::Alias { monitor, alias }) => {
+                match set_monitor_alias(monitor, alias) {
+                    Ok(()) if alias.trim().is_empty() => {
+                        println!("Removed the alias of `{monitor}`")
+                    }
+                    Ok(()) => println!("`{monitor}` is now called `{}`", alias.trim()),
+                    Err(e) => {
+                        eprintln!("Failed to set alias: {e}");
+                        std::process::exit(1);
+                    }
+                }
+            }
+        },
+        Commands::Export {
+            profile_name,
+            output,
+        } => {
+            let output = output
+                .clone()
+                .unwrap_or_else(|| PathBuf::from(format!("{profile_name}.{BUNDLE_EXTENSION}")));
+            match export_profile_bundle(profile_name, &output) {
+                Ok(manifest) => println!(
+                    "Exported profile `{profile_name}` with {} asset(s) to `{}`",
+                    manifest.assets.len(),
+                    output.display()
+                ),
+                Err(e) => {
+                    eprintln!("Failed to export profile: {e}");
+                    std::process::exit(1);
+                }
+            }
+        }
+        Commands::Import { file } => match import_profile_bundle(file) {
+            Ok(profile_name) => println!("Imported profile `{profile_name}`"),
+            Err(e) => {
+                eprintln!("Failed to import profile: {e}");
+                std::process::exit(1);
+            }
+        },
+        Commands::Validate {
+            profile_name, json, ..
+        } => validate_profiles(profile_name.as_deref(), *json),
+        Commands::Migrate => migrate_profiles(),
+        Commands::Schema { kind } => match schema_json((*kind).into()) {
+            Ok(schema) => println!("{schema}"),
+            Err(e) => {
+                eprintln!("Failed to generate schema: {e}");
+                std::process::exit(1);
+            }
+        },
+    }
+}
+
+fn show_profile(profile_name: &str, resolved: bool) {
+    let profile = FsProfileStore::open_default().and_then(|store| {
+        if resolved {
+            resolve_profile(&store, profile_name)
+        } else {
+            unresolved_profile(&store, profile_name)
+        }
+    });
+    let profile = match profile {
+        Ok(profile) => profile,
+        Err(e) => {
+            eprintln!("Failed to load profile: {e}");
+            std::process::exit(1);
+        }
+    };
+
+    println!("Profile `{}`", profile.name);
+    if profile.chain.len() > 1 {
+        println!("Extends: {}", profile.chain[1..].join(" -> "));
+    }
+    if !profile.config.is_empty() {
+        match toml::to_string(&profile.config) {
+            Ok(config) => {
+                println!("\nprofile.toml:");
+                for line in config.lines() {
+                    if line.is_empty() {
+                        println!();
+                    } else {
+                        println!("  {line}");
+                    }
+                }
+            }
+            Err(e) => eprintln!("Failed to serialize profile.toml: {e}"),
+        }
+    }
+
+    if let Some(display_layout) = &profile.layout {
+        let layout = Layout::from(display_layout);
+        let inventory = Inventory::load_default().unwrap_or_default();
+        println!("\nDisplays:");
+        if layout.monitors.is_empty() {
+            println!("  (none)");
+        }
+        for (display, monitor) in display_layout.displays.iter().zip(&layout.monitors) {
+            println!(
+                "  {}",
+                describe_monitor(&inventory.display_name(display), monitor)
+            );
+            let edid = monitor
+                .edid_info
+                .clone()
+                .or_else(|| EdidInfo::from_ids(monitor.edid));
+            if let Some(edid) = edid {
+                println!("    EDID: {}", edid.summary());
+            }
+        }
+        let global = &layout.global;
+        if let Some(icon_size) = global.iconSize {
+            println!("Icon size: {icon_size}");
+        }
+        if let Some(wallpaper) = &global.wallpaperInfo {
+            println!(
+                "Wallpaper: {} ({})",
+                wallpaper.wallpaperPath, wallpaper.wallpaperPosition
+            );
+        }
+        if let Some(audio_output) = &global.audioOutput {
+            println!("Audio output: {audio_output}");
+        }
+    } else if let Some(spec) = &profile.layout_spec {
+        println!(
+            "\nlayout.toml with {} monitor(s), see `womp-cli schema layout`",
+            spec.monitors.len()
+        );
+    } else {
+        println!("\nNo displays.json or layout.toml");
+    }
+}
+
+fn show_status(global_config: &GlobalConfig, all: bool, json: bool) {
+    let ranking = match rank_profiles(global_config) {
+        Ok(ranking) => ranking,
+        Err(e) => {
+            eprintln!("Failed to compare profiles: {e}");
+            std::process::exit(1);
+        }
+    };
+    let shown = if all {
+        &ranking[..]
+    } else {
+        &ranking[..ranking.len().min(1)]
+    };
+
+    if json {
+        match serde_json::to_string_pretty(shown) {
+            Ok(output) => println!("{output}"),
+            Err(e) => eprintln!("Failed to serialize status: {e}"),
+        }
+        return;
+    }
+    match ranking.first() {
+        None => println!("No profile has a saved layout to compare"),
+        Some(closest) if closest.is_active() => println!("Active profile: `{}`", closest.profile),
+        Some(closest) => println!(
+            "No profile is active, the closest is `{}` ({}%)",
+            closest.profile, closest.comparison.score
+        ),
+    }
+    if all {
+        for profile_match in shown {
+            println!();
+            print_profile_match(profile_match);
+        }
+    } else if let Some(closest) = ranking.first() {
+        print_differences(closest);
+    }
+}
+
+fn show_plan(profile_name: &str, global_config: &GlobalConfig, json: bool) {
+    let plan = match plan_display_layout(profile_name, global_config) {
+        Ok(plan) => plan,
+        Err(e) => {
+            eprintln!("Failed to plan applying `{profile_name}`: {e}");
+            std::process::exit(1);
+        }
+    };
+    if json {
+        match serde_json::to_string_pretty(&plan) {
+            Ok(output) => println!("{output}"),
+            Err(e) => eprintln!("Failed to serialize plan: {e}"),
+        }
+        return;
+    }
+    if plan.is_empty() {
+        println!("Applying `{profile_name}` wouldn't change anything");
+    } else {
+        println!("Applying `{profile_name}` would:");
+    }
+    for (i, change) in plan.changes.iter().enumerate() {
+        println!("  {}. {change}", i + 1);
+    }
+}
+
+fn print_apply_report(report: &ApplyReport, json: bool) {
+    if json {
+        match serde_json::to_string_pretty(report) {
+            Ok(output) => println!("{output}"),
+            Err(e) => eprintln!("Failed to serialize report: {e}"),
+        }
+        return;
+    }
+    if !report.failed() {
+        println!(
+            "Successfully applied display layout: {} ({} ms)",
+            report.profile,
+            report.duration.as_millis()
+        );
+    }
+    let print_hooks = |stage: &str| {
+        for hook in report.hooks.iter().filter(|hook| hook.stage == stage) {
+            println!("  {hook}");
+            for line in hook.stdout.lines().chain(hook.stderr.lines()) {
+                println!("    | {line}");
+            }
+        }
+    };
+    print_hooks("before");
+    for step in &report.steps {
+        println!("  {step}");
+    }
+    if let Some(flags) = &report.display_config_flags {
+        println!("  The displays were set with {}", flags.join(" | "));
+    }
+    if let Some(verification) = &report.verification {
+        println!("  {verification}");
+        for difference in &verification.differences {
+            println!("    {difference}");
+        }
+    }
+    print_hooks("after");
+}
+
+fn print_apply_failure(error: &WompError, json: bool) {
+    if let WompError::ApplyFailed { report, .. } = error {
+        print_apply_report(report, json);
+    }
+    eprintln!("Failed to apply display layout: {error}");
+}
+
+/// Applies the profile and restores the previous layout unless Enter is pressed within
+/// `seconds`. Exits with 1 if applying failed or the new layout wasn't kept, once the
+/// displays are confirmed or restored.
+fn apply_confirmed(profile_name: &str, global_config: &GlobalConfig, seconds: u32, json: bool) {
+    let (snapshot, failed) = match apply_display_layout_unconfirmed(profile_name, global_config) {
+        Ok((report, snapshot)) => {
+            print_apply_report(&report, json);
+            (snapshot, false)
+        }
+        Err(e) => {
+            print_apply_failure(&e, json);
+            // The displays were changed before a later step failed
+            match e {
+                WompError::ApplyFailed {
+                    snapshot: Some(snapshot),
+                    ..
+                } => (*snapshot, true),
+                _ => std::process::exit(1),
+            }
+        }
+    };
+    let confirmation = confirm_or_revert(profile_name, &snapshot, seconds, json);
+    if failed || confirmation != Confirmation::Kept {
+        std::process::exit(1);
+    }
+}
+
+/// What became of a layout that had to be confirmed.
+#[derive(Clone, Copy, PartialEq, Eq)]
+enum Confirmation {
+    Kept,
+    /// Not confirmed in time, the previous layout is back.
+    Reverted,
+    /// Saving the confirmed layout or restoring the previous one failed.
+    Failed,
+}
+
+/// Keeps the new layout if Enter is pressed within `seconds`, otherwise restores `snapshot`.
+fn confirm_or_revert(
+    profile_name: &str,
+    snapshot: &Snapshot,
+    seconds: u32,
+    json: bool,
+) -> Confirmation {
+    // Only the report goes to stdout with --json, so it stays parseable
+    let say = |message: String| {
+        if json {
+            eprintln!("{message}");
+        } else {
+            println!("{message}");
+        }
+    };
+    say(format!(
+        "Press Enter within {seconds} seconds to keep the new layout, otherwise the previous \
+         layout is restored"
+    ));
+
+    let (confirm, confirmed) = mpsc::channel();
+    thread::spawn(move || {
+        let mut line = String::new();
+        // Without a terminal to read from there's no one to confirm
+        if let Ok(1..) = io::stdin().read_line(&mut line) {
+            let _ = confirm.send(());
+        }
+    });
+    if confirmed
+        .recv_timeout(Duration::from_secs(seconds.into()))
+        .is_ok()
+    {
+        return match confirm_display_layout(snapshot) {
+            Ok(()) => {
+                say(format!("Kept display layout: {profile_name}"));
+                Confirmation::Kept
+            }
+            Err(e) => {
+                eprintln!("Failed to save the new layout: {e}");
+                Confirmation::Failed
+            }
+        };
+    }
+    match revert_display_layout(snapshot) {
+        Ok(()) => {
+            say("Not confirmed, restored the previous layout".to_string());
+            Confirmation::Reverted
+        }
+        Err(e) => {
+            eprintln!("Failed to restore the previous layout: {e}");
+            Confirmation::Failed
+        }
+    }
+}
+
+fn print_profile_match(profile_match: &ProfileMatch) {
+    let partial = if profile_match.partial {
+        ", partial"
+    } else {
+        ""
+    };
+    println!(
+        "`{}`: {}%{partial}",
+        profile_match.profile, profile_match.comparison.score
+    );
+    print_differences(profile_match);
+}
+
+fn print_differences(profile_match: &ProfileMatch) {
+    for difference in &profile_match.comparison.differences {
+        println!("  - {difference}");
+    }
+}
+
+fn describe_monitor(name: &str, monitor: &Monitor) -> String {
+    let mut description = format!(
+        "{name}: {}x{} @ {} at ({}, {})",
+        monitor.mode.width,
+        monitor.mode.height,
+        monitor.mode.refresh_rate,
+        monitor.position.x,
+        monitor.position.y
+    );
+    if monitor.rotation != Rotation::Identity {
+        description += &format!(", rotated {:?}", monitor.rotation);
+    }
+    let extras = &monitor.extras;
+    if let Some(dpi_scale) = extras.dpiScale {
+        description += &format!(", DPI {dpi_scale}%");
+    }
+    if let Some(hdr_enabled) = extras.hdrEnabled {
+        description += if hdr_enabled { ", HDR on" } else { ", HDR off" };
+    }
+    if let Some(white_level) = extras.sdrWhiteLevel {
+        description += &format!(", SDR white level {white_level} nits");
+    }
+    description
+}
+
+fn list_monitors(json: bool) {
+    let inventory = match get_monitor_inventory() {
+        Ok(inventory) => inventory,
+        Err(e) => {
+            eprintln!("Failed to read the monitor inventory: {e}");
+            std::process::exit(1);
+        }
+    };
+    if json {
+        match serde_json::to_string_pretty(&inventory.monitors) {
+            Ok(output) => println!("{output}"),
+            Err(e) => eprintln!("Failed to serialize monitors: {e}"),
+        }
+        return;
+    }
+    if inventory.monitors.is_empty() {
+        println!("No monitors yet, they're added when a profile is saved");
+    }
+    // The date is enough, RFC 3339 starts with it
+    let date = |time: &str| time.get(..10).unwrap_or(time).to_string();
+    for monitor in &inventory.monitors {
+        let mut line = format!("{}: {}", monitor.id, monitor.label());
+        if !monitor.alias.is_empty() && !monitor.name.is_empty() {
+            line += &format!(" ({})", monitor.name);
+        }
+        println!("{line}, {}", monitor.output_technology);
+        let edid = monitor.edid.clone().or_else(|| {
+            EdidInfo::from_ids(EdidIds {
+                manufacturer_id: monitor.manufacturer_id,
+                product_code: monitor.product_code,
+            })
+        });
+        if let Some(edid) = edid {
+            println!("   EDID: {}", edid.summary());
+        }
+        println!(
+            "   First seen {}, last seen {}",
+            date(&monitor.first_seen),
+            date(&monitor.last_seen)
+        );
+        if !monitor.profiles.is_empty() {
+            println!("   Profiles: {}", monitor.profiles.join(", "));
+        }
+    }
+}
+
+fn migrate_profiles() {
+    let store = match FsProfileStore::open_default() {
+        Ok(store) => store,
+        Err(e) => {
+            eprintln!("Failed to open profiles directory: {e}");
+            std::process::exit(1);
+        }
+    };
+    let profiles = match store.list() {
+        Ok(profiles) => profiles,
+        Err(e) => {
+            eprintln!("Failed to list profiles: {e}");
+            std::process::exit(1);
+        }
+    };
+
+    let mut migrated = 0;
+    let mut failed = false;
+    for profile_name in profiles {
+        match store.migrate_profile(&profile_name) {
+            Ok(migrations) => {
+                for migration in &migrations {
+                    println!(
+                        "Upgraded `{}` from version {} to {} (backup: `{}`)",
+                        migration.path.display(),
+                        migration.from,
+                        migration.to,
+                        migration.backup.display()
+                    );
+                }
+                migrated += migrations.len();
+            }
+            Err(e) => {
+                eprintln!("Failed to migrate profile `{profile_name}`: {e}");
+                failed = true;
+            }
+        }
+    }
+    if failed {
+        std::process::exit(1);
+    }
+    if migrated == 0 {
+        println!("All profiles are up to date");
+    }
+}
+
+#[derive(Serialize)]
+struct ProfileValidation {
+    profile: String,
+    /// Set if the profile couldn't be checked at all
+    #[serde(skip_serializing_if = "Option::is_none")]
+    error: Option<String>,
+    issues: Vec<Issue>,
+}
+
+fn validate_profiles(profile_name: Option<&str>, json: bool) {
+    let profiles = match profile_name {
+        Some(profile_name) => vec![profile_name.to_string()],
+        None => match FsProfileStore::open_default().and_then(|store| store.list()) {
+            Ok(profiles) => profiles,
+            Err(e) => {
+                eprintln!("Failed to list profiles: {e}");
+                std::process::exit(1);
+            }
+        },
+    };
+
+    let results: Vec<ProfileValidation> = profiles
+        .into_iter()
+        .map(|profile| match validate_profile(&profile) {
+            Ok(ValidationReport { issues }) => ProfileValidation {
+                profile,
+                error: None,
+                issues,
+            },
+            Err(e) => ProfileValidation {
+                profile,
+                error: Some(e.to_string()),
+                issues: vec![],
+            },
+        })
+        .collect();
+
+    if json {
+        match serde_json::to_string_pretty(&results) {
+            Ok(output) => println!("{output}"),
+            Err(e) => eprintln!("Failed to serialize results: {e}"),
+        }
+    } else {
+        for result in &results {
+            match &result.error {
+                Some(e) => println!("`{}`: could not be checked: {e}", result.profile),
+                None if result.issues.is_empty() => println!("`{}`: ok", result.profile),
+                None => {
+                    println!("`{}`:", result.profile);
+                    for issue in &result.issues {
+                        println!("  {issue}");
+                    }
+                }
+            }
+        }
+    }
+
+    let failed = results.iter().any(|result| {
+        result.error.is_some()
+            || result
+                .issues
+                .iter()
+                .any(|issue| issue.severity == Severity::Error)
+    });
+    if failed {
+        std::process::exit(1);
     }
 }
@@ -0,0 +1,500 @@
+#![allow(non_snake_case)]
+
+use crate::comparison::Setting;
+use crate::edid::EdidInfo;
+use crate::error::Result;
+use crate::identity::{connected_monitors, remap_layout};
+use crate::model::EdidIds;
+use crate::report::describe_setting;
+use crate::serde_types::{
+    Display, DisplayLayout, GlobalInfo, WallpaperInfo, global_config::GlobalConfig,
+    optional_info::OptionalInfo,
+};
+use crate::win32_types::*;
+use itertools::Itertools;
+use log::{debug, warn};
+use std::ops::ControlFlow;
+use std::time::{Duration, Instant};
+pub mod simulated;
+
+// DPI values observed from system settings
+pub const DPI_VALS: [u32; 12] = [100, 125, 150, 175, 200, 225, 250, 300, 350, 400, 450, 500];
+
+// Values of WallpaperInfo.wallpaperPosition that set_wallpaper_info understands
+pub const WALLPAPER_POSITIONS: [&str; 6] = ["center", "tile", "stretch", "fit", "fill", "span"];
+
+// DPI scaling info structure
+pub struct DpiScalingInfo {
+    pub minimum: u32,
+    pub maximum: u32,
+    pub current: u32,
+    pub recommended: u32,
+}
+
+// HDR/Advanced Color info structure
+pub struct HdrInfo {
+    pub advanced_color_supported: bool,
+    pub advanced_color_enabled: bool,
+    pub wide_color_enforced: bool,
+    pub advanced_color_force_disabled: bool,
+    pub color_encoding: u32,
+    pub bits_per_color_channel: i32,
+}
+
+/// Returns the backend that talks to the displays of the machine we're running on.
+#[cfg(windows)]
+pub fn system_backend() -> Result<Box<dyn DisplayBackend>> {
+    Ok(Box::new(crate::ccd_manager::CCDWrapper::new(true)))
+}
+
+/// Returns the backend that talks to the displays of the machine we're running on.
+#[cfg(not(windows))]
+pub fn system_backend() -> Result<Box<dyn DisplayBackend>> {
+    Err(crate::error::WompError::UnsupportedBackend(
+        std::env::consts::OS.to_string(),
+    ))
+}
+
+/// Everything WOMP needs from the system to capture and apply a display layout.
+///
+/// The required methods are thin primitives (query/set the CCD topology, per-display
+/// DPI/HDR/SDR and the desktop extras); capturing a [`DisplayLayout`] and applying one
+/// is built on top of them, so the same logic runs against [`crate::ccd_manager::CCDWrapper`]
+/// on a real desktop and against [`simulated::SimulatedBackend`] anywhere else.
+pub trait DisplayBackend {
+    /// Returns the current paths and modes, like `QueryDisplayConfig`.
+    fn query_display_config(
+        &mut self,
+    ) -> Result<(Vec<DISPLAYCONFIG_PATH_INFO>, Vec<DISPLAYCONFIG_MODE_INFO>)>;
+
+    /// Returns every path the system could activate, including those of connected but
+    /// disabled monitors, like `QueryDisplayConfig` with `QDC_ALL_PATHS`. Only the paths
+    /// that are active have modes.
+    fn query_all_paths(
+        &mut self,
+    ) -> Result<(Vec<DISPLAYCONFIG_PATH_INFO>, Vec<DISPLAYCONFIG_MODE_INFO>)>;
+
+    fn get_target_name(
+        &self,
+        adapter_id: LUID,
+        target_id: u32,
+    ) -> Result<DISPLAYCONFIG_TARGET_DEVICE_NAME>;
+
+    fn get_adapter_name(
+        &self,
+        adapter_id: LUID,
+        target_id: u32,
+    ) -> Result<DISPLAYCONFIG_ADAPTER_NAME>;
+
+    /// Validates or applies the supplied topology, like `SetDisplayConfig`.
+    fn set_display_config(
+        &mut self,
+        paths: &[DISPLAYCONFIG_PATH_INFO],
+        modes: &[DISPLAYCONFIG_MODE_INFO],
+        flags: SET_DISPLAY_CONFIG_FLAGS,
+    ) -> Result<()>;
+
+    fn turn_off_all_displays(&mut self) -> Result<()>;
+
+    fn get_display_dpi_info(&self, adapter_id: LUID, source_id: u32) -> Result<DpiScalingInfo>;
+
+    fn set_display_dpi(&mut self, adapter_id: LUID, source_id: u32, dpi_percent: u32)
+    -> Result<()>;
+
+    fn get_display_hdr_info(&self, adapter_id: LUID, target_id: u32) -> Result<HdrInfo>;
+
+    fn set_display_hdr(&mut self, adapter_id: LUID, target_id: u32, enable: bool) -> Result<()>;
+
+    fn get_display_sdr_white_level(&self, adapter_id: LUID, target_id: u32) -> Result<u32>;
+
+    fn set_display_sdr_white_level(
+        &mut self,
+        adapter_id: LUID,
+        target_id: u32,
+        nits: u32,
+    ) -> Result<()>;
+
+    fn get_desktop_icon_size(&self) -> Result<i32>;
+
+    fn set_desktop_icon_size(&mut self, icon_size: i32) -> Result<()>;
+
+    fn get_wallpaper_info(&self) -> Result<WallpaperInfo>;
+
+    fn set_wallpaper_info(&mut self, wallpaper_info: &WallpaperInfo) -> Result<()>;
+
+    fn get_default_audio_output(&self) -> Result<String>;
+
+    fn set_default_audio_output(&mut self, device_id: &str) -> Result<()>;
+
+    /// The raw EDID of the monitor on the target, `None` if the backend can't read it.
+    fn get_edid(&self, _adapter_id: LUID, _target_id: u32) -> Result<Option<Vec<u8>>> {
+        Ok(None)
+    }
+
+    /// Captures the current layout, including the extras enabled in `global_config`.
+    fn get_display_layout(&mut self, global_config: &GlobalConfig) -> Result<DisplayLayout> {
+        let (paths, modes) = self.query_display_config()?;
+
+        let mut displays: Vec<Display> = vec![];
+
+        for path in &paths {
+            let [Some(target_mode), Some(source_mode)] = get_modes(&modes, path) else {
+                continue;
+            };
+
+            let (target_name, adapter_name, optional_info) =
+                get_additional_info(self, path, global_config)?;
+
+            let mut display = Display::from(
+                path,
+                target_mode,
+                source_mode,
+                &target_name,
+                &adapter_name,
+                &optional_info,
+            );
+            let edid = self
+                .get_edid(path.targetInfo.adapterId, path.targetInfo.id)
+                .unwrap_or_else(|e| {
+                    debug!(target_id = path.targetInfo.id, error:% = e; "Could not read EDID");
+                    None
+                });
+            let ids = EdidIds {
+                manufacturer_id: target_name.edidManufactureId,
+                product_code: target_name.edidProductCodeId,
+            };
+            display.edidInfo = EdidInfo::decode(ids, edid.as_deref());
+            displays.push(display)
+        }
+
+        let icon_size = if global_config.save_icon_size {
+            self.get_desktop_icon_size().ok()
+        } else {
+            None
+        };
+
+        let wallpaper_info = if global_config.save_wallpaper_info {
+            self.get_wallpaper_info().ok()
+        } else {
+            None
+        };
+
+        let audio_output = if global_config.save_audio_output {
+            self.get_default_audio_output().ok()
+        } else {
+            None
+        };
+
+        let global_info = GlobalInfo::from(icon_size, wallpaper_info, audio_output);
+        Ok(DisplayLayout::from(displays, global_info))
+    }
+
+    /// Applies `display_layout` and then the extras enabled in `global_config`. Its displays
+    /// are moved to wherever their monitors are connected now first.
+    fn apply_display_layout(
+        &mut self,
+        display_layout: &mut DisplayLayout,
+        global_config: &GlobalConfig,
+    ) -> Result<()> {
+        let connected = connected_monitors(self)?;
+        remap_layout(&connected, display_layout);
+
+        self.apply_topology(display_layout, true)?;
+        self.apply_extras(display_layout, global_config)
+    }
+
+    /// Validates and applies the paths and modes of `display_layout`, which must already
+    /// refer to the current adapters and targets, see [`remap_layout`]. With `save`, Windows
+    /// also keeps it for the next time these monitors are connected, a layout that's still
+    /// waiting to be confirmed isn't saved. Returns the flags `SetDisplayConfig` accepted.
+    fn apply_topology(
+        &mut self,
+        display_layout: &DisplayLayout,
+        save: bool,
+    ) -> Result<SET_DISPLAY_CONFIG_FLAGS> {
+        let mut paths = vec![];
+        let mut target_modes = vec![];
+        let mut source_modes = vec![];
+
+        for d in &display_layout.displays {
+            debug!(
+                source_id = d.pathInfo.sourceInfo.id,
+                target_id = d.pathInfo.targetInfo.id;
+                "Adding display to layout"
+            );
+            let (path_info, target_mode_info, source_mode_info, _, _) = d.to_windows_types();
+            paths.push(path_info);
+            target_modes.push(target_mode_info);
+            source_modes.push(source_mode_info);
+        }
+
+        let modes: Vec<DISPLAYCONFIG_MODE_INFO> =
+            target_modes.into_iter().interleave(source_modes).collect();
+
+        self.set_display_config(
+            &paths,
+            &modes,
+            SDC_USE_SUPPLIED_DISPLAY_CONFIG | SDC_VALIDATE,
+        )
+        .map_err(|e| e.context("Input arrays are invalid"))?;
+
+        let mut flags = SDC_APPLY | SDC_USE_SUPPLIED_DISPLAY_CONFIG | SDC_NO_OPTIMIZATION;
+        if save {
+            flags |= SDC_SAVE_TO_DATABASE;
+        }
+
+        if let Err(e) = self.set_display_config(&paths, &modes, flags) {
+            warn!(error:% = e; "Failed to apply, will try again with SDC_ALLOW_CHANGES");
+
+            flags |= SDC_ALLOW_CHANGES;
+
+            self.set_display_config(&paths, &modes, flags)
+                .map_err(|e| e.context("Failed to apply using SDC_ALLOW_CHANGES"))?;
+        }
+
+        Ok(flags)
+    }
+
+    /// Applies the extras of `display_layout` that are enabled in `global_config`.
+    fn apply_extras(
+        &mut self,
+        display_layout: &DisplayLayout,
+        global_config: &GlobalConfig,
+    ) -> Result<()> {
+        let mut error = None;
+        let _ = self.apply_extras_with(
+            display_layout,
+            global_config,
+            &mut |step| match step.result {
+                Ok(()) => ControlFlow::Continue(()),
+                Err(e) => {
+                    error = Some(e);
+                    ControlFlow::Break(())
+                }
+            },
+        );
+        error.map_or(Ok(()), Err)
+    }
+
+    /// Like [`DisplayBackend::apply_extras`], but every step that's made is handed to
+    /// `made`, which decides whether to go on with the next one.
+    fn apply_extras_with(
+        &mut self,
+        display_layout: &DisplayLayout,
+        global_config: &GlobalConfig,
+        made: &mut dyn FnMut(ExtraStep) -> ControlFlow<()>,
+    ) -> ControlFlow<()> {
+        if global_config.save_dpi_scale {
+            for d in &display_layout.displays {
+                if let Some(dpi_scale) = d.optionalInfo.dpiScale {
+                    let started = Instant::now();
+                    let result = self.set_display_dpi(
+                        d.pathInfo.sourceInfo.adapterId,
+                        d.pathInfo.sourceInfo.id,
+                        dpi_scale,
+                    );
+                    made(ExtraStep::new(Setting::DpiScale, Some(d), result, started))?;
+                }
+            }
+        }
+
+        if global_config.save_hdr_state {
+            for d in &display_layout.displays {
+                if let (Some(enabled), Some(true)) =
+                    (d.optionalInfo.hdrEnabled, d.optionalInfo.hdrSupported)
+                {
+                    let started = Instant::now();
+                    let result = self.set_display_hdr(
+                        d.pathInfo.targetInfo.adapterId,
+                        d.pathInfo.targetInfo.id,
+                        enabled,
+                    );
+                    made(ExtraStep::new(Setting::Hdr, Some(d), result, started))?;
+                }
+            }
+        }
+
+        if global_config.save_sdr_white_level {
+            for d in &display_layout.displays {
+                if let (Some(white_level), Some(true)) =
+                    (d.optionalInfo.sdrWhiteLevel, d.optionalInfo.hdrEnabled)
+                {
+                    let started = Instant::now();
+                    let result = self.set_display_sdr_white_level(
+                        d.pathInfo.targetInfo.adapterId,
+                        d.pathInfo.targetInfo.id,
+                        white_level,
+                    );
+                    made(ExtraStep::new(
+                        Setting::SdrWhiteLevel,
+                        Some(d),
+                        result,
+                        started,
+                    ))?;
+                }
+            }
+        }
+
+        // Extras that weren't captured when the profile was saved are left alone
+        if let (true, Some(icon_size)) = (
+            global_config.save_icon_size,
+            display_layout.globalInfo.iconSize,
+        ) {
+            let started = Instant::now();
+            let result = self.set_desktop_icon_size(icon_size);
+            made(ExtraStep::new(Setting::IconSize, None, result, started))?;
+        }
+
+        if let (true, Some(wallpaper_info)) = (
+            global_config.save_wallpaper_info,
+            &display_layout.globalInfo.wallpaperInfo,
+        ) {
+            let started = Instant::now();
+            let result = self.set_wallpaper_info(wallpaper_info);
+            made(ExtraStep::new(Setting::Wallpaper, None, result, started))?;
+        }
+
+        if let (true, Some(audio_output)) = (
+            global_config.save_audio_output,
+            &display_layout.globalInfo.audioOutput,
+        ) {
+            let started = Instant::now();
+            let result = self.set_default_audio_output(audio_output);
+            made(ExtraStep::new(Setting::AudioOutput, None, result, started))?;
+        }
+
+        ControlFlow::Continue(())
+    }
+}
+
+/// A step of [`DisplayBackend::apply_extras_with`] that was made.
+pub struct ExtraStep<'a> {
+    pub setting: Setting,
+    /// The display the setting was made on, unset for desktop-wide settings.
+    pub display: Option<&'a Display>,
+    pub result: Result<()>,
+    pub duration: Duration,
+}
+
+impl<'a> ExtraStep<'a> {
+    fn new(
+        setting: Setting,
+        display: Option<&'a Display>,
+        result: Result<()>,
+        started: Instant,
+    ) -> Self {
+        ExtraStep {
+            setting,
+            display,
+            result,
+            duration: started.elapsed(),
+        }
+    }
+
+    /// What the step set, e.g. "the DPI scale of `DELL U2720Q`".
+    pub fn describe(&self) -> String {
+        let display = self.display.map(Display::describe);
+        describe_setting(self.setting, display.as_deref())
+    }
+}
+
+/// Finds the target and source mode referenced by `path`.
+pub fn get_modes<'a>(
+    modes: &'a [DISPLAYCONFIG_MODE_INFO],
+    path: &DISPLAYCONFIG_PATH_INFO,
+) -> [Option<&'a DISPLAYCONFIG_MODE_INFO>; 2] {
+    let target_id = path.targetInfo.id;
+    let target_adapter_id = path.targetInfo.adapterId;
+    let source_id = path.sourceInfo.id;
+    let source_adapter_id = path.sourceInfo.adapterId;
+
+    let mut target_mode_opt: Option<&DISPLAYCONFIG_MODE_INFO> = None;
+    let mut source_mode_opt: Option<&DISPLAYCONFIG_MODE_INFO> = None;
+
+    for mode in modes {
+        if mode.infoType == DISPLAYCONFIG_MODE_INFO_TYPE_TARGET
+            && mode.adapterId == target_adapter_id
+            && mode.id == target_id
+        {
+            target_mode_opt = Some(mode);
+        }
+        if mode.infoType == DISPLAYCONFIG_MODE_INFO_TYPE_SOURCE
+            && mode.adapterId == source_adapter_id
+            && mode.id == source_id
+        {
+            source_mode_opt = Some(mode);
+        }
+
+        if target_mode_opt.is_some() && source_mode_opt.is_some() {
+            break;
+        }
+    }
+
+    [target_mode_opt, source_mode_opt]
+}
+
+fn get_additional_info<B: DisplayBackend + ?Sized>(
+    backend: &B,
+    path: &DISPLAYCONFIG_PATH_INFO,
+    global_config: &GlobalConfig,
+) -> Result<(
+    DISPLAYCONFIG_TARGET_DEVICE_NAME,
+    DISPLAYCONFIG_ADAPTER_NAME,
+    OptionalInfo,
+)> {
+    let target = &path.targetInfo;
+    let source = &path.sourceInfo;
+
+    let target_name = backend.get_target_name(target.adapterId, target.id)?;
+    let adapter_name = backend.get_adapter_name(target.adapterId, target.id)?;
+
+    // Only get DPI info if it's enabled in config
+    let dpi_scale = if global_config.save_dpi_scale {
+        backend
+            .get_display_dpi_info(source.adapterId, source.id)
+            .ok()
+            .map(|dpi_info| dpi_info.current)
+    } else {
+        None
+    };
+
+    // Only get HDR info if it's enabled in config
+    let (hdr_supported, hdr_enabled) = if global_config.save_hdr_state {
+        match backend.get_display_hdr_info(target.adapterId, target.id) {
+            Ok(hdr_info) => (
+                Some(hdr_info.advanced_color_supported),
+                Some(hdr_info.advanced_color_enabled),
+            ),
+            Err(_) => (None, None),
+        }
+    } else {
+        (None, None)
+    };
+
+    // Only get SDR white level if HDR is enabled and the feature is enabled in config
+    let sdr_white_level = if global_config.save_sdr_white_level && hdr_enabled == Some(true) {
+        backend
+            .get_display_sdr_white_level(target.adapterId, target.id)
+            .ok()
+    } else {
+        None
+    };
+
+    let settings = OptionalInfo {
+        dpiScale: dpi_scale,
+        hdrSupported: hdr_supported,
+        hdrEnabled: hdr_enabled,
+        sdrWhiteLevel: sdr_white_level,
+    };
+
+    Ok((target_name, adapter_name, settings))
+}
+
+/// Renumbers the mode indices of the paths to match the order [`DisplayBackend::apply_topology`]
+/// sends the modes in: [target 0, source 0, target 1, source 1, ...].
+pub fn renumber_mode_indices(display_layout: &mut DisplayLayout) {
+    for (i, display) in display_layout.displays.iter_mut().enumerate() {
+        display.pathInfo.targetInfo.Anonymous.modeInfoIdx = (i * 2) as u32;
+        display.pathInfo.sourceInfo.Anonymous.modeInfoIdx = (i * 2 + 1) as u32;
+    }
+}
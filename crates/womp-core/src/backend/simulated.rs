@@ -0,0 +1,754 @@
+#![allow(non_snake_case)]
+
+use crate::backend::{DPI_VALS, DisplayBackend, DpiScalingInfo, HdrInfo, WALLPAPER_POSITIONS};
+use crate::error::{Result, WompError};
+use crate::serde_types::{Display, DisplayLayout, WallpaperInfo};
+use crate::win32_types::*;
+
+// 8 = DISPLAYCONFIG_PATH_SUPPORT_VIRTUAL_MODE
+const PATH_SUPPORT_VIRTUAL_MODE: u32 = 8;
+
+struct SimulatedDisplay {
+    display: Display,
+    active: bool,
+    recommended_dpi: u32,
+    edid: Option<Vec<u8>>,
+    /// The refresh rate the simulated driver picks whatever it's asked for.
+    forced_refresh_rate: Option<DISPLAYCONFIG_RATIONAL>,
+}
+
+/// An in-memory display topology that behaves like the CCD API closely enough to
+/// exercise WOMP's capture and apply logic without a Windows desktop.
+///
+/// Build one from a captured `displays.json` with [`SimulatedBackend::from_layout`],
+/// apply profiles to it and inspect the result with
+/// [`DisplayBackend::get_display_layout`].
+pub struct SimulatedBackend {
+    displays: Vec<SimulatedDisplay>,
+    icon_size: i32,
+    wallpaper_info: WallpaperInfo,
+    audio_output: String,
+    displays_off: bool,
+    applied_flags: Vec<SET_DISPLAY_CONFIG_FLAGS>,
+}
+
+impl Default for SimulatedBackend {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl SimulatedBackend {
+    pub fn new() -> Self {
+        SimulatedBackend {
+            displays: vec![],
+            icon_size: 48,
+            wallpaper_info: WallpaperInfo {
+                wallpaperPath: String::new(),
+                wallpaperPosition: "fill".to_string(),
+            },
+            audio_output: String::new(),
+            displays_off: false,
+            applied_flags: vec![],
+        }
+    }
+
+    /// Creates a backend whose connected and active monitors are the displays of `layout`.
+    ///
+    /// Optional info and global info present in the layout become the simulated
+    /// DPI/HDR/SDR state and desktop extras.
+    pub fn from_layout(layout: &DisplayLayout) -> Self {
+        let mut backend = Self::new();
+        for display in &layout.displays {
+            backend.add_display(display.clone(), true);
+        }
+        if let Some(icon_size) = layout.globalInfo.iconSize {
+            backend.icon_size = icon_size;
+        }
+        if let Some(wallpaper_info) = &layout.globalInfo.wallpaperInfo {
+            backend.wallpaper_info = wallpaper_info.clone();
+        }
+        if let Some(audio_output) = &layout.globalInfo.audioOutput {
+            backend.audio_output = audio_output.clone();
+        }
+        backend
+    }
+
+    /// Connects a monitor. Inactive monitors are known to the backend but not reported
+    /// by [`DisplayBackend::query_display_config`] until a layout enables them.
+    pub fn add_display(&mut self, display: Display, active: bool) {
+        let recommended_dpi = display.optionalInfo.dpiScale.unwrap_or(100);
+        self.displays.push(SimulatedDisplay {
+            display,
+            active,
+            recommended_dpi,
+            edid: None,
+            forced_refresh_rate: None,
+        });
+    }
+
+    /// Disconnects every monitor with the given target id.
+    pub fn remove_display(&mut self, target_id: u32) {
+        self.displays
+            .retain(|d| d.display.pathInfo.targetInfo.id != target_id);
+    }
+
+    /// Gives every monitor with the given target id the raw EDID `edid`.
+    pub fn set_edid(&mut self, target_id: u32, edid: Vec<u8>) {
+        for d in &mut self.displays {
+            if d.display.pathInfo.targetInfo.id == target_id {
+                d.edid = Some(edid.clone());
+            }
+        }
+    }
+
+    /// Makes every monitor with the given target id end up at `refresh_rate` whenever it's
+    /// set, like a driver that silently picks another mode than the one it was given.
+    pub fn force_refresh_rate(&mut self, target_id: u32, refresh_rate: DISPLAYCONFIG_RATIONAL) {
+        for d in &mut self.displays {
+            if d.display.pathInfo.targetInfo.id == target_id {
+                d.forced_refresh_rate = Some(refresh_rate);
+            }
+        }
+    }
+
+    /// Changes the adapter LUID of every monitor, like a reboot does.
+    pub fn set_adapter_id(&mut self, adapter_id: LUID) {
+        for d in &mut self.displays {
+            let display = &mut d.display;
+            display.pathInfo.sourceInfo.adapterId = adapter_id;
+            display.pathInfo.targetInfo.adapterId = adapter_id;
+            display.modeInfo.sourceModeInfo.adapterId = adapter_id;
+            display.modeInfo.targetModeInfo.adapterId = adapter_id;
+            display.additionalInfo.adapter.header.adapterId = adapter_id;
+            display.additionalInfo.target.header.adapterId = adapter_id;
+        }
+    }
+
+    /// The flags of every successful, non-validating `set_display_config` call.
+    pub fn applied_flags(&self) -> &[SET_DISPLAY_CONFIG_FLAGS] {
+        &self.applied_flags
+    }
+
+    pub fn displays_off(&self) -> bool {
+        self.displays_off
+    }
+
+    fn find(&self, adapter_id: LUID, target_id: u32) -> Result<&SimulatedDisplay> {
+        self.displays
+            .iter()
+            .find(|d| {
+                d.display.pathInfo.targetInfo.adapterId == adapter_id
+                    && d.display.pathInfo.targetInfo.id == target_id
+            })
+            .ok_or_else(|| {
+                WompError::Backend(format!("No simulated display with target id {target_id}"))
+            })
+    }
+
+    fn find_mut(&mut self, adapter_id: LUID, target_id: u32) -> Result<&mut SimulatedDisplay> {
+        self.displays
+            .iter_mut()
+            .find(|d| {
+                d.display.pathInfo.targetInfo.adapterId == adapter_id
+                    && d.display.pathInfo.targetInfo.id == target_id
+            })
+            .ok_or_else(|| {
+                WompError::Backend(format!("No simulated display with target id {target_id}"))
+            })
+    }
+
+    fn find_source(&self, adapter_id: LUID, source_id: u32) -> Result<&SimulatedDisplay> {
+        self.displays
+            .iter()
+            .find(|d| {
+                d.active
+                    && d.display.pathInfo.sourceInfo.adapterId == adapter_id
+                    && d.display.pathInfo.sourceInfo.id == source_id
+            })
+            .ok_or_else(|| {
+                WompError::Backend(format!(
+                    "No active simulated display with source id {source_id}"
+                ))
+            })
+    }
+
+    fn find_source_mut(
+        &mut self,
+        adapter_id: LUID,
+        source_id: u32,
+    ) -> Result<&mut SimulatedDisplay> {
+        self.displays
+            .iter_mut()
+            .find(|d| {
+                d.active
+                    && d.display.pathInfo.sourceInfo.adapterId == adapter_id
+                    && d.display.pathInfo.sourceInfo.id == source_id
+            })
+            .ok_or_else(|| {
+                WompError::Backend(format!(
+                    "No active simulated display with source id {source_id}"
+                ))
+            })
+    }
+}
+
+fn mode_at(
+    modes: &[DISPLAYCONFIG_MODE_INFO],
+    status_flags: u32,
+    idx: u32,
+    info_type: DISPLAYCONFIG_MODE_INFO_TYPE,
+) -> std::result::Result<Option<DISPLAYCONFIG_MODE_INFO>, String> {
+    // An invalid index lets the system pick the mode
+    if status_flags & PATH_SUPPORT_VIRTUAL_MODE != 0 || idx == DISPLAYCONFIG_PATH_MODE_IDX_INVALID {
+        return Ok(None);
+    }
+    match modes.get(idx as usize) {
+        Some(mode) if mode.infoType == info_type => Ok(Some(*mode)),
+        Some(_) => Err(format!("Mode {idx} has the wrong type")),
+        None => Err(format!("Mode index {idx} is out of range")),
+    }
+}
+
+impl DisplayBackend for SimulatedBackend {
+    fn query_display_config(
+        &mut self,
+    ) -> Result<(Vec<DISPLAYCONFIG_PATH_INFO>, Vec<DISPLAYCONFIG_MODE_INFO>)> {
+        let mut paths = vec![];
+        let mut modes = vec![];
+
+        for d in self.displays.iter().filter(|d| d.active) {
+            let mut path = d.display.pathInfo;
+            path.targetInfo.Anonymous.modeInfoIdx = modes.len() as u32;
+            modes.push(d.display.modeInfo.targetModeInfo);
+            path.sourceInfo.Anonymous.modeInfoIdx = modes.len() as u32;
+            modes.push(d.display.modeInfo.sourceModeInfo);
+            paths.push(path);
+        }
+
+        Ok((paths, modes))
+    }
+
+    fn query_all_paths(
+        &mut self,
+    ) -> Result<(Vec<DISPLAYCONFIG_PATH_INFO>, Vec<DISPLAYCONFIG_MODE_INFO>)> {
+        let (mut paths, modes) = self.query_display_config()?;
+
+        // Unlike the real thing only one path per monitor, with the source it had last
+        for d in self.displays.iter().filter(|d| !d.active) {
+            let mut path = d.display.pathInfo;
+            path.flags &= !DISPLAYCONFIG_PATH_ACTIVE;
+            path.sourceInfo.Anonymous.modeInfoIdx = DISPLAYCONFIG_PATH_MODE_IDX_INVALID;
+            path.targetInfo.Anonymous.modeInfoIdx = DISPLAYCONFIG_PATH_MODE_IDX_INVALID;
+            paths.push(path);
+        }
+
+        Ok((paths, modes))
+    }
+
+    fn get_target_name(
+        &self,
+        adapter_id: LUID,
+        target_id: u32,
+    ) -> Result<DISPLAYCONFIG_TARGET_DEVICE_NAME> {
+        Ok(self
+            .find(adapter_id, target_id)?
+            .display
+            .additionalInfo
+            .target)
+    }
+
+    fn get_adapter_name(
+        &self,
+        adapter_id: LUID,
+        target_id: u32,
+    ) -> Result<DISPLAYCONFIG_ADAPTER_NAME> {
+        Ok(self
+            .find(adapter_id, target_id)?
+            .display
+            .additionalInfo
+            .adapter)
+    }
+
+    fn set_display_config(
+        &mut self,
+        paths: &[DISPLAYCONFIG_PATH_INFO],
+        modes: &[DISPLAYCONFIG_MODE_INFO],
+        flags: SET_DISPLAY_CONFIG_FLAGS,
+    ) -> Result<()> {
+        let mut updates = vec![];
+
+        for path in paths {
+            let target = &path.targetInfo;
+            let source = &path.sourceInfo;
+            let idx = self
+                .displays
+                .iter()
+                .position(|d| {
+                    d.display.pathInfo.targetInfo.adapterId == target.adapterId
+                        && d.display.pathInfo.targetInfo.id == target.id
+                })
+                .ok_or_else(|| {
+                    WompError::Backend(format!(
+                        "ERROR_INVALID_PARAMETER: unknown target id {}",
+                        target.id
+                    ))
+                })?;
+
+            let (target_mode, source_mode) = unsafe {
+                (
+                    mode_at(
+                        modes,
+                        target.statusFlags,
+                        target.Anonymous.modeInfoIdx,
+                        DISPLAYCONFIG_MODE_INFO_TYPE_TARGET,
+                    ),
+                    mode_at(
+                        modes,
+                        source.statusFlags,
+                        source.Anonymous.modeInfoIdx,
+                        DISPLAYCONFIG_MODE_INFO_TYPE_SOURCE,
+                    ),
+                )
+            };
+            let target_mode = target_mode
+                .map_err(|e| WompError::Backend(format!("ERROR_INVALID_PARAMETER: {e}")))?;
+            let source_mode = source_mode
+                .map_err(|e| WompError::Backend(format!("ERROR_INVALID_PARAMETER: {e}")))?;
+
+            updates.push((idx, *path, target_mode, source_mode));
+        }
+
+        if flags.0 & SDC_VALIDATE.0 != 0 {
+            return Ok(());
+        }
+
+        for d in &mut self.displays {
+            d.active = false;
+        }
+        for (idx, path, target_mode, source_mode) in updates {
+            let d = &mut self.displays[idx];
+            d.active = true;
+            d.display.pathInfo = path;
+            if let Some(target_mode) = target_mode {
+                d.display.modeInfo.targetModeInfo = target_mode;
+            }
+            if let Some(source_mode) = source_mode {
+                d.display.modeInfo.sourceModeInfo = source_mode;
+            }
+            if let Some(refresh_rate) = d.forced_refresh_rate {
+                d.display.pathInfo.targetInfo.refreshRate = refresh_rate;
+                let mode = &mut d.display.modeInfo.targetModeInfo.Anonymous;
+                mode.targetMode.targetVideoSignalInfo.vSyncFreq = refresh_rate;
+            }
+        }
+        self.displays_off = false;
+        self.applied_flags.push(flags);
+
+        Ok(())
+    }
+
+    fn turn_off_all_displays(&mut self) -> Result<()> {
+        self.displays_off = true;
+        Ok(())
+    }
+
+    fn get_display_dpi_info(&self, adapter_id: LUID, source_id: u32) -> Result<DpiScalingInfo> {
+        let d = self.find_source(adapter_id, source_id)?;
+        Ok(DpiScalingInfo {
+            minimum: 100,
+            maximum: DPI_VALS[DPI_VALS.len() - 1],
+            current: d.display.optionalInfo.dpiScale.unwrap_or(d.recommended_dpi),
+            recommended: d.recommended_dpi,
+        })
+    }
+
+    fn set_display_dpi(
+        &mut self,
+        adapter_id: LUID,
+        source_id: u32,
+        dpi_percent: u32,
+    ) -> Result<()> {
+        if !DPI_VALS.contains(&dpi_percent) {
+            return Err(WompError::Validation(format!(
+                "Could not find DPI value {dpi_percent} in supported values"
+            )));
+        }
+        let d = self.find_source_mut(adapter_id, source_id)?;
+        d.display.optionalInfo.dpiScale = Some(dpi_percent);
+        Ok(())
+    }
+
+    fn get_display_hdr_info(&self, adapter_id: LUID, target_id: u32) -> Result<HdrInfo> {
+        let info = self.find(adapter_id, target_id)?.display.optionalInfo;
+        Ok(HdrInfo {
+            advanced_color_supported: info.hdrSupported.unwrap_or(false),
+            advanced_color_enabled: info.hdrEnabled.unwrap_or(false),
+            wide_color_enforced: false,
+            advanced_color_force_disabled: false,
+            color_encoding: 0,
+            bits_per_color_channel: 8,
+        })
+    }
+
+    fn set_display_hdr(&mut self, adapter_id: LUID, target_id: u32, enable: bool) -> Result<()> {
+        let d = self.find_mut(adapter_id, target_id)?;
+        if !d.display.optionalInfo.hdrSupported.unwrap_or(false) {
+            return Err(WompError::Backend(
+                "HDR is not supported on this display".to_string(),
+            ));
+        }
+        d.display.optionalInfo.hdrEnabled = Some(enable);
+        Ok(())
+    }
+
+    fn get_display_sdr_white_level(&self, adapter_id: LUID, target_id: u32) -> Result<u32> {
+        let info = self.find(adapter_id, target_id)?.display.optionalInfo;
+        if !info.hdrSupported.unwrap_or(false) || !info.hdrEnabled.unwrap_or(false) {
+            return Err(WompError::Backend(
+                "HDR is not supported or not enabled on this display".to_string(),
+            ));
+        }
+        Ok(info.sdrWhiteLevel.unwrap_or(80))
+    }
+
+    fn set_display_sdr_white_level(
+        &mut self,
+        adapter_id: LUID,
+        target_id: u32,
+        nits: u32,
+    ) -> Result<()> {
+        let d = self.find_mut(adapter_id, target_id)?;
+        let info = &mut d.display.optionalInfo;
+        if !info.hdrSupported.unwrap_or(false) || !info.hdrEnabled.unwrap_or(false) {
+            return Err(WompError::Backend(
+                "HDR is not supported or not enabled on this display".to_string(),
+            ));
+        }
+        if !(80..=480).contains(&nits) {
+            return Err(WompError::Validation(format!(
+                "Invalid nits value {nits}. Value must be between 80 and 480."
+            )));
+        }
+        info.sdrWhiteLevel = Some(nits.next_multiple_of(4));
+        Ok(())
+    }
+
+    fn get_desktop_icon_size(&self) -> Result<i32> {
+        Ok(self.icon_size)
+    }
+
+    fn set_desktop_icon_size(&mut self, icon_size: i32) -> Result<()> {
+        self.icon_size = icon_size;
+        Ok(())
+    }
+
+    fn get_wallpaper_info(&self) -> Result<WallpaperInfo> {
+        Ok(self.wallpaper_info.clone())
+    }
+
+    fn set_wallpaper_info(&mut self, wallpaper_info: &WallpaperInfo) -> Result<()> {
+        let position = wallpaper_info.wallpaperPosition.to_lowercase();
+        if !WALLPAPER_POSITIONS.contains(&position.as_str()) {
+            return Err(WompError::Validation(format!(
+                "Invalid position value: {}. Must be one of: {}",
+                wallpaper_info.wallpaperPosition,
+                WALLPAPER_POSITIONS.join(", ")
+            )));
+        }
+        self.wallpaper_info = wallpaper_info.clone();
+        Ok(())
+    }
+
+    fn get_default_audio_output(&self) -> Result<String> {
+        Ok(self.audio_output.clone())
+    }
+
+    fn set_default_audio_output(&mut self, device_id: &str) -> Result<()> {
+        self.audio_output = device_id.to_string();
+        Ok(())
+    }
+
+    fn get_edid(&self, adapter_id: LUID, target_id: u32) -> Result<Option<Vec<u8>>> {
+        Ok(self.find(adapter_id, target_id)?.edid.clone())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::comparison::layouts_match;
+    use crate::error::AfterFailure;
+    use crate::layout_spec::LayoutSpec;
+    use crate::profile_store::{MemoryProfileStore, ProfileStore};
+    use crate::serde_types::config::{Config, OnFailure, Run, RunCommand};
+    use crate::serde_types::global_config::GlobalConfig;
+    use crate::testing::{layout, monitor};
+    use crate::{
+        apply_display_layout_unconfirmed_with, apply_display_layout_with,
+        save_current_display_layout_with,
+    };
+    use std::path::Path;
+
+    fn config() -> GlobalConfig {
+        // The simulated displays are set right away, there's nothing to wait for
+        GlobalConfig {
+            settle_timeout_ms: 0,
+            ..GlobalConfig::new()
+        }
+    }
+
+    fn current(backend: &mut SimulatedBackend) -> DisplayLayout {
+        backend
+            .get_display_layout(&GlobalConfig::default())
+            .unwrap()
+    }
+
+    /// A backend with two monitors side by side, saved as `desk`, and a `swapped`
+    /// profile that puts the second one on the left and runs the first one at 144 Hz.
+    fn desk() -> (SimulatedBackend, MemoryProfileStore) {
+        let mut backend =
+            SimulatedBackend::from_layout(&layout(&[monitor(1, 0, 60), monitor(2, 1920, 60)]));
+        let mut store = MemoryProfileStore::new();
+        save_current_display_layout_with(&mut backend, &mut store, "desk", &config()).unwrap();
+        let swapped = layout(&[monitor(1, 1920, 144), monitor(2, 0, 60)]);
+        store.save_layout("swapped", &swapped).unwrap();
+        (backend, store)
+    }
+
+    #[test]
+    fn two_monitors_round_trip() {
+        let (mut backend, store) = desk();
+
+        apply_display_layout_with(&mut backend, &store, "swapped", &config()).unwrap();
+        let swapped = store.load_layout("swapped").unwrap();
+        assert!(layouts_match(&swapped, &current(&mut backend), false));
+
+        let report = apply_display_layout_with(&mut backend, &store, "desk", &config()).unwrap();
+        assert!(!report.failed());
+        let desk = store.load_layout("desk").unwrap();
+        assert_eq!(desk.displays.len(), 2);
+        assert!(layouts_match(&desk, &current(&mut backend), false));
+    }
+
+    #[test]
+    fn applies_after_the_adapter_luid_changed() {
+        let (mut backend, store) = desk();
+        apply_display_layout_with(&mut backend, &store, "swapped", &config()).unwrap();
+
+        // Like after a reboot
+        let rebooted = LUID {
+            LowPart: 0x5678,
+            HighPart: 0,
+        };
+        backend.set_adapter_id(rebooted);
+        apply_display_layout_with(&mut backend, &store, "desk", &config()).unwrap();
+
+        let current = current(&mut backend);
+        assert!(layouts_match(
+            &store.load_layout("desk").unwrap(),
+            &current,
+            false
+        ));
+        assert!(
+            current
+                .displays
+                .iter()
+                .all(|d| d.pathInfo.targetInfo.adapterId == rebooted)
+        );
+    }
+
+    #[test]
+    fn rolls_back_when_a_display_was_removed() {
+        let (mut backend, store) = desk();
+        apply_display_layout_with(&mut backend, &store, "swapped", &config()).unwrap();
+        let before = current(&mut backend);
+
+        backend.remove_display(2);
+        let Err(WompError::ApplyFailed {
+            report,
+            snapshot: None,
+        }) = apply_display_layout_with(&mut backend, &store, "desk", &config())
+        else {
+            panic!("applying a profile with a missing monitor succeeded");
+        };
+        assert!(matches!(report.after, Some(AfterFailure::RolledBack)));
+
+        // The monitor that's left is as it was
+        let current = current(&mut backend);
+        assert_eq!(current.displays.len(), 1);
+        assert!(layouts_match(&current, &before, true));
+    }
+
+    #[test]
+    fn keeps_the_snapshot_when_applying_fails_halfway() {
+        let (mut backend, mut store) = desk();
+        let before = current(&mut backend);
+        let mut swapped = store.load_layout("swapped").unwrap();
+        // Saved on a monitor with HDR, this one doesn't have it
+        swapped.displays[0].optionalInfo.hdrSupported = Some(true);
+        swapped.displays[0].optionalInfo.hdrEnabled = Some(true);
+        store.save_layout("swapped", &swapped).unwrap();
+        let best_effort = Config {
+            on_failure: Some(OnFailure::BestEffort),
+            ..Config::default()
+        };
+        store.save_config("swapped", &best_effort).unwrap();
+        let config = GlobalConfig {
+            save_hdr_state: true,
+            ..config()
+        };
+
+        let Err(WompError::ApplyFailed {
+            report,
+            snapshot: Some(snapshot),
+        }) = apply_display_layout_unconfirmed_with(&mut backend, &store, "swapped", &config)
+        else {
+            panic!("applying HDR to a monitor without it didn't keep the snapshot");
+        };
+        assert!(matches!(report.after, Some(AfterFailure::KeptGoing)));
+        assert!(layouts_match(&swapped, &current(&mut backend), false));
+
+        // The new displays can still be reverted
+        snapshot.revert_with(&mut backend).unwrap();
+        assert!(layouts_match(&before, &current(&mut backend), false));
+    }
+
+    #[test]
+    fn saves_the_displays_only_once_confirmed() {
+        let (mut backend, store) = desk();
+        let saved = |backend: &SimulatedBackend| {
+            let flags = backend.applied_flags().last().unwrap();
+            flags.0 & SDC_SAVE_TO_DATABASE.0 != 0
+        };
+
+        let (_, snapshot) =
+            apply_display_layout_unconfirmed_with(&mut backend, &store, "swapped", &config())
+                .unwrap();
+        assert!(!saved(&backend));
+
+        snapshot.confirm_with(&mut backend).unwrap();
+        assert!(saved(&backend));
+        let swapped = store.load_layout("swapped").unwrap();
+        assert!(layouts_match(&swapped, &current(&mut backend), false));
+
+        apply_display_layout_with(&mut backend, &store, "desk", &config()).unwrap();
+        assert!(saved(&backend));
+    }
+
+    /// Makes the driver keep the first monitor at 60 Hz whatever it's set to.
+    fn stuck_at_60_hz(backend: &mut SimulatedBackend) {
+        let rate = DISPLAYCONFIG_RATIONAL {
+            Numerator: 60,
+            Denominator: 1,
+        };
+        backend.force_refresh_rate(1, rate);
+    }
+
+    #[test]
+    fn rolls_back_when_the_displays_still_differ() {
+        let (mut backend, store) = desk();
+        let before = current(&mut backend);
+        stuck_at_60_hz(&mut backend);
+
+        let Err(WompError::ApplyFailed {
+            report,
+            snapshot: None,
+        }) = apply_display_layout_with(&mut backend, &store, "swapped", &config())
+        else {
+            panic!("applying succeeded although the refresh rate didn't stick");
+        };
+        let verification = report.verification.as_ref().unwrap();
+        assert!(verification.retried_with.is_some());
+        assert_eq!(verification.differences.len(), 1);
+        assert_eq!(report.failures().count(), 1);
+        assert!(matches!(report.after, Some(AfterFailure::RolledBack)));
+        assert!(layouts_match(&before, &current(&mut backend), false));
+    }
+
+    /// A `layout.toml` that sets the displays like the `swapped` profile.
+    fn by_hand(store: &mut MemoryProfileStore) {
+        let spec = LayoutSpec::parse(
+            Path::new("layout.toml"),
+            r#"
+            [[monitor]]
+            name = "Monitor 1"
+            position = { x = 1920, y = 0 }
+            refresh_rate = 144
+
+            [[monitor]]
+            name = "Monitor 2"
+            position = { x = 0, y = 0 }
+            "#,
+        )
+        .unwrap();
+        store.save_layout_spec("by hand", &spec).unwrap();
+    }
+
+    #[test]
+    fn checks_the_displays_after_applying_a_layout_toml() {
+        let (mut backend, mut store) = desk();
+        by_hand(&mut store);
+
+        let report = apply_display_layout_with(&mut backend, &store, "by hand", &config()).unwrap();
+        let verification = report.verification.unwrap();
+        assert!(verification.is_match(), "{verification}");
+        let swapped = store.load_layout("swapped").unwrap();
+        assert!(layouts_match(&swapped, &current(&mut backend), false));
+    }
+
+    #[test]
+    fn layout_toml_that_didnt_stick_fails() {
+        let (mut backend, mut store) = desk();
+        by_hand(&mut store);
+        let best_effort = Config {
+            on_failure: Some(OnFailure::BestEffort),
+            ..Config::default()
+        };
+        store.save_config("by hand", &best_effort).unwrap();
+        stuck_at_60_hz(&mut backend);
+
+        let Err(WompError::ApplyFailed {
+            report,
+            snapshot: Some(_),
+        }) = apply_display_layout_unconfirmed_with(&mut backend, &store, "by hand", &config())
+        else {
+            panic!("applying succeeded although the refresh rate didn't stick");
+        };
+        let differences = &report.verification.as_ref().unwrap().differences;
+        assert_eq!(
+            differences[0].to_string(),
+            "Monitor 1: refresh rate is 144.00 Hz in the profile, 60.00 Hz now"
+        );
+        assert!(matches!(report.after, Some(AfterFailure::KeptGoing)));
+    }
+
+    #[test]
+    fn before_hook_only_runs_for_a_layout_that_can_be_applied() {
+        let (mut backend, mut store) = desk();
+        let marker = std::env::temp_dir().join(format!("womp-before-{}", std::process::id()));
+        let _ = std::fs::remove_file(&marker);
+        let with_hook = Config {
+            run: Run {
+                before: RunCommand {
+                    target: "echo".to_string(),
+                    args: format!("ran > \"{}\"", marker.display()),
+                },
+                ..Run::default()
+            },
+            ..Config::default()
+        };
+        store.save_layout("nothing", &layout(&[])).unwrap();
+        store.save_config("nothing", &with_hook).unwrap();
+        store.save_config("swapped", &with_hook).unwrap();
+
+        assert!(apply_display_layout_with(&mut backend, &store, "nothing", &config()).is_err());
+        assert!(!marker.exists());
+
+        let report = apply_display_layout_with(&mut backend, &store, "swapped", &config()).unwrap();
+        assert_eq!(report.hooks.len(), 1);
+        assert_eq!(report.hooks[0].stage, "before");
+        assert!(marker.exists());
+        std::fs::remove_file(&marker).unwrap();
+    }
+}
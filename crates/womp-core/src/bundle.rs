@@ -0,0 +1,461 @@
+//! Single-file `.womp` profile bundles.
+//!
+//! A bundle is a zip archive holding a profile's `profile.toml`, `displays.json` and
+//! `layout.toml` as they are on disk, the wallpaper and icon files they reference under
+//! `assets/`, and a `manifest.json` describing where the bundle came from.
+
+use crate::error::{Result, WompError};
+use crate::profile_store::filesystem::{CONFIG_FILE_NAME, LAYOUT_FILE_NAME, LAYOUT_SPEC_FILE_NAME};
+use crate::profile_store::{FsProfileStore, ProfileStore, clone_name};
+use log::{debug, info};
+use serde::{Deserialize, Serialize};
+use std::fs::{self, File};
+use std::io::{Read, Write};
+use std::path::{Path, PathBuf};
+use zip::result::ZipError;
+use zip::write::SimpleFileOptions;
+use zip::{CompressionMethod, ZipArchive, ZipWriter};
+
+/// Format version of bundles written by this build.
+pub const BUNDLE_VERSION: u32 = 1;
+/// File extension of bundles, without the dot.
+pub const BUNDLE_EXTENSION: &str = "womp";
+
+const MANIFEST_FILE_NAME: &str = "manifest.json";
+const ASSETS_DIR: &str = "assets";
+const PROFILE_FILES: [&str; 3] = [CONFIG_FILE_NAME, LAYOUT_FILE_NAME, LAYOUT_SPEC_FILE_NAME];
+
+/// `manifest.json` of a bundle.
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct BundleManifest {
+    pub version: u32,
+    /// Name of the profile on the machine it was exported from.
+    pub profile: String,
+    /// Version of WOMP that wrote the bundle.
+    pub womp_version: String,
+    /// RFC 3339 timestamp of the export.
+    pub exported_at: String,
+    /// Computer name of the machine the profile was exported from, if known.
+    #[serde(default, skip_serializing_if = "String::is_empty")]
+    pub source_machine: String,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub assets: Vec<BundleAsset>,
+}
+
+/// A file a profile references that was packed into the bundle.
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct BundleAsset {
+    pub kind: AssetKind,
+    /// Path inside the bundle, e.g. `assets/wallpaper/beach.jpg`.
+    pub file: String,
+    /// Where the file was on the machine the profile was exported from.
+    pub original_path: String,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum AssetKind {
+    /// `globalInfo.wallpaperInfo.wallpaperPath` of `displays.json`
+    Wallpaper,
+    /// `icon` of `profile.toml`, if it's a file rather than a glyph
+    Icon,
+}
+
+impl AssetKind {
+    fn dir_name(self) -> &'static str {
+        match self {
+            AssetKind::Wallpaper => "wallpaper",
+            AssetKind::Icon => "icon",
+        }
+    }
+}
+
+fn source_machine() -> String {
+    std::env::var("COMPUTERNAME")
+        .or_else(|_| std::env::var("HOSTNAME"))
+        .unwrap_or_default()
+}
+
+// I/O errors keep their kind, everything else means the archive is broken
+fn zip_error(path: &Path, e: ZipError) -> WompError {
+    match e {
+        ZipError::Io(e) => WompError::io(path, e),
+        e => WompError::parse(path, e),
+    }
+}
+
+/// Files referenced by the profile that exist on this machine. Relative paths are
+/// relative to the profile directory, an icon like `Monitor` is a glyph, not a file.
+fn referenced_assets(
+    store: &FsProfileStore,
+    name: &str,
+    profile_dir: &Path,
+) -> Vec<(AssetKind, PathBuf)> {
+    let mut assets = vec![];
+    let wallpaper = store
+        .load_layout(name)
+        .ok()
+        .and_then(|layout| layout.globalInfo.wallpaperInfo)
+        .map(|info| info.wallpaperPath);
+    let icon = store.load_config(name).ok().map(|config| config.icon);
+    for (kind, path) in [(AssetKind::Wallpaper, wallpaper), (AssetKind::Icon, icon)] {
+        let path = path.filter(|p| !p.is_empty()).map(|p| profile_dir.join(p));
+        if let Some(path) = path.filter(|p| p.is_file()) {
+            assets.push((kind, path));
+        }
+    }
+    assets
+}
+
+/// Packs the profile `name` into a bundle at `destination`, overwriting it if it exists.
+pub fn export_profile(
+    store: &FsProfileStore,
+    name: &str,
+    destination: &Path,
+) -> Result<BundleManifest> {
+    // Bundles always contain the current file formats
+    store.migrate_profile(name)?;
+    let profile_dir = store.existing_profile_dir(name)?;
+
+    let file = File::create(destination).map_err(|e| WompError::io(destination, e))?;
+    let mut zip = ZipWriter::new(file);
+    let options = SimpleFileOptions::default().compression_method(CompressionMethod::Deflated);
+    let mut add_file = |zip_path: &str, source: &Path| -> Result<()> {
+        let contents = fs::read(source).map_err(|e| WompError::io(source, e))?;
+        zip.start_file(zip_path, options)
+            .map_err(|e| zip_error(destination, e))?;
+        zip.write_all(&contents)
+            .map_err(|e| WompError::io(destination, e))?;
+        debug!(file = zip_path, source:% = source.display(); "Added file to bundle");
+        Ok(())
+    };
+
+    for file_name in PROFILE_FILES {
+        let path = profile_dir.join(file_name);
+        if path.is_file() {
+            add_file(file_name, &path)?;
+        }
+    }
+
+    let mut assets = vec![];
+    for (kind, path) in referenced_assets(store, name, &profile_dir) {
+        let Some(file_name) = path.file_name() else {
+            continue;
+        };
+        let zip_path = format!(
+            "{ASSETS_DIR}/{}/{}",
+            kind.dir_name(),
+            file_name.to_string_lossy()
+        );
+        add_file(&zip_path, &path)?;
+        assets.push(BundleAsset {
+            kind,
+            file: zip_path,
+            original_path: path.to_string_lossy().to_string(),
+        });
+    }
+
+    let manifest = BundleManifest {
+        version: BUNDLE_VERSION,
+        profile: name.to_string(),
+        womp_version: env!("CARGO_PKG_VERSION").to_string(),
+        exported_at: chrono::Local::now().to_rfc3339(),
+        source_machine: source_machine(),
+        assets,
+    };
+    let manifest_json = serde_json::to_string_pretty(&manifest)
+        .map_err(|e| WompError::serialize("bundle manifest", e))?;
+    zip.start_file(MANIFEST_FILE_NAME, options)
+        .map_err(|e| zip_error(destination, e))?;
+    zip.write_all(manifest_json.as_bytes())
+        .map_err(|e| WompError::io(destination, e))?;
+    zip.finish().map_err(|e| zip_error(destination, e))?;
+
+    info!(profile = name, bundle:% = destination.display(); "Exported profile");
+    Ok(manifest)
+}
+
+fn read_entry(
+    archive: &mut ZipArchive<File>,
+    source: &Path,
+    name: &str,
+) -> Result<Option<Vec<u8>>> {
+    let mut entry = match archive.by_name(name) {
+        Ok(entry) => entry,
+        Err(ZipError::FileNotFound) => return Ok(None),
+        Err(e) => return Err(zip_error(source, e)),
+    };
+    let mut contents = vec![];
+    entry
+        .read_to_end(&mut contents)
+        .map_err(|e| WompError::io(source, e))?;
+    Ok(Some(contents))
+}
+
+/// Reads the manifest of the bundle at `source` without importing it.
+pub fn read_manifest(source: &Path) -> Result<BundleManifest> {
+    let file = File::open(source).map_err(|e| WompError::io(source, e))?;
+    let mut archive = ZipArchive::new(file).map_err(|e| zip_error(source, e))?;
+    manifest_of(&mut archive, source)
+}
+
+fn manifest_of(archive: &mut ZipArchive<File>, source: &Path) -> Result<BundleManifest> {
+    let contents = read_entry(archive, source, MANIFEST_FILE_NAME)?.ok_or_else(|| {
+        WompError::parse(
+            source,
+            format!("not a WOMP bundle, `{MANIFEST_FILE_NAME}` is missing"),
+        )
+    })?;
+    let manifest: BundleManifest =
+        serde_json::from_slice(&contents).map_err(|e| WompError::parse(source, e))?;
+    if manifest.version > BUNDLE_VERSION {
+        return Err(WompError::UnsupportedVersion {
+            path: source.to_path_buf(),
+            found: manifest.version,
+            supported: BUNDLE_VERSION,
+        });
+    }
+    Ok(manifest)
+}
+
+/// Unpacks the bundle at `source` into a new profile and returns its name. If a profile
+/// with the bundled name exists, the first free `<name>_clone[_n]` is used instead.
+pub fn import_profile(store: &mut FsProfileStore, source: &Path) -> Result<String> {
+    let file = File::open(source).map_err(|e| WompError::io(source, e))?;
+    let mut archive = ZipArchive::new(file).map_err(|e| zip_error(source, e))?;
+    let manifest = manifest_of(&mut archive, source)?;
+
+    let name = if store.exists(&manifest.profile)? {
+        clone_name(&manifest.profile, |candidate| store.exists(candidate))?
+    } else {
+        manifest.profile.clone()
+    };
+    let profile_dir = store.profile_dir(&name)?;
+    fs::create_dir_all(&profile_dir).map_err(|e| WompError::io(&profile_dir, e))?;
+
+    // Don't leave half a profile behind
+    let result = unpack(store, &mut archive, source, &manifest, &name, &profile_dir);
+    if let Err(e) = result {
+        let _ = fs::remove_dir_all(&profile_dir);
+        return Err(e);
+    }
+
+    info!(
+        profile = name.as_str(),
+        bundle:% = source.display(),
+        source_machine = manifest.source_machine.as_str();
+        "Imported profile"
+    );
+    Ok(name)
+}
+
+fn unpack(
+    store: &mut FsProfileStore,
+    archive: &mut ZipArchive<File>,
+    source: &Path,
+    manifest: &BundleManifest,
+    name: &str,
+    profile_dir: &Path,
+) -> Result<()> {
+    for file_name in PROFILE_FILES {
+        if let Some(contents) = read_entry(archive, source, file_name)? {
+            let path = profile_dir.join(file_name);
+            fs::write(&path, contents).map_err(|e| WompError::io(&path, e))?;
+        }
+    }
+
+    // Make sure what we unpacked is usable before pointing it at the assets
+    if profile_dir.join(CONFIG_FILE_NAME).is_file() {
+        store.load_config(name)?;
+    }
+    if profile_dir.join(LAYOUT_FILE_NAME).is_file() {
+        store.load_layout(name)?;
+    }
+    if profile_dir.join(LAYOUT_SPEC_FILE_NAME).is_file() {
+        store.load_layout_spec(name)?;
+    }
+
+    for asset in &manifest.assets {
+        // Only the file name is trusted, the bundle decides nothing about where things go
+        let Some(file_name) = Path::new(&asset.file).file_name() else {
+            continue;
+        };
+        let Some(contents) = read_entry(archive, source, &asset.file)? else {
+            continue;
+        };
+        let asset_dir = profile_dir.join(ASSETS_DIR).join(asset.kind.dir_name());
+        fs::create_dir_all(&asset_dir).map_err(|e| WompError::io(&asset_dir, e))?;
+        let path = asset_dir.join(file_name);
+        fs::write(&path, contents).map_err(|e| WompError::io(&path, e))?;
+        let path = path.to_string_lossy().to_string();
+
+        match asset.kind {
+            AssetKind::Wallpaper => {
+                let mut layout = store.load_layout(name)?;
+                if let Some(wallpaper_info) = &mut layout.globalInfo.wallpaperInfo {
+                    wallpaper_info.wallpaperPath = path;
+                    store.save_layout(name, &layout)?;
+                }
+            }
+            AssetKind::Icon => {
+                let mut config = store.load_config(name)?;
+                config.icon = path;
+                store.save_config(name, &config)?;
+            }
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::serde_types::{DisplayLayout, WallpaperInfo, config::Config};
+    use crate::testing::{layout, monitor};
+
+    /// An empty directory of its own for each test.
+    fn scratch_dir(name: &str) -> PathBuf {
+        let dir = std::env::temp_dir().join(format!("womp-bundle-{}-{name}", std::process::id()));
+        let _ = fs::remove_dir_all(&dir);
+        fs::create_dir_all(&dir).unwrap();
+        dir
+    }
+
+    fn with_wallpaper(path: &Path) -> DisplayLayout {
+        let mut layout = layout(&[monitor(1, 0, 60)]);
+        layout.globalInfo.wallpaperInfo = Some(WallpaperInfo {
+            wallpaperPath: path.to_string_lossy().to_string(),
+            wallpaperPosition: "fill".to_string(),
+        });
+        layout
+    }
+
+    fn manifest(version: u32, assets: Vec<BundleAsset>) -> BundleManifest {
+        BundleManifest {
+            version,
+            profile: "desk".to_string(),
+            womp_version: env!("CARGO_PKG_VERSION").to_string(),
+            exported_at: "2026-10-18T12:00:00+00:00".to_string(),
+            source_machine: String::new(),
+            assets,
+        }
+    }
+
+    /// A bundle with `manifest` and `files`, written by hand to get what export never does.
+    fn write_bundle(path: &Path, manifest: &BundleManifest, files: &[(&str, Vec<u8>)]) {
+        let mut zip = ZipWriter::new(File::create(path).unwrap());
+        let manifest = serde_json::to_vec(manifest).unwrap();
+        for (name, contents) in [(MANIFEST_FILE_NAME, manifest)].iter().chain(files) {
+            zip.start_file(*name, SimpleFileOptions::default()).unwrap();
+            zip.write_all(contents).unwrap();
+        }
+        zip.finish().unwrap();
+    }
+
+    #[test]
+    fn imported_twice_gets_a_clone_name() {
+        let dir = scratch_dir("twice");
+        let wallpaper = dir.join("beach.jpg");
+        fs::write(&wallpaper, "beach").unwrap();
+        let mut store = FsProfileStore::new(dir.join("profiles"));
+        store
+            .save_layout("desk", &with_wallpaper(&wallpaper))
+            .unwrap();
+        // A glyph, not a file
+        let config = Config {
+            icon: "Monitor".to_string(),
+            ..Config::default()
+        };
+        store.save_config("desk", &config).unwrap();
+
+        let bundle = dir.join("desk.womp");
+        let manifest = export_profile(&store, "desk", &bundle).unwrap();
+        assert_eq!(manifest.assets.len(), 1);
+        assert_eq!(manifest.assets[0].file, "assets/wallpaper/beach.jpg");
+
+        let mut other = FsProfileStore::new(dir.join("other"));
+        assert_eq!(import_profile(&mut other, &bundle).unwrap(), "desk");
+        assert_eq!(import_profile(&mut other, &bundle).unwrap(), "desk_clone");
+        assert_eq!(import_profile(&mut other, &bundle).unwrap(), "desk_clone_2");
+
+        let imported = other.load_layout("desk_clone").unwrap();
+        let wallpaper = imported.globalInfo.wallpaperInfo.unwrap().wallpaperPath;
+        let expected = other.profile_dir("desk_clone").unwrap();
+        assert_eq!(
+            Path::new(&wallpaper),
+            expected.join("assets/wallpaper/beach.jpg")
+        );
+        assert_eq!(fs::read_to_string(&wallpaper).unwrap(), "beach");
+        assert_eq!(other.load_config("desk_clone").unwrap().icon, "Monitor");
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn assets_stay_inside_the_profile() {
+        let dir = scratch_dir("traversal");
+        let asset = "../../evil.jpg";
+        let layout = serde_json::to_vec(&with_wallpaper(Path::new("C:\\evil.jpg"))).unwrap();
+        let bundle = dir.join("evil.womp");
+        let assets = vec![BundleAsset {
+            kind: AssetKind::Wallpaper,
+            file: asset.to_string(),
+            original_path: "C:\\evil.jpg".to_string(),
+        }];
+        write_bundle(
+            &bundle,
+            &manifest(BUNDLE_VERSION, assets),
+            &[(LAYOUT_FILE_NAME, layout), (asset, b"evil".to_vec())],
+        );
+
+        let mut store = FsProfileStore::new(dir.join("profiles"));
+        let name = import_profile(&mut store, &bundle).unwrap();
+        let unpacked = store
+            .profile_dir(&name)
+            .unwrap()
+            .join("assets/wallpaper/evil.jpg");
+        assert_eq!(fs::read_to_string(&unpacked).unwrap(), "evil");
+        for escaped in ["evil.jpg", "profiles/evil.jpg", "profiles/desk/evil.jpg"] {
+            assert!(!dir.join(escaped).exists(), "{escaped}");
+        }
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn newer_bundles_are_refused() {
+        let dir = scratch_dir("newer");
+        let bundle = dir.join("newer.womp");
+        write_bundle(&bundle, &manifest(BUNDLE_VERSION + 1, vec![]), &[]);
+
+        let mut store = FsProfileStore::new(dir.join("profiles"));
+        assert!(matches!(
+            import_profile(&mut store, &bundle),
+            Err(WompError::UnsupportedVersion { found, .. }) if found == BUNDLE_VERSION + 1
+        ));
+        assert!(!store.exists("desk").unwrap());
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn broken_bundle_leaves_no_profile_behind() {
+        let dir = scratch_dir("broken");
+        let bundle = dir.join("broken.womp");
+        let config = toml::to_string(&Config::default()).unwrap().into_bytes();
+        write_bundle(
+            &bundle,
+            &manifest(BUNDLE_VERSION, vec![]),
+            &[
+                (CONFIG_FILE_NAME, config),
+                (LAYOUT_FILE_NAME, b"not json".to_vec()),
+            ],
+        );
+
+        let mut store = FsProfileStore::new(dir.join("profiles"));
+        assert!(matches!(
+            import_profile(&mut store, &bundle),
+            Err(WompError::Parse { .. })
+        ));
+        assert!(!store.exists("desk").unwrap());
+        assert!(!store.profile_dir("desk").unwrap().exists());
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
@@ -0,0 +1,43 @@
+//! Canonical form of a [`DisplayLayout`], for keeping profiles in version control.
+//!
+//! Saving the same setup twice normally produces different `displays.json` files: adapter
+//! LUIDs change on every boot and `QueryDisplayConfig` returns displays and modes in
+//! whatever order it likes. The canonical form drops that noise, so the file only changes
+//! when the layout does. Fields are always written in the same order either way.
+
+use crate::backend::renumber_mode_indices;
+use crate::model::wide_to_string;
+use crate::serde_types::{Display, DisplayLayout};
+use crate::win32_types::LUID;
+
+/// Puts `layout` into canonical form:
+/// - displays are sorted by monitor device path, then target id, both stable across boots
+/// - adapter LUIDs are zeroed, they're replaced with the current ones when applying anyway
+/// - mode indices are renumbered to match the order the modes are applied in
+pub fn canonicalize(layout: &mut DisplayLayout) {
+    layout.displays.sort_by_cached_key(|d| {
+        (
+            wide_to_string(&d.additionalInfo.target.monitorDevicePath).to_lowercase(),
+            d.pathInfo.targetInfo.id,
+        )
+    });
+    layout.displays.iter_mut().for_each(clear_adapter_id);
+    renumber_mode_indices(layout);
+}
+
+/// A canonical copy of `layout`, see [`canonicalize`].
+pub fn canonical(layout: &DisplayLayout) -> DisplayLayout {
+    let mut layout = layout.clone();
+    canonicalize(&mut layout);
+    layout
+}
+
+fn clear_adapter_id(display: &mut Display) {
+    let zero = LUID::default();
+    display.pathInfo.sourceInfo.adapterId = zero;
+    display.pathInfo.targetInfo.adapterId = zero;
+    display.modeInfo.sourceModeInfo.adapterId = zero;
+    display.modeInfo.targetModeInfo.adapterId = zero;
+    display.additionalInfo.adapter.header.adapterId = zero;
+    display.additionalInfo.target.header.adapterId = zero;
+}
@@ -1,21 +1,19 @@
 #![allow(non_camel_case_types)]
 #![allow(non_snake_case)]
 
-use crate::serde_types::{
-    Display, DisplayLayout, GlobalInfo, WallpaperInfo, global_config::GlobalConfig,
-    optional_info::OptionalInfo,
-};
-use itertools::Itertools;
+use crate::backend::{DPI_VALS, DisplayBackend, DpiScalingInfo, HdrInfo};
+use crate::edid;
+use crate::error::{Result, WompError};
+use crate::model::wide_to_string;
+use crate::serde_types::WallpaperInfo;
+use log::debug;
 use std::mem::size_of;
 use windows::Win32::{
-    Devices::Display::*, Foundation::*, Media::Audio::*, System::Com::*, System::Variant::VARIANT,
-    UI::Shell::*, UI::WindowsAndMessaging::*,
+    Devices::Display::*, Foundation::*, Media::Audio::*, System::Com::*, System::Registry::*,
+    System::Variant::VARIANT, UI::Shell::*, UI::WindowsAndMessaging::*,
 };
 use windows::core::{GUID, HRESULT, HSTRING, Interface, PCWSTR};
 
-// DPI values observed from system settings
-const DPI_VALS: [u32; 12] = [100, 125, 150, 175, 200, 225, 250, 300, 350, 400, 450, 500];
-
 // Custom device info types (undocumented)
 const DISPLAYCONFIG_DEVICE_INFO_GET_DPI_SCALE: i32 = -3;
 const DISPLAYCONFIG_DEVICE_INFO_SET_DPI_SCALE: i32 = -4;
@@ -24,24 +22,6 @@ const DISPLAYCONFIG_DEVICE_INFO_SET_ADVANCED_COLOR_STATE: i32 = 10;
 const DISPLAYCONFIG_DEVICE_INFO_GET_SDR_WHITE_LEVEL: i32 = 11;
 const DISPLAYCONFIG_DEVICE_INFO_SET_SDR_WHITE_LEVEL: i32 = -18; // 0xFFFFFFEE in hex
 
-// DPI scaling info structure
-pub struct DpiScalingInfo {
-    pub minimum: u32,
-    pub maximum: u32,
-    pub current: u32,
-    pub recommended: u32,
-}
-
-// HDR/Advanced Color info structure
-pub struct HdrInfo {
-    pub advanced_color_supported: bool,
-    pub advanced_color_enabled: bool,
-    pub wide_color_enforced: bool,
-    pub advanced_color_force_disabled: bool,
-    pub color_encoding: u32,
-    pub bits_per_color_channel: i32,
-}
-
 // Custom struct for getting DPI scale info
 #[repr(C)]
 struct DisplayConfigSourceDpiScaleGet {
@@ -89,11 +69,14 @@ struct DisplayConfigSetSdrWhiteLevel {
     final_value: u8,
 }
 
+fn com_error(operation: &str, e: windows::core::Error) -> WompError {
+    WompError::com(operation, e.code().0)
+}
+
 pub struct CCDWrapper {
     flags: QUERY_DISPLAY_CONFIG_FLAGS,
     paths: Vec<DISPLAYCONFIG_PATH_INFO>,
     modes: Vec<DISPLAYCONFIG_MODE_INFO>,
-    _debug: bool,
 }
 
 // Define the UUIDs for the undocumented COM interfaces
@@ -153,7 +136,7 @@ unsafe trait IPolicyConfig: windows::core::IUnknown {
 }
 
 impl CCDWrapper {
-    pub fn new(active_only: bool, debug: bool) -> Self {
+    pub fn new(active_only: bool) -> Self {
         let flags = if active_only {
             QDC_ONLY_ACTIVE_PATHS
         } else {
@@ -165,11 +148,10 @@ impl CCDWrapper {
             flags,
             paths,
             modes,
-            _debug: debug,
         }
     }
 
-    fn get_paths_and_modes(&mut self) -> Result<String, String> {
+    fn get_paths_and_modes(&mut self) -> Result<String> {
         let max_retries = 3;
         let mut path_count: u32 = 0;
         let mut mode_count: u32 = 0;
@@ -181,7 +163,10 @@ impl CCDWrapper {
             unsafe {
                 let res = GetDisplayConfigBufferSizes(self.flags, raw_path_count, raw_mode_count);
                 if res != ERROR_SUCCESS {
-                    return Result::Err(format!("Unknown Error occured."));
+                    return Err(WompError::win32(
+                        "GetDisplayConfigBufferSizes failed",
+                        res.0,
+                    ));
                 }
             }
 
@@ -202,13 +187,11 @@ impl CCDWrapper {
                     raw_modes,
                     None,
                 );
-                if res != ERROR_SUCCESS {
-                    return Result::Err(format!("Unknown Error occured."));
-                } else if res == ERROR_INSUFFICIENT_BUFFER {
-                    println!(
-                        "Buffer mismatch! Trying again for attempt no. {attempt}/{max_retries}"
-                    );
+                if res == ERROR_INSUFFICIENT_BUFFER && attempt < max_retries {
+                    debug!(attempt, max_retries; "QueryDisplayConfig buffer mismatch, trying again");
                     continue;
+                } else if res != ERROR_SUCCESS {
+                    return Err(WompError::win32("QueryDisplayConfig failed", res.0));
                 }
             }
 
@@ -216,355 +199,192 @@ impl CCDWrapper {
                 .resize_with(path_count as usize, Default::default);
             self.modes
                 .resize_with(mode_count as usize, Default::default);
+            break;
         }
 
-        return Result::Ok(format!(
+        Ok(format!(
             "Successfully retrieved {path_count} Paths and {mode_count} Modes"
-        ));
+        ))
     }
 
-    fn get_modes(&self, path: &DISPLAYCONFIG_PATH_INFO) -> [Option<&DISPLAYCONFIG_MODE_INFO>; 2] {
-        let target_id = path.targetInfo.id;
-        let target_adapter_id = path.targetInfo.adapterId;
-        let source_id = path.sourceInfo.id;
-        let source_adapter_id = path.sourceInfo.adapterId;
-
-        let mut target_mode_opt: Option<&DISPLAYCONFIG_MODE_INFO> = None;
-        let mut source_mode_opt: Option<&DISPLAYCONFIG_MODE_INFO> = None;
+    fn get_desktop_view_mode_and_icon_size(&self) -> Result<(FOLDERVIEWMODE, i32)> {
+        let result = self.find_desktop_folder_view();
+        match result {
+            Ok(folder_view) => {
+                let mut view_mode = FOLDERVIEWMODE::default();
+                let mut icon_size = 0i32;
 
-        for mode in &self.modes {
-            if mode.adapterId == target_adapter_id && mode.id == target_id {
-                target_mode_opt = Some(mode);
-            }
-            if mode.adapterId == source_adapter_id && mode.id == source_id {
-                source_mode_opt = Some(mode);
-            }
+                unsafe {
+                    if let Err(e) =
+                        folder_view.GetViewModeAndIconSize(&mut view_mode, &mut icon_size)
+                    {
+                        return Err(com_error("GetViewModeAndIconSize failed", e));
+                    }
+                }
 
-            if target_mode_opt.is_some() && source_mode_opt.is_some() {
-                break;
+                Ok((view_mode, icon_size))
             }
+            Err(e) => Err(e),
         }
-
-        return [target_mode_opt, source_mode_opt];
     }
 
-    fn get_additional_info(
-        &self,
-        path: &DISPLAYCONFIG_PATH_INFO,
-        global_config: &GlobalConfig,
-    ) -> (
-        DISPLAYCONFIG_TARGET_DEVICE_NAME,
-        DISPLAYCONFIG_ADAPTER_NAME,
-        OptionalInfo,
-    ) {
-        let target = &path.targetInfo;
-        let source = &path.sourceInfo;
-
-        let mut target_name: DISPLAYCONFIG_TARGET_DEVICE_NAME = Default::default();
-        let mut adapter_name: DISPLAYCONFIG_ADAPTER_NAME = Default::default();
-
-        target_name.header = DISPLAYCONFIG_DEVICE_INFO_HEADER {
-            adapterId: target.adapterId,
-            id: target.id,
-            r#type: DISPLAYCONFIG_DEVICE_INFO_GET_TARGET_NAME,
-            size: size_of::<DISPLAYCONFIG_TARGET_DEVICE_NAME>() as u32,
-        };
-
-        adapter_name.header = DISPLAYCONFIG_DEVICE_INFO_HEADER {
-            adapterId: target.adapterId,
-            id: target.id,
-            r#type: DISPLAYCONFIG_DEVICE_INFO_GET_ADAPTER_NAME,
-            size: size_of::<DISPLAYCONFIG_ADAPTER_NAME>() as u32,
-        };
-
-        let raw_target_header = &mut target_name.header as *mut DISPLAYCONFIG_DEVICE_INFO_HEADER;
-        let raw_adapter_header = &mut adapter_name.header as *mut DISPLAYCONFIG_DEVICE_INFO_HEADER;
-
+    // Helper function to find the desktop folder view and query for the requested interface
+    fn find_desktop_folder_view(&self) -> Result<IFolderView2> {
         unsafe {
-            let e = DisplayConfigGetDeviceInfo(raw_target_header);
-            let res = WIN32_ERROR(e.try_into().unwrap());
-            if res != ERROR_SUCCESS {
-                eprintln!("{res:?}");
-                panic!();
-            }
-
-            let e = DisplayConfigGetDeviceInfo(raw_adapter_header);
-            let res = WIN32_ERROR(e.try_into().unwrap());
-            if res != ERROR_SUCCESS {
-                eprintln!("{res:?}");
-                panic!();
-            }
-        }
-
-        // Only get DPI info if it's enabled in config
-        let dpi_scale = if global_config.save_dpi_scale {
-            match self.get_display_dpi_info(source.adapterId, source.id) {
-                Ok(dpi_info) => Some(dpi_info.current),
-                Err(_) => None,
-            }
-        } else {
-            None
-        };
-
-        // Only get HDR info if it's enabled in config
-        let (hdr_supported, hdr_enabled) = if global_config.save_hdr_state {
-            match self.get_display_hdr_info(target.adapterId, target.id) {
-                Ok(hdr_info) => (
-                    Some(hdr_info.advanced_color_supported),
-                    Some(hdr_info.advanced_color_enabled),
-                ),
-                Err(_) => (None, None),
+            // Initialize COM if not already initialized
+            let hr = CoInitializeEx(None, COINIT_APARTMENTTHREADED);
+            if hr.is_err() && hr != CO_E_ALREADYINITIALIZED {
+                return Err(WompError::com("Failed to initialize COM", hr.0));
             }
-        } else {
-            (None, None)
-        };
 
-        // Only get SDR white level if HDR is enabled and the feature is enabled in config
-        let sdr_white_level = if global_config.save_sdr_white_level && hdr_enabled == Some(true) {
-            match self.get_display_sdr_white_level(target.adapterId, target.id) {
-                Ok(level) => Some(level),
-                Err(_) => None,
-            }
-        } else {
-            None
-        };
+            // Create IShellWindows instance
+            let shell_windows: IShellWindows =
+                match CoCreateInstance(&ShellWindows, None, CLSCTX_ALL) {
+                    Ok(windows) => windows,
+                    Err(e) => {
+                        return Err(com_error("Failed to create IShellWindows instance", e));
+                    }
+                };
 
-        let settings = OptionalInfo {
-            dpiScale: dpi_scale,
-            hdrSupported: hdr_supported,
-            hdrEnabled: hdr_enabled,
-            sdrWhiteLevel: sdr_white_level,
-        };
+            // Find desktop window
+            let loc = VARIANT::from(CSIDL_DESKTOP as i32);
+            let empty = VARIANT::default();
+            let mut hwnd = 0i32;
 
-        (target_name, adapter_name, settings)
-    }
+            let dispatch = match shell_windows.FindWindowSW(
+                &loc,
+                &empty,
+                SWC_DESKTOP,
+                &mut hwnd,
+                SWFO_NEEDDISPATCH,
+            ) {
+                Ok(dispatch) => dispatch,
+                Err(e) => return Err(com_error("Failed to find desktop window", e)),
+            };
 
-    pub fn get_display_layout(
-        &mut self,
-        global_config: &GlobalConfig,
-    ) -> Result<DisplayLayout, String> {
-        let result = self.get_paths_and_modes();
-        if let Err(_) = result {
-            panic!("Cannot continue without paths and modes!");
-        }
+            // Query for IServiceProvider
+            let service_provider: IServiceProvider = match dispatch.cast() {
+                Ok(provider) => provider,
+                Err(e) => return Err(com_error("Failed to get IServiceProvider interface", e)),
+            };
 
-        let mut displays: Vec<Display> = vec![];
+            // Get shell browser
+            let browser: IShellBrowser = match service_provider.QueryService(&SID_STopLevelBrowser)
+            {
+                Ok(browser) => browser,
+                Err(e) => return Err(com_error("Failed to get IShellBrowser", e)),
+            };
 
-        for path in &self.paths {
-            let modes: [&DISPLAYCONFIG_MODE_INFO; 2];
+            // Get shell view
+            let view = match browser.QueryActiveShellView() {
+                Ok(view) => view,
+                Err(e) => return Err(com_error("Failed to get IShellView", e)),
+            };
 
-            if let [Some(target_mode), Some(source_mode)] = self.get_modes(path) {
-                modes = [target_mode, source_mode];
-            } else {
-                continue;
+            // Query for requested interface
+            match view.cast::<IFolderView2>() {
+                Ok(folder_view) => Ok(folder_view),
+                Err(e) => Err(com_error("Failed to query for requested interface", e)),
             }
-
-            let (target_name, adapter_name, optional_info) =
-                self.get_additional_info(&path, global_config);
-
-            displays.push(Display::from(
-                path,
-                modes[0],
-                modes[1],
-                &target_name,
-                &adapter_name,
-                &optional_info,
-            ))
         }
-
-        let icon_size = if global_config.save_icon_size {
-            match self.get_desktop_icon_size() {
-                Ok((_, icon_size)) => Some(icon_size),
-                Err(_) => None,
-            }
-        } else {
-            None
-        };
-
-        let wallpaper_info = if global_config.save_wallpaper_info {
-            match self.get_wallpaper_info() {
-                Ok(wallpaper_info) => Some(wallpaper_info),
-                Err(_) => None,
-            }
-        } else {
-            None
-        };
-
-        let audio_output = if global_config.save_audio_output {
-            match self.get_default_audio_output() {
-                Ok(audio_output) => Some(audio_output),
-                Err(_) => None,
-            }
-        } else {
-            None
-        };
-
-        let global_info = GlobalInfo::from(icon_size, wallpaper_info, audio_output);
-        let display_layout = DisplayLayout::from(displays, global_info);
-
-        Result::Ok(display_layout)
     }
+}
 
-    fn adjust_adapter_ids(
-        &self,
-        source: &DisplayLayout,
-        target: &mut DisplayLayout,
-    ) -> Result<(), String> {
-        for display in &mut target.displays {
-            for s in &source.displays {
-                if s.pathInfo.targetInfo.id == display.pathInfo.targetInfo.id {
-                    let new_id = s.pathInfo.targetInfo.adapterId;
-
-                    display.pathInfo.sourceInfo.adapterId = new_id;
-                    display.pathInfo.targetInfo.adapterId = new_id;
-                    display.modeInfo.sourceModeInfo.adapterId = new_id;
-                    display.modeInfo.targetModeInfo.adapterId = new_id;
-                    display.additionalInfo.adapter.header.adapterId = new_id;
-                    display.additionalInfo.target.header.adapterId = new_id;
-                }
-            }
-        }
-        Ok(())
+impl DisplayBackend for CCDWrapper {
+    fn query_display_config(
+        &mut self,
+    ) -> Result<(Vec<DISPLAYCONFIG_PATH_INFO>, Vec<DISPLAYCONFIG_MODE_INFO>)> {
+        self.get_paths_and_modes()
+            .map_err(|e| e.context("Cannot continue without paths and modes"))?;
+        Ok((self.paths.clone(), self.modes.clone()))
     }
 
-    pub fn apply_display_layout(
+    fn query_all_paths(
         &mut self,
-        display_layout: &mut DisplayLayout,
-        global_config: &GlobalConfig,
-    ) -> Result<(), String> {
-        let current_layout = match self.get_display_layout(global_config) {
-            Ok(display_layout) => display_layout,
-            Err(e) => panic!("Could not load current display config: {e}!"),
-        };
-
-        if let Err(e) = self.adjust_adapter_ids(&current_layout, display_layout) {
-            panic!("Could not adjust adapterIds: {e}!");
-        }
-
-        let mut paths = vec![];
-        let mut target_modes = vec![];
-        let mut source_modes = vec![];
-        let mut target_names = vec![];
-        let mut adapter_names = vec![];
-
-        for d in &display_layout.displays {
-            let (path_info, target_mode_info, source_mode_info, target_name, adapter_name) =
-                d.to_windows_types();
-            paths.push(path_info);
-            target_modes.push(target_mode_info);
-            source_modes.push(source_mode_info);
-            target_names.push(target_name);
-            adapter_names.push(adapter_name);
-        }
-
-        let modes: Vec<DISPLAYCONFIG_MODE_INFO> = target_modes
-            .into_iter()
-            .interleave(source_modes.into_iter())
-            .collect();
-
-        let mut flags = SDC_APPLY
-            | SDC_USE_SUPPLIED_DISPLAY_CONFIG
-            | SDC_SAVE_TO_DATABASE
-            | SDC_NO_OPTIMIZATION;
+    ) -> Result<(Vec<DISPLAYCONFIG_PATH_INFO>, Vec<DISPLAYCONFIG_MODE_INFO>)> {
+        let flags = self.flags;
+        self.flags = QDC_ALL_PATHS;
+        let result = self.query_display_config();
+        self.flags = flags;
+        result
+    }
 
-        let patharray = Some(paths.as_slice());
-        let modeinfoarray = Some(modes.as_slice());
+    fn get_target_name(
+        &self,
+        adapter_id: LUID,
+        target_id: u32,
+    ) -> Result<DISPLAYCONFIG_TARGET_DEVICE_NAME> {
+        let mut target_name: DISPLAYCONFIG_TARGET_DEVICE_NAME = Default::default();
 
-        let mut res: WIN32_ERROR;
+        target_name.header = DISPLAYCONFIG_DEVICE_INFO_HEADER {
+            adapterId: adapter_id,
+            id: target_id,
+            r#type: DISPLAYCONFIG_DEVICE_INFO_GET_TARGET_NAME,
+            size: size_of::<DISPLAYCONFIG_TARGET_DEVICE_NAME>() as u32,
+        };
 
-        unsafe {
-            let e = SetDisplayConfig(
-                patharray,
-                modeinfoarray,
-                SDC_USE_SUPPLIED_DISPLAY_CONFIG | SDC_VALIDATE,
-            );
-            res = WIN32_ERROR(e.try_into().unwrap());
-        }
+        let raw_target_header = &mut target_name.header as *mut DISPLAYCONFIG_DEVICE_INFO_HEADER;
 
-        if res != ERROR_SUCCESS {
-            return Err(format!("Input arrays are invalid: {res:?}"));
-        }
+        let result = unsafe { DisplayConfigGetDeviceInfo(raw_target_header) };
 
-        unsafe {
-            let e = SetDisplayConfig(patharray, modeinfoarray, flags);
-            res = WIN32_ERROR(e.try_into().unwrap());
+        if result != ERROR_SUCCESS.0 as i32 {
+            return Err(WompError::win32("Failed to get target name", result as u32));
         }
 
-        if res != ERROR_SUCCESS {
-            eprintln!("Failed to apply, will try again with SDC_ALLOW_CHANGES: {res:?}");
-
-            flags |= SDC_ALLOW_CHANGES;
+        Ok(target_name)
+    }
 
-            unsafe {
-                let e = SetDisplayConfig(patharray, modeinfoarray, flags);
-                res = WIN32_ERROR(e.try_into().unwrap());
-            }
-        }
+    fn get_adapter_name(
+        &self,
+        adapter_id: LUID,
+        target_id: u32,
+    ) -> Result<DISPLAYCONFIG_ADAPTER_NAME> {
+        let mut adapter_name: DISPLAYCONFIG_ADAPTER_NAME = Default::default();
 
-        if res != ERROR_SUCCESS {
-            return Err(format!("Failed to apply using SDC_ALLOW_CHANGES: {res:?}"));
-        }
+        adapter_name.header = DISPLAYCONFIG_DEVICE_INFO_HEADER {
+            adapterId: adapter_id,
+            id: target_id,
+            r#type: DISPLAYCONFIG_DEVICE_INFO_GET_ADAPTER_NAME,
+            size: size_of::<DISPLAYCONFIG_ADAPTER_NAME>() as u32,
+        };
 
-        if global_config.save_dpi_scale {
-            for d in &display_layout.displays {
-                self.set_display_dpi(
-                    d.pathInfo.sourceInfo.adapterId,
-                    d.pathInfo.sourceInfo.id,
-                    d.optionalInfo.dpiScale.unwrap(),
-                )?;
-            }
-        }
+        let raw_adapter_header = &mut adapter_name.header as *mut DISPLAYCONFIG_DEVICE_INFO_HEADER;
 
-        if global_config.save_hdr_state {
-            for d in &display_layout.displays {
-                if let (Some(enabled), Some(supported)) =
-                    (d.optionalInfo.hdrEnabled, d.optionalInfo.hdrSupported)
-                {
-                    if supported {
-                        self.set_display_hdr(
-                            d.pathInfo.targetInfo.adapterId,
-                            d.pathInfo.targetInfo.id,
-                            enabled,
-                        )?;
-                    }
-                }
-            }
-        }
+        let result = unsafe { DisplayConfigGetDeviceInfo(raw_adapter_header) };
 
-        if global_config.save_sdr_white_level {
-            for d in &display_layout.displays {
-                if let (Some(white_level), Some(hdr_enabled)) =
-                    (d.optionalInfo.sdrWhiteLevel, d.optionalInfo.hdrEnabled)
-                {
-                    if hdr_enabled {
-                        self.set_display_sdr_white_level(
-                            d.pathInfo.targetInfo.adapterId,
-                            d.pathInfo.targetInfo.id,
-                            white_level,
-                        )?;
-                    }
-                }
-            }
+        if result != ERROR_SUCCESS.0 as i32 {
+            return Err(WompError::win32(
+                "Failed to get adapter name",
+                result as u32,
+            ));
         }
 
-        if global_config.save_icon_size {
-            self.set_desktop_icon_size(&display_layout.globalInfo.iconSize.unwrap())?;
-        }
+        Ok(adapter_name)
+    }
 
-        if global_config.save_wallpaper_info {
-            self.set_wallpaper_info(&display_layout.globalInfo.wallpaperInfo.as_ref().unwrap())?;
-        }
+    fn set_display_config(
+        &mut self,
+        paths: &[DISPLAYCONFIG_PATH_INFO],
+        modes: &[DISPLAYCONFIG_MODE_INFO],
+        flags: SET_DISPLAY_CONFIG_FLAGS,
+    ) -> Result<()> {
+        let result = unsafe { SetDisplayConfig(Some(paths), Some(modes), flags) };
+        debug!(
+            paths = paths.len(),
+            modes = modes.len(),
+            flags = flags.0,
+            result;
+            "SetDisplayConfig"
+        );
 
-        if global_config.save_audio_output {
-            self.set_default_audio_output(
-                &display_layout.globalInfo.audioOutput.as_ref().unwrap(),
-            )?;
+        if result != ERROR_SUCCESS.0 as i32 {
+            return Err(WompError::win32("SetDisplayConfig failed", result as u32));
         }
 
         Ok(())
     }
 
-    pub fn turn_off_all_displays(&mut self) -> Result<(), String> {
+    fn turn_off_all_displays(&mut self) -> Result<()> {
         unsafe {
             let res = SendMessageA(
                 HWND_BROADCAST,
@@ -573,17 +393,15 @@ impl CCDWrapper {
                 LPARAM(2), // MONITOR_OFF
             );
             if res != LRESULT(0) {
-                return Err(format!("Failed to turn off all displays: {res:?}"));
+                return Err(WompError::Backend(format!(
+                    "Failed to turn off all displays: {res:?}"
+                )));
             }
         }
         Ok(())
     }
 
-    fn get_display_dpi_info(
-        &self,
-        adapter_id: LUID,
-        source_id: u32,
-    ) -> Result<DpiScalingInfo, String> {
+    fn get_display_dpi_info(&self, adapter_id: LUID, source_id: u32) -> Result<DpiScalingInfo> {
         let mut dpi_info = DpiScalingInfo {
             minimum: 100,
             maximum: 100,
@@ -608,10 +426,7 @@ impl CCDWrapper {
         let result = unsafe { DisplayConfigGetDeviceInfo(header_ptr) };
 
         if result != ERROR_SUCCESS.0 as i32 {
-            return Err(format!(
-                "Failed to get DPI info: {:?}",
-                WIN32_ERROR(result.try_into().unwrap())
-            ));
+            return Err(WompError::win32("Failed to get DPI info", result as u32));
         }
 
         // Ensure current value is within bounds
@@ -657,19 +472,23 @@ impl CCDWrapper {
 
                 Ok(dpi_info)
             } else {
-                Err("Calculated DPI indices out of bounds".to_string())
+                Err(WompError::Backend(
+                    "Calculated DPI indices out of bounds".to_string(),
+                ))
             }
         } else {
-            Err("DPI values array is outdated or incompatible".to_string())
+            Err(WompError::Backend(
+                "DPI values array is outdated or incompatible".to_string(),
+            ))
         }
     }
 
-    pub fn set_display_dpi(
-        &self,
+    fn set_display_dpi(
+        &mut self,
         adapter_id: LUID,
         source_id: u32,
         dpi_percent: u32,
-    ) -> Result<(), String> {
+    ) -> Result<()> {
         // First get current DPI info to determine relative values
         let dpi_info = self.get_display_dpi_info(adapter_id, source_id)?;
 
@@ -696,10 +515,10 @@ impl CCDWrapper {
         }
 
         if target_idx == -1 || recommended_idx == -1 {
-            return Err(format!(
+            return Err(WompError::Validation(format!(
                 "Could not find DPI value {} or recommended value {} in supported values",
                 dpi_percent, dpi_info.recommended
-            ));
+            )));
         }
 
         // Calculate relative DPI value
@@ -721,121 +540,13 @@ impl CCDWrapper {
         let result = unsafe { DisplayConfigSetDeviceInfo(header_ptr) };
 
         if result != ERROR_SUCCESS.0 as i32 {
-            return Err(format!(
-                "Failed to set DPI value: {:?}",
-                WIN32_ERROR(result.try_into().unwrap())
-            ));
+            return Err(WompError::win32("Failed to set DPI value", result as u32));
         }
 
         Ok(())
     }
 
-    pub fn get_desktop_icon_size(&self) -> Result<(FOLDERVIEWMODE, i32), String> {
-        let result = self.find_desktop_folder_view();
-        match result {
-            Ok(folder_view) => {
-                let mut view_mode = FOLDERVIEWMODE::default();
-                let mut icon_size = 0i32;
-
-                unsafe {
-                    let hr = folder_view.GetViewModeAndIconSize(&mut view_mode, &mut icon_size);
-                    if hr.is_err() {
-                        return Err(format!("GetViewModeAndIconSize failed: {:?}", hr));
-                    }
-                }
-
-                Ok((view_mode, icon_size))
-            }
-            Err(e) => Err(e),
-        }
-    }
-
-    pub fn set_desktop_icon_size(&self, icon_size: &i32) -> Result<(), String> {
-        // Get current view mode and icon size
-        let (view_mode, current_size) = self.get_desktop_icon_size()?;
-
-        // Skip if already at requested size
-        if current_size == *icon_size {
-            return Ok(());
-        }
-
-        let result = self.find_desktop_folder_view();
-        match result {
-            Ok(folder_view) => {
-                unsafe {
-                    let hr = folder_view.SetViewModeAndIconSize(view_mode, *icon_size);
-                    if hr.is_err() {
-                        return Err(format!("SetViewModeAndIconSize failed: {:?}", hr));
-                    }
-                }
-                Ok(())
-            }
-            Err(e) => Err(e),
-        }
-    }
-
-    // Helper function to find the desktop folder view and query for the requested interface
-    fn find_desktop_folder_view(&self) -> Result<IFolderView2, String> {
-        unsafe {
-            // Initialize COM if not already initialized
-            let hr = CoInitializeEx(None, COINIT_APARTMENTTHREADED);
-            if hr.is_err() && hr != CO_E_ALREADYINITIALIZED {
-                return Err(format!("Failed to initialize COM: {:?}", hr));
-            }
-
-            // Create IShellWindows instance
-            let shell_windows: IShellWindows =
-                match CoCreateInstance(&ShellWindows, None, CLSCTX_ALL) {
-                    Ok(windows) => windows,
-                    Err(e) => {
-                        return Err(format!("Failed to create IShellWindows instance: {:?}", e));
-                    }
-                };
-
-            // Find desktop window
-            let loc = VARIANT::from(CSIDL_DESKTOP as i32);
-            let empty = VARIANT::default();
-            let mut hwnd = 0i32;
-
-            let dispatch = match shell_windows.FindWindowSW(
-                &loc,
-                &empty,
-                SWC_DESKTOP,
-                &mut hwnd,
-                SWFO_NEEDDISPATCH,
-            ) {
-                Ok(dispatch) => dispatch,
-                Err(e) => return Err(format!("Failed to find desktop window: {:?}", e)),
-            };
-
-            // Query for IServiceProvider
-            let service_provider: IServiceProvider = match dispatch.cast() {
-                Ok(provider) => provider,
-                Err(e) => return Err(format!("Failed to get IServiceProvider interface: {:?}", e)),
-            };
-
-            // Get shell browser
-            let browser: IShellBrowser = match service_provider.QueryService(&SID_STopLevelBrowser)
-            {
-                Ok(browser) => browser,
-                Err(e) => return Err(format!("Failed to get IShellBrowser: {:?}", e)),
-            };
-
-            // Get shell view
-            let view = match browser.QueryActiveShellView() {
-                Ok(view) => view,
-                Err(e) => return Err(format!("Failed to get IShellView: {:?}", e)),
-            };
-
-            // Query for requested interface
-            match view.cast::<IFolderView2>() {
-                Ok(folder_view) => Ok(folder_view),
-                Err(e) => Err(format!("Failed to query for requested interface: {:?}", e)),
-            }
-        }
-    }
-
-    fn get_display_hdr_info(&self, adapter_id: LUID, source_id: u32) -> Result<HdrInfo, String> {
+    fn get_display_hdr_info(&self, adapter_id: LUID, source_id: u32) -> Result<HdrInfo> {
         let mut color_info = DisplayConfigGetAdvancedColorInfo {
             header: DISPLAYCONFIG_DEVICE_INFO_HEADER {
                 adapterId: adapter_id,
@@ -855,10 +566,7 @@ impl CCDWrapper {
         let result = unsafe { DisplayConfigGetDeviceInfo(header_ptr) };
 
         if result != ERROR_SUCCESS.0 as i32 {
-            return Err(format!(
-                "Failed to get HDR info: {:?}",
-                WIN32_ERROR(result.try_into().unwrap())
-            ));
+            return Err(WompError::win32("Failed to get HDR info", result as u32));
         }
 
         // Extract boolean flags from the value field
@@ -877,16 +585,14 @@ impl CCDWrapper {
         })
     }
 
-    pub fn get_display_sdr_white_level(
-        &self,
-        adapter_id: LUID,
-        source_id: u32,
-    ) -> Result<u32, String> {
+    fn get_display_sdr_white_level(&self, adapter_id: LUID, source_id: u32) -> Result<u32> {
         // First check if HDR is supported and enabled
         let hdr_info = self.get_display_hdr_info(adapter_id, source_id)?;
 
         if !hdr_info.advanced_color_supported || !hdr_info.advanced_color_enabled {
-            return Err("HDR is not supported or not enabled on this display".to_string());
+            return Err(WompError::Backend(
+                "HDR is not supported or not enabled on this display".to_string(),
+            ));
         }
 
         let mut white_level_info = DisplayConfigGetSdrWhiteLevel {
@@ -906,9 +612,9 @@ impl CCDWrapper {
         let result = unsafe { DisplayConfigGetDeviceInfo(header_ptr) };
 
         if result != ERROR_SUCCESS.0 as i32 {
-            return Err(format!(
-                "Failed to get SDR white level: {:?}",
-                WIN32_ERROR(result.try_into().unwrap())
+            return Err(WompError::win32(
+                "Failed to get SDR white level",
+                result as u32,
             ));
         }
 
@@ -918,18 +624,15 @@ impl CCDWrapper {
         Ok(nits)
     }
 
-    pub fn set_display_hdr(
-        &self,
-        adapter_id: LUID,
-        source_id: u32,
-        enable: bool,
-    ) -> Result<(), String> {
+    fn set_display_hdr(&mut self, adapter_id: LUID, source_id: u32, enable: bool) -> Result<()> {
         // First check if HDR is supported and if it's already in the desired state
         let hdr_info = self.get_display_hdr_info(adapter_id, source_id)?;
 
         // If HDR is not supported, return an error
         if !hdr_info.advanced_color_supported {
-            return Err("HDR is not supported on this display".to_string());
+            return Err(WompError::Backend(
+                "HDR is not supported on this display".to_string(),
+            ));
         }
 
         // If HDR is already in the desired state, we don't need to do anything
@@ -955,34 +658,33 @@ impl CCDWrapper {
         let result = unsafe { DisplayConfigSetDeviceInfo(header_ptr) };
 
         if result != ERROR_SUCCESS.0 as i32 {
-            return Err(format!(
-                "Failed to set HDR state: {:?}",
-                WIN32_ERROR(result.try_into().unwrap())
-            ));
+            return Err(WompError::win32("Failed to set HDR state", result as u32));
         }
 
         Ok(())
     }
 
-    pub fn set_display_sdr_white_level(
-        &self,
+    fn set_display_sdr_white_level(
+        &mut self,
         adapter_id: LUID,
         source_id: u32,
         nits: u32,
-    ) -> Result<(), String> {
+    ) -> Result<()> {
         // Check if HDR is supported and enabled
         let hdr_info = self.get_display_hdr_info(adapter_id, source_id)?;
 
         if !hdr_info.advanced_color_supported || !hdr_info.advanced_color_enabled {
-            return Err("HDR is not supported or not enabled on this display".to_string());
+            return Err(WompError::Backend(
+                "HDR is not supported or not enabled on this display".to_string(),
+            ));
         }
 
         // Validate nits range (same as in C example)
         if nits < 80 || nits > 480 {
-            return Err(format!(
+            return Err(WompError::Validation(format!(
                 "Invalid nits value {}. Value must be between 80 and 480.",
                 nits
-            ));
+            )));
         }
 
         // Round up to multiple of 4 to match SDR brightness slider increments
@@ -1013,21 +715,49 @@ impl CCDWrapper {
         let result = unsafe { DisplayConfigSetDeviceInfo(header_ptr) };
 
         if result != ERROR_SUCCESS.0 as i32 {
-            return Err(format!(
-                "Failed to set SDR white level: {:?}",
-                WIN32_ERROR(result.try_into().unwrap())
+            return Err(WompError::win32(
+                "Failed to set SDR white level",
+                result as u32,
             ));
         }
 
         Ok(())
     }
 
-    pub fn get_wallpaper_info(&self) -> Result<WallpaperInfo, String> {
+    fn get_desktop_icon_size(&self) -> Result<i32> {
+        let (_, icon_size) = self.get_desktop_view_mode_and_icon_size()?;
+        Ok(icon_size)
+    }
+
+    fn set_desktop_icon_size(&mut self, icon_size: i32) -> Result<()> {
+        // Get current view mode and icon size
+        let (view_mode, current_size) = self.get_desktop_view_mode_and_icon_size()?;
+
+        // Skip if already at requested size
+        if current_size == icon_size {
+            return Ok(());
+        }
+
+        let result = self.find_desktop_folder_view();
+        match result {
+            Ok(folder_view) => {
+                unsafe {
+                    if let Err(e) = folder_view.SetViewModeAndIconSize(view_mode, icon_size) {
+                        return Err(com_error("SetViewModeAndIconSize failed", e));
+                    }
+                }
+                Ok(())
+            }
+            Err(e) => Err(e),
+        }
+    }
+
+    fn get_wallpaper_info(&self) -> Result<WallpaperInfo> {
         unsafe {
             // Initialize COM
             let hr = CoInitializeEx(None, COINIT_APARTMENTTHREADED);
             if hr.is_err() && hr != CO_E_ALREADYINITIALIZED {
-                return Err(format!("Failed to initialize COM: {:?}", hr));
+                return Err(WompError::com("Failed to initialize COM", hr.0));
             }
 
             // Create IDesktopWallpaper instance
@@ -1035,10 +765,7 @@ impl CCDWrapper {
                 match CoCreateInstance(&DesktopWallpaper, None, CLSCTX_LOCAL_SERVER) {
                     Ok(wallpaper) => wallpaper,
                     Err(e) => {
-                        return Err(format!(
-                            "Failed to create IDesktopWallpaper instance: {:?}",
-                            e
-                        ));
+                        return Err(com_error("Failed to create IDesktopWallpaper instance", e));
                     }
                 };
 
@@ -1046,7 +773,7 @@ impl CCDWrapper {
             let wallpaper_pwstr = match desktop_wallpaper.GetWallpaper(PCWSTR::null()) {
                 Ok(path) => path,
                 Err(e) => {
-                    return Err(format!("Failed to get wallpaper path: {:?}", e));
+                    return Err(com_error("Failed to get wallpaper path", e));
                 }
             };
 
@@ -1055,7 +782,9 @@ impl CCDWrapper {
             let wallpaper_path = match String::from_utf16(wallpaper_path_wide) {
                 Ok(s) => s,
                 Err(_) => {
-                    return Err("Failed to convert wallpaper path to string".to_string());
+                    return Err(WompError::Backend(
+                        "Failed to convert wallpaper path to string".to_string(),
+                    ));
                 }
             };
 
@@ -1063,7 +792,7 @@ impl CCDWrapper {
             let position = match desktop_wallpaper.GetPosition() {
                 Ok(pos) => pos,
                 Err(e) => {
-                    return Err(format!("Failed to get wallpaper position: {:?}", e));
+                    return Err(com_error("Failed to get wallpaper position", e));
                 }
             };
 
@@ -1088,12 +817,12 @@ impl CCDWrapper {
         }
     }
 
-    pub fn set_wallpaper_info(&self, wallpaper_info: &WallpaperInfo) -> Result<(), String> {
+    fn set_wallpaper_info(&mut self, wallpaper_info: &WallpaperInfo) -> Result<()> {
         unsafe {
             // Initialize COM
             let hr = CoInitializeEx(None, COINIT_APARTMENTTHREADED);
             if hr.is_err() && hr != CO_E_ALREADYINITIALIZED {
-                return Err(format!("Failed to initialize COM: {:?}", hr));
+                return Err(WompError::com("Failed to initialize COM", hr.0));
             }
 
             // Create IDesktopWallpaper instance
@@ -1101,10 +830,7 @@ impl CCDWrapper {
                 match CoCreateInstance(&DesktopWallpaper, None, CLSCTX_LOCAL_SERVER) {
                     Ok(wallpaper) => wallpaper,
                     Err(e) => {
-                        return Err(format!(
-                            "Failed to create IDesktopWallpaper instance: {:?}",
-                            e
-                        ));
+                        return Err(com_error("Failed to create IDesktopWallpaper instance", e));
                     }
                 };
 
@@ -1113,7 +839,7 @@ impl CCDWrapper {
 
             // Set wallpaper for all monitors (NULL)
             if let Err(e) = desktop_wallpaper.SetWallpaper(PCWSTR::null(), &wallpaper_path) {
-                return Err(format!("Failed to set wallpaper: {:?}", e));
+                return Err(com_error("Failed to set wallpaper", e));
             }
 
             // Convert position string to DESKTOP_WALLPAPER_POSITION
@@ -1125,16 +851,16 @@ impl CCDWrapper {
                 "fill" => DWPOS_FILL,
                 "span" => DWPOS_SPAN,
                 _ => {
-                    return Err(format!(
+                    return Err(WompError::Validation(format!(
                         "Invalid position value: {}. Must be one of: center, tile, stretch, fit, fill, span",
                         wallpaper_info.wallpaperPosition
-                    ));
+                    )));
                 }
             };
 
             // Set position
             if let Err(e) = desktop_wallpaper.SetPosition(position_val) {
-                return Err(format!("Failed to set wallpaper position: {:?}", e));
+                return Err(com_error("Failed to set wallpaper position", e));
             }
 
             // Free COM resources
@@ -1144,12 +870,12 @@ impl CCDWrapper {
         }
     }
 
-    pub fn get_default_audio_output(&self) -> Result<String, String> {
+    fn get_default_audio_output(&self) -> Result<String> {
         unsafe {
             // Initialize COM if not already initialized
             let hr = CoInitializeEx(None, COINIT_APARTMENTTHREADED);
             if hr.is_err() && hr != CO_E_ALREADYINITIALIZED {
-                return Err(format!("Failed to initialize COM: {:?}", hr));
+                return Err(WompError::com("Failed to initialize COM", hr.0));
             }
 
             // Create device enumerator
@@ -1157,7 +883,7 @@ impl CCDWrapper {
                 match CoCreateInstance(&MMDeviceEnumerator, None, CLSCTX_ALL) {
                     Ok(enumerator) => enumerator,
                     Err(e) => {
-                        return Err(format!("Failed to create IMMDeviceEnumerator: {:?}", e));
+                        return Err(com_error("Failed to create IMMDeviceEnumerator", e));
                     }
                 };
 
@@ -1165,7 +891,7 @@ impl CCDWrapper {
             let device = match device_enumerator.GetDefaultAudioEndpoint(eRender, eConsole) {
                 Ok(device) => device,
                 Err(e) => {
-                    return Err(format!("Failed to get default audio endpoint: {:?}", e));
+                    return Err(com_error("Failed to get default audio endpoint", e));
                 }
             };
 
@@ -1176,7 +902,7 @@ impl CCDWrapper {
                     String::from_utf16_lossy(wide_str)
                 }
                 Err(e) => {
-                    return Err(format!("Failed to get device ID: {:?}", e));
+                    return Err(com_error("Failed to get device ID", e));
                 }
             };
 
@@ -1191,12 +917,12 @@ impl CCDWrapper {
         }
     }
 
-    pub fn set_default_audio_output(&self, device_id: &str) -> Result<(), String> {
+    fn set_default_audio_output(&mut self, device_id: &str) -> Result<()> {
         unsafe {
             // Initialize COM if not already initialized
             let hr = CoInitializeEx(None, COINIT_APARTMENTTHREADED);
             if hr.is_err() && hr != CO_E_ALREADYINITIALIZED {
-                return Err(format!("Failed to initialize COM: {:?}", hr));
+                return Err(WompError::com("Failed to initialize COM", hr.0));
             }
 
             // Create PolicyConfig instance
@@ -1204,7 +930,7 @@ impl CCDWrapper {
                 match CoCreateInstance(&CLSID_POLICY_CONFIG_CLIENT, None, CLSCTX_ALL) {
                     Ok(config) => config,
                     Err(e) => {
-                        return Err(format!("Failed to create IPolicyConfig: {:?}", e));
+                        return Err(com_error("Failed to create IPolicyConfig", e));
                     }
                 };
 
@@ -1216,9 +942,9 @@ impl CCDWrapper {
                 let hr =
                     policy_config.SetDefaultEndpoint(PCWSTR::from_raw(id_hstring.as_ptr()), role);
                 if hr.is_err() {
-                    return Err(format!(
-                        "Failed to set default endpoint for role {}: {:?}",
-                        role, hr
+                    return Err(WompError::com(
+                        format!("Failed to set default endpoint for role {}", role),
+                        hr.0,
                     ));
                 }
             }
@@ -1229,4 +955,50 @@ impl CCDWrapper {
             Ok(())
         }
     }
+
+    fn get_edid(&self, adapter_id: LUID, target_id: u32) -> Result<Option<Vec<u8>>> {
+        let target_name = self.get_target_name(adapter_id, target_id)?;
+        let device_path = wide_to_string(&target_name.monitorDevicePath);
+        let Some(key) = edid::registry_key(&device_path) else {
+            return Ok(None);
+        };
+        let key = HSTRING::from(key);
+        let value = HSTRING::from("EDID");
+
+        unsafe {
+            // Ask for the size first, EDIDs with extension blocks are longer than 128 bytes
+            let mut size = 0u32;
+            let result = RegGetValueW(
+                HKEY_LOCAL_MACHINE,
+                PCWSTR::from_raw(key.as_ptr()),
+                PCWSTR::from_raw(value.as_ptr()),
+                RRF_RT_REG_BINARY,
+                None,
+                None,
+                Some(&mut size),
+            );
+            if result == ERROR_FILE_NOT_FOUND {
+                return Ok(None);
+            }
+            if result != ERROR_SUCCESS {
+                return Err(WompError::win32("Failed to get EDID size", result.0));
+            }
+
+            let mut edid = vec![0u8; size as usize];
+            let result = RegGetValueW(
+                HKEY_LOCAL_MACHINE,
+                PCWSTR::from_raw(key.as_ptr()),
+                PCWSTR::from_raw(value.as_ptr()),
+                RRF_RT_REG_BINARY,
+                None,
+                Some(edid.as_mut_ptr() as *mut _),
+                Some(&mut size),
+            );
+            if result != ERROR_SUCCESS {
+                return Err(WompError::win32("Failed to read EDID", result.0));
+            }
+            edid.truncate(size as usize);
+            Ok(Some(edid))
+        }
+    }
 }
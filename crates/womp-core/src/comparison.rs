@@ -0,0 +1,442 @@
+//! Compares a saved layout with the current one by what the user would notice.
+//!
+//! Monitors are paired by identity, so order, adapter LUIDs and target ids don't matter,
+//! and only the settings a profile is about are compared: which monitors are on, their
+//! resolution, refresh rate, position and rotation, and the extras the profile captured.
+//! Everything else `QueryDisplayConfig` reports, like pixel rates or status flags, can
+//! drift without the layout changing.
+
+use crate::identity::{KnownMonitor, match_monitors};
+use crate::inventory::Inventory;
+use crate::model::{Layout, Rotation};
+use crate::serde_types::DisplayLayout;
+use serde::Serialize;
+use std::fmt;
+
+/// Refresh rates closer than this are the same, drivers round differently between modes.
+const REFRESH_RATE_TOLERANCE_HZ: f64 = 0.05;
+
+/// The settings a profile is about, in the order applying a profile changes them.
+#[derive(Serialize, Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+#[serde(rename_all = "snake_case")]
+pub enum Setting {
+    /// Whether the display is on
+    Enabled,
+    Resolution,
+    RefreshRate,
+    Position,
+    Rotation,
+    DpiScale,
+    Hdr,
+    SdrWhiteLevel,
+    IconSize,
+    Wallpaper,
+    AudioOutput,
+}
+
+impl fmt::Display for Setting {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let name = match self {
+            Setting::Enabled => "display",
+            Setting::Resolution => "resolution",
+            Setting::RefreshRate => "refresh rate",
+            Setting::Position => "position",
+            Setting::Rotation => "rotation",
+            Setting::DpiScale => "DPI scale",
+            Setting::Hdr => "HDR",
+            Setting::SdrWhiteLevel => "SDR white level",
+            Setting::IconSize => "icon size",
+            Setting::Wallpaper => "wallpaper",
+            Setting::AudioOutput => "audio output",
+        };
+        write!(f, "{name}")
+    }
+}
+
+impl Setting {
+    /// How much the setting counts towards the score, a display that's off is worse than
+    /// another wallpaper.
+    fn weight(self) -> u32 {
+        match self {
+            Setting::Enabled => 8,
+            Setting::Resolution => 4,
+            Setting::RefreshRate | Setting::Position | Setting::Rotation => 2,
+            _ => 1,
+        }
+    }
+}
+
+/// A setting that's different from what the profile has.
+#[derive(Serialize, Debug, Clone, PartialEq)]
+pub struct Difference {
+    /// Name of the display it's about, unset for desktop-wide settings.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub display: Option<String>,
+    pub setting: Setting,
+    /// The value in the profile.
+    pub profile: String,
+    /// The value right now.
+    pub current: String,
+}
+
+impl fmt::Display for Difference {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if let Some(display) = &self.display {
+            write!(f, "{display}: ")?;
+        }
+        write!(
+            f,
+            "{} is {} in the profile, {} now",
+            self.setting, self.profile, self.current
+        )
+    }
+}
+
+/// How the current layout compares to the layout of a profile.
+#[derive(Serialize, Debug, Clone, PartialEq)]
+pub struct Comparison {
+    /// How alike the two are, from 0 to 100 for a layout that has everything the profile
+    /// has. Weighted by what the settings mean to the user, not by how many there are.
+    pub score: u32,
+    pub differences: Vec<Difference>,
+}
+
+impl Comparison {
+    pub fn is_match(&self) -> bool {
+        self.differences.is_empty()
+    }
+}
+
+/// How close the current layout is to a profile, see [`crate::rank_profiles`].
+#[derive(Serialize, Debug, Clone, PartialEq)]
+pub struct ProfileMatch {
+    pub profile: String,
+    /// Whether the profile only covers some displays.
+    pub partial: bool,
+    #[serde(flatten)]
+    pub comparison: Comparison,
+}
+
+impl ProfileMatch {
+    /// Whether the profile is what's on the screen right now.
+    pub fn is_active(&self) -> bool {
+        self.comparison.is_match()
+    }
+}
+
+/// A refresh rate, the same as rates within [`REFRESH_RATE_TOLERANCE_HZ`] of it.
+#[derive(Clone, Copy)]
+pub(crate) struct Hz(pub(crate) f64);
+
+impl PartialEq for Hz {
+    fn eq(&self, other: &Self) -> bool {
+        (self.0 - other.0).abs() <= REFRESH_RATE_TOLERANCE_HZ
+    }
+}
+
+pub(crate) fn rotation(rotation: Rotation) -> String {
+    match rotation {
+        Rotation::Identity => "0°".to_string(),
+        Rotation::Rotate90 => "90°".to_string(),
+        Rotation::Rotate180 => "180°".to_string(),
+        Rotation::Rotate270 => "270°".to_string(),
+        Rotation::Other(value) => format!("unknown ({value})"),
+    }
+}
+
+pub(crate) fn on_off(enabled: bool) -> String {
+    if enabled { "on" } else { "off" }.to_string()
+}
+
+/// The differences found so far and the weight of everything compared.
+#[derive(Default)]
+struct Differences {
+    found: Vec<Difference>,
+    checked: u32,
+    differing: u32,
+}
+
+impl Differences {
+    fn check<T: PartialEq>(
+        &mut self,
+        display: Option<&str>,
+        setting: Setting,
+        (profile, current): (T, T),
+        format: impl Fn(&T) -> String,
+    ) {
+        self.checked += setting.weight();
+        if profile != current {
+            self.differing += setting.weight();
+            self.found.push(Difference {
+                display: display.map(str::to_string),
+                setting,
+                profile: format(&profile),
+                current: format(&current),
+            });
+        }
+    }
+
+    /// Like `check`, but only if both sides have the value, e.g. extras that weren't
+    /// captured can't be compared.
+    fn check_captured<T: PartialEq>(
+        &mut self,
+        display: Option<&str>,
+        setting: Setting,
+        (profile, current): (Option<T>, Option<T>),
+        format: impl Fn(&T) -> String,
+    ) {
+        if let (Some(profile), Some(current)) = (profile, current) {
+            self.check(display, setting, (profile, current), format);
+        }
+    }
+
+    fn finish(self) -> Comparison {
+        let score = match self.checked {
+            0 => 100,
+            checked => 100 * (checked - self.differing) / checked,
+        };
+        Comparison {
+            score,
+            differences: self.found,
+        }
+    }
+}
+
+/// Everything about `current` that's different from `profile`. A `partial` profile only
+/// covers its own displays, displays it doesn't have being on isn't a difference. Monitors
+/// with an alias in `inventory` are called by it.
+pub fn compare_layouts(
+    profile: &DisplayLayout,
+    current: &DisplayLayout,
+    partial: bool,
+    inventory: &Inventory,
+) -> Comparison {
+    let known = |layout: &DisplayLayout| -> Vec<KnownMonitor> {
+        layout
+            .displays
+            .iter()
+            .map(KnownMonitor::of_display)
+            .collect()
+    };
+    let pairs = match_monitors(&known(profile), &known(current));
+    let (profile_model, current_model) = (Layout::from(profile), Layout::from(current));
+    let mut differences = Differences::default();
+
+    for (i, display) in profile.displays.iter().enumerate() {
+        if !pairs.iter().any(|&(pi, _)| pi == i) {
+            let name = inventory.display_name(display);
+            differences.check(Some(&name), Setting::Enabled, (true, false), |&e| on_off(e));
+        }
+    }
+    if !partial {
+        for (j, display) in current.displays.iter().enumerate() {
+            if !pairs.iter().any(|&(_, cj)| cj == j) {
+                let name = inventory.display_name(display);
+                differences.check(Some(&name), Setting::Enabled, (false, true), |&e| on_off(e));
+            }
+        }
+    }
+
+    for &(i, j) in &pairs {
+        let (saved, live) = (&profile_model.monitors[i], &current_model.monitors[j]);
+        let name = inventory.display_name(&profile.displays[i]);
+        let display = Some(name.as_str());
+        differences.check(display, Setting::Enabled, (true, true), |&e| on_off(e));
+        differences.check(
+            display,
+            Setting::Resolution,
+            (
+                (saved.mode.width, saved.mode.height),
+                (live.mode.width, live.mode.height),
+            ),
+            |&(width, height)| format!("{width}x{height}"),
+        );
+        differences.check_captured(
+            display,
+            Setting::RefreshRate,
+            (
+                saved.mode.refresh_rate.as_hz().map(Hz),
+                live.mode.refresh_rate.as_hz().map(Hz),
+            ),
+            |hz| format!("{:.2} Hz", hz.0),
+        );
+        differences.check(
+            display,
+            Setting::Position,
+            (saved.position, live.position),
+            |p| format!("({}, {})", p.x, p.y),
+        );
+        differences.check(
+            display,
+            Setting::Rotation,
+            (saved.rotation, live.rotation),
+            |&r| rotation(r),
+        );
+
+        let (extras, live_extras) = (&saved.extras, &live.extras);
+        differences.check_captured(
+            display,
+            Setting::DpiScale,
+            (extras.dpiScale, live_extras.dpiScale),
+            |dpi| format!("{dpi}%"),
+        );
+        differences.check_captured(
+            display,
+            Setting::Hdr,
+            (extras.hdrEnabled, live_extras.hdrEnabled),
+            |&e| on_off(e),
+        );
+        differences.check_captured(
+            display,
+            Setting::SdrWhiteLevel,
+            (extras.sdrWhiteLevel, live_extras.sdrWhiteLevel),
+            |nits| format!("{nits} nits"),
+        );
+    }
+
+    let (global, live_global) = (&profile.globalInfo, &current.globalInfo);
+    differences.check_captured(
+        None,
+        Setting::IconSize,
+        (global.iconSize, live_global.iconSize),
+        |size| size.to_string(),
+    );
+    differences.check_captured(
+        None,
+        Setting::Wallpaper,
+        (
+            global
+                .wallpaperInfo
+                .as_ref()
+                .map(|w| (&w.wallpaperPath, &w.wallpaperPosition)),
+            live_global
+                .wallpaperInfo
+                .as_ref()
+                .map(|w| (&w.wallpaperPath, &w.wallpaperPosition)),
+        ),
+        |(path, position)| format!("`{path}` ({position})"),
+    );
+    differences.check_captured(
+        None,
+        Setting::AudioOutput,
+        (
+            global.audioOutput.as_ref(),
+            live_global.audioOutput.as_ref(),
+        ),
+        |audio| format!("`{audio}`"),
+    );
+
+    differences.finish()
+}
+
+/// Whether `current` looks like `profile` was applied, see [`compare_layouts`].
+pub fn layouts_match(profile: &DisplayLayout, current: &DisplayLayout, partial: bool) -> bool {
+    compare_layouts(profile, current, partial, &Inventory::default()).is_match()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::model::RefreshRate;
+    use crate::testing::{layout, monitor};
+
+    fn compare(profile: &DisplayLayout, current: &DisplayLayout) -> Comparison {
+        compare_layouts(profile, current, false, &Inventory::default())
+    }
+
+    #[test]
+    fn exact_match() {
+        let profile = layout(&[monitor(1, 0, 60), monitor(2, 1920, 144)]);
+        let comparison = compare(&profile, &profile);
+        assert_eq!(comparison.score, 100);
+        assert!(comparison.is_match());
+    }
+
+    #[test]
+    fn order_and_adapter_luid_dont_matter() {
+        let profile = layout(&[monitor(1, 0, 60), monitor(2, 1920, 144)]);
+        let mut rebooted = [monitor(2, 1920, 144), monitor(1, 0, 60)];
+        for monitor in &mut rebooted {
+            monitor.adapter.luid.low_part = 0x5678;
+        }
+        assert!(layouts_match(&profile, &layout(&rebooted), false));
+    }
+
+    #[test]
+    fn ntsc_rate_differs_from_60_hz() {
+        let mut ntsc = monitor(1, 0, 60);
+        ntsc.mode.refresh_rate = RefreshRate::new(60000, 1001);
+        let profile = layout(&[ntsc, monitor(2, 1920, 60)]);
+        let current = layout(&[monitor(1, 0, 60), monitor(2, 1920, 60)]);
+
+        let comparison = compare(&profile, &current);
+        assert_eq!(
+            comparison.differences,
+            [Difference {
+                display: Some("Monitor 1".to_string()),
+                setting: Setting::RefreshRate,
+                profile: "59.94 Hz".to_string(),
+                current: "60.00 Hz".to_string(),
+            }]
+        );
+        // Two displays of 18 and the icon size, 2 of that differ
+        assert_eq!(comparison.score, 100 * 35 / 37);
+    }
+
+    #[test]
+    fn rates_within_the_tolerance_are_the_same() {
+        let mut rounded = monitor(1, 0, 60);
+        rounded.mode.refresh_rate = RefreshRate::new(5994, 100);
+        let mut ntsc = monitor(1, 0, 60);
+        ntsc.mode.refresh_rate = RefreshRate::new(60000, 1001);
+        assert!(layouts_match(&layout(&[rounded]), &layout(&[ntsc]), false));
+    }
+
+    #[test]
+    fn unspecified_rate_isnt_compared() {
+        let mut any_rate = monitor(1, 0, 60);
+        any_rate.mode.refresh_rate = RefreshRate::default();
+        assert!(layouts_match(
+            &layout(&[any_rate]),
+            &layout(&[monitor(1, 0, 144)]),
+            false
+        ));
+    }
+
+    #[test]
+    fn missing_monitor() {
+        let profile = layout(&[monitor(1, 0, 60), monitor(2, 1920, 60)]);
+        let current = layout(&[monitor(1, 0, 60)]);
+
+        let comparison = compare(&profile, &current);
+        assert_eq!(comparison.differences.len(), 1);
+        let difference = &comparison.differences[0];
+        assert_eq!(difference.setting, Setting::Enabled);
+        assert_eq!(difference.display.as_deref(), Some("Monitor 2"));
+        assert_eq!(
+            (difference.profile.as_str(), difference.current.as_str()),
+            ("on", "off")
+        );
+        // The missing display weighs 8, the one that's there 18 and the icon size 1
+        assert_eq!(comparison.score, 100 * 19 / 27);
+    }
+
+    #[test]
+    fn extra_monitor_only_matters_to_whole_profiles() {
+        let profile = layout(&[monitor(1, 0, 60)]);
+        let current = layout(&[monitor(1, 0, 60), monitor(2, 1920, 60)]);
+
+        let comparison = compare(&profile, &current);
+        assert_eq!(comparison.differences.len(), 1);
+        assert_eq!(comparison.differences[0].profile, "off");
+        assert!(layouts_match(&profile, &current, true));
+    }
+
+    #[test]
+    fn closer_layouts_score_higher() {
+        let profile = layout(&[monitor(1, 0, 60), monitor(2, 1920, 60)]);
+        let other_rate = layout(&[monitor(1, 0, 144), monitor(2, 1920, 60)]);
+        let one_off = layout(&[monitor(1, 0, 60)]);
+        assert!(compare(&profile, &other_rate).score > compare(&profile, &one_off).score);
+    }
+}
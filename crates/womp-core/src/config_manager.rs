@@ -1,179 +1,169 @@
+use crate::bundle::{self, BundleManifest};
+use crate::error::{Result, WompError};
+use crate::profile_store::filesystem::{CONFIG_FILE_NAME, LAYOUT_FILE_NAME};
+use crate::profile_store::{FsProfileStore, ProfileStore};
 use crate::serde_types::config::Config;
 use dirs::config_dir;
-use std::{fs, os::windows::process::CommandExt, path::PathBuf};
+#[cfg(windows)]
+use std::os::windows::process::CommandExt;
+use std::{
+    fs,
+    path::{Path, PathBuf},
+    sync::RwLock,
+};
+
+/// Environment variable that overrides the config root.
+pub const CONFIG_DIR_ENV: &str = "WOMP_CONFIG_DIR";
+/// If a file with this name sits next to the executable, WOMP runs in portable mode and
+/// keeps everything in [`PORTABLE_CONFIG_DIR`] beside it.
+pub const PORTABLE_MARKER: &str = "womp.portable";
+pub const PORTABLE_CONFIG_DIR: &str = "config";
+
+static CONFIG_DIR_OVERRIDE: RwLock<Option<PathBuf>> = RwLock::new(None);
+
+/// Makes every following call use `dir` as the config root, e.g. for `--config-dir`.
+/// Takes precedence over [`CONFIG_DIR_ENV`] and portable mode; `None` removes the override.
+pub fn set_config_dir_override(dir: Option<PathBuf>) {
+    let mut config_dir_override = CONFIG_DIR_OVERRIDE
+        .write()
+        .unwrap_or_else(|poisoned| poisoned.into_inner());
+    *config_dir_override = dir;
+}
+
+fn portable_config_dir() -> Option<PathBuf> {
+    let exe = std::env::current_exe().ok()?;
+    let exe_dir = exe.parent()?;
+    if exe_dir.join(PORTABLE_MARKER).is_file() {
+        Some(exe_dir.join(PORTABLE_CONFIG_DIR))
+    } else {
+        None
+    }
+}
+
+/// Resolves the config root without creating it, in order of precedence: the override set
+/// by [`set_config_dir_override`], [`CONFIG_DIR_ENV`], portable mode and finally the
+/// per-user config directory.
+pub fn resolve_config_dir() -> Result<PathBuf> {
+    let config_dir_override = CONFIG_DIR_OVERRIDE
+        .read()
+        .unwrap_or_else(|poisoned| poisoned.into_inner())
+        .clone();
+    if let Some(dir) = config_dir_override {
+        return Ok(dir);
+    }
+    if let Some(dir) = std::env::var_os(CONFIG_DIR_ENV).filter(|dir| !dir.is_empty()) {
+        return Ok(PathBuf::from(dir));
+    }
+    if let Some(dir) = portable_config_dir() {
+        return Ok(dir);
+    }
+    Ok(config_dir()
+        .ok_or(WompError::ConfigDirNotFound)?
+        .join("WOMP"))
+}
 
-pub fn get_config_dir() -> Result<PathBuf, String> {
-    let config_path = config_dir().expect("Couldn't get config dir!").join("WOMP");
-    std::fs::create_dir_all(&config_path)
-        .map_err(|e| format!("Failed to create config dir: {e}"))?;
+pub fn get_config_dir() -> Result<PathBuf> {
+    let config_path = resolve_config_dir()?;
+    fs::create_dir_all(&config_path).map_err(|e| WompError::io(&config_path, e))?;
     Ok(config_path)
 }
 
-pub fn get_profiles_dir() -> Result<PathBuf, String> {
-    let profiles_dir = get_config_dir()
-        .expect("Couldn't get config dir!")
-        .join("profiles");
-    std::fs::create_dir_all(&profiles_dir)
-        .map_err(|e| format!("Failed to create profiles dir: {e}"))?;
+pub fn get_profiles_dir() -> Result<PathBuf> {
+    let profiles_dir = get_config_dir()?.join("profiles");
+    fs::create_dir_all(&profiles_dir).map_err(|e| WompError::io(&profiles_dir, e))?;
     Ok(profiles_dir)
 }
 
-pub fn get_profiles_and_configs() -> Result<Vec<(String, Option<Config>)>, String> {
-    let profiles_dir = get_profiles_dir().unwrap();
-    let mut profiles = Vec::new();
-    for entry in std::fs::read_dir(profiles_dir).unwrap() {
-        let entry = entry.unwrap();
-        let path = entry.path();
-        let profile_name = path.file_name().unwrap().to_str().unwrap().to_string();
-        let config = match read_display_config(&profile_name) {
-            Ok(c) => Some(c),
-            Err(_) => None,
-        };
-        profiles.push((profile_name.clone(), config));
+/// Rejects profile names that would escape the profiles directory or that
+/// Windows can't use as a folder name.
+pub fn validate_profile_name(name: &str) -> Result<()> {
+    let invalid_chars = ['/', '\\', ':', '*', '?', '"', '<', '>', '|'];
+    if name.trim().is_empty()
+        || name == "."
+        || name == ".."
+        || name
+            .chars()
+            .any(|c| invalid_chars.contains(&c) || c.is_control())
+    {
+        return Err(WompError::Validation(format!(
+            "`{name}` is not a valid profile name"
+        )));
     }
-    Ok(profiles)
+    Ok(())
 }
 
-pub fn get_profile_dir(name: &String) -> Result<PathBuf, String> {
-    let profile_dir = get_profiles_dir()
-        .expect("Couldn't get profiles dir!")
-        .join(String::from(name));
-    Ok(profile_dir)
+pub fn get_profiles_and_configs() -> Result<Vec<(String, Option<Config>)>> {
+    FsProfileStore::open_default()?.list_with_configs()
 }
 
-pub fn get_config_file_path(name: &String) -> Result<PathBuf, String> {
-    let config_file = get_profile_dir(name)
-        .expect("Couldn't get profile dir!")
-        .join("profile.toml");
-    Ok(config_file)
+pub fn get_profile_dir(name: &str) -> Result<PathBuf> {
+    FsProfileStore::open_default()?.profile_dir(name)
 }
 
-pub fn get_display_layout_file_path(name: &String) -> Result<PathBuf, String> {
-    let display_layout_file = get_profile_dir(name)
-        .expect("Couldn't get profile dir!")
-        .join("displays.json");
-    Ok(display_layout_file)
+/// Like [`get_profile_dir`], but fails with [`WompError::ProfileNotFound`] if the profile
+/// doesn't exist yet.
+pub fn get_existing_profile_dir(name: &str) -> Result<PathBuf> {
+    FsProfileStore::open_default()?.existing_profile_dir(name)
 }
 
-pub fn read_display_config(profile_name: &String) -> Result<Config, String> {
-    let config_file = get_config_file_path(profile_name).expect("Couldn't get config file!");
-    // Check if the file exists
-    if !std::fs::metadata(&config_file).is_ok() {
-        return Err(format!(
-            "Config file `{}` does not exist.",
-            config_file.to_string_lossy()
-        ));
-    }
-    let contents = match fs::read_to_string(&config_file) {
-        Ok(c) => c,
-        Err(_) => {
-            eprintln!(
-                "Could not read config file `{}`.",
-                config_file.to_string_lossy()
-            );
-            return Err(format!(
-                "Config file `{}` does not exist.",
-                config_file.to_string_lossy()
-            ));
-        }
-    };
-
-    let config: Config = match toml::from_str(&contents) {
-        Ok(d) => d,
-        Err(_) => {
-            eprintln!(
-                "Could not parse data in config file `{}`, will skip.",
-                config_file.to_string_lossy()
-            );
-            return Err(format!(
-                "Invalid config in `{}`.",
-                config_file.to_string_lossy()
-            ));
-        }
-    };
-
-    Ok(config)
-}
-
-pub fn write_display_config(config: &Config, profile_name: &String) -> Result<(), String> {
-    let config_str = toml::to_string(&config).unwrap();
-    let config_file = get_config_file_path(profile_name).expect("Couldn't get config file!");
-    std::fs::create_dir_all(config_file.parent().unwrap())
-        .map_err(|e| format!("Failed to create profile dir: {e}"))?;
-    fs::write(config_file, config_str).map_err(|e| format!("Failed to write config file: {e}"))
-}
-
-pub fn rename_profile_folder(old_name: &String, new_name: &String) -> Result<(), String> {
-    let old_path = get_profile_dir(old_name).expect("Couldn't get old profile path!");
-    let new_path = old_path.parent().unwrap().join(new_name);
-    if std::fs::exists(&new_path).unwrap() {
-        return Err("New profile path already exists".to_string());
-    }
-    fs::rename(old_path, new_path).map_err(|e| format!("Failed to rename profile: {e}"))
-}
-
-pub fn delete_profile_dir(profile_name: &String) -> Result<(), String> {
-    let profile_path = get_profile_dir(profile_name).expect("Couldn't get profile path!");
-    fs::remove_dir_all(profile_path).map_err(|e| format!("Failed to delete profile: {e}"))
-}
-
-fn get_clone_dir(source_dir: &PathBuf) -> PathBuf {
-    let mut suffix = 1;
-    let mut clone_dir;
-    loop {
-        let suffix_str = if suffix == 1 {
-            "".to_string()
-        } else {
-            format!("_{}", suffix)
-        };
-        clone_dir = source_dir.parent().unwrap().join(format!(
-            "{}_clone{}",
-            source_dir.file_name().unwrap().to_string_lossy(),
-            suffix_str
-        ));
-        if !std::fs::metadata(&clone_dir).is_err() {
-            suffix += 1;
-            continue;
-        }
-        break;
-    }
-    clone_dir
+pub fn get_config_file_path(name: &str) -> Result<PathBuf> {
+    Ok(get_profile_dir(name)?.join(CONFIG_FILE_NAME))
 }
 
-pub fn clone_profile_dir(profile_name: &String) -> Result<String, String> {
-    let profile_dir = get_profile_dir(profile_name).expect("Couldn't get profile path!");
-    let clone_dir = get_clone_dir(&profile_dir);
+pub fn get_display_layout_file_path(name: &str) -> Result<PathBuf> {
+    Ok(get_profile_dir(name)?.join(LAYOUT_FILE_NAME))
+}
 
-    let status = std::process::Command::new("cmd")
-        .raw_arg(format!(
-            r#"/c xcopy "{}" "{}" /E /I /H /Y"#,
-            profile_dir.to_string_lossy(),
-            clone_dir.to_string_lossy()
-        ))
-        .status()
-        .map_err(|e| format!("Failed to execute xcopy command: {e}"))?;
+pub fn read_display_config(profile_name: &str) -> Result<Config> {
+    FsProfileStore::open_default()?.load_config(profile_name)
+}
 
-    if status.success() {
-        Ok(clone_dir.file_name().unwrap().to_string_lossy().to_string())
-    } else {
-        Err(format!(
-            "Failed to clone profile: xcopy exited with status {}",
-            status
-        ))
-    }
+pub fn write_display_config(config: &Config, profile_name: &str) -> Result<()> {
+    FsProfileStore::open_default()?.save_config(profile_name, config)
 }
 
-pub fn open_profile_dir(profile_name: &String) -> Result<(), String> {
-    let profile_dir = get_profile_dir(profile_name).expect("Couldn't get profile path!");
-    println!("Opening profile folder: {}", profile_dir.to_string_lossy());
-    match std::process::Command::new("cmd")
-        .raw_arg(format!(
-            r#"/c start "" "{}""#,
-            profile_dir.to_string_lossy()
-        ))
+pub fn rename_profile_folder(old_name: &str, new_name: &str) -> Result<()> {
+    FsProfileStore::open_default()?.rename(old_name, new_name)
+}
+
+pub fn delete_profile_dir(profile_name: &str) -> Result<()> {
+    FsProfileStore::open_default()?.delete(profile_name)
+}
+
+pub fn clone_profile_dir(profile_name: &str) -> Result<String> {
+    FsProfileStore::open_default()?.clone_profile(profile_name)
+}
+
+pub fn export_profile_bundle(profile_name: &str, destination: &Path) -> Result<BundleManifest> {
+    bundle::export_profile(&FsProfileStore::open_default()?, profile_name, destination)
+}
+
+/// Returns the name the profile was imported as.
+pub fn import_profile_bundle(source: &Path) -> Result<String> {
+    bundle::import_profile(&mut FsProfileStore::open_default()?, source)
+}
+
+#[cfg(windows)]
+fn open_in_file_manager(dir: &Path) -> std::io::Result<std::process::Child> {
+    std::process::Command::new("cmd")
+        .raw_arg(format!(r#"/c start "" "{}""#, dir.to_string_lossy()))
         .spawn()
-    {
-        Ok(_) => Ok(()),
-        Err(e) => Err(format!("Failed to open profile folder: {e}")),
-    }
+}
+
+#[cfg(target_os = "macos")]
+fn open_in_file_manager(dir: &Path) -> std::io::Result<std::process::Child> {
+    std::process::Command::new("open").arg(dir).spawn()
+}
+
+#[cfg(not(any(windows, target_os = "macos")))]
+fn open_in_file_manager(dir: &Path) -> std::io::Result<std::process::Child> {
+    std::process::Command::new("xdg-open").arg(dir).spawn()
+}
+
+pub fn open_profile_dir(profile_name: &str) -> Result<()> {
+    let profile_dir = get_existing_profile_dir(profile_name)?;
+    log::info!(path:% = profile_dir.display(); "Opening profile folder");
+    open_in_file_manager(&profile_dir)
+        .map(|_| ())
+        .map_err(|e| WompError::io(&profile_dir, e))
 }
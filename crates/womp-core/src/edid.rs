@@ -0,0 +1,567 @@
+//! What a monitor's EDID says about it.
+//!
+//! Windows only hands out the manufacturer and product ids of the EDID, as raw numbers.
+//! The manufacturer id is the monitor vendor's three-letter PNP id packed into five bits
+//! per letter, e.g. `DEL` for Dell. If the backend can read the raw EDID, its base block
+//! adds the serial number, manufacture date, physical size and the modes the monitor
+//! supports. Extension blocks are ignored.
+
+use crate::model::EdidIds;
+use log::debug;
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+
+const HEADER: [u8; 8] = [0x00, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0x00];
+const BLOCK_SIZE: usize = 128;
+const DESCRIPTORS: [usize; 4] = [54, 72, 90, 108];
+// Display descriptor tags
+const SERIAL_DESCRIPTOR: u8 = 0xFF;
+
+/// PNP ids of well-known monitor and panel makers.
+const VENDORS: &[(&str, &str)] = &[
+    ("AAC", "Acer"),
+    ("ACI", "ASUS"),
+    ("ACR", "Acer"),
+    ("AOC", "AOC"),
+    ("APP", "Apple"),
+    ("AUO", "AU Optronics"),
+    ("AUS", "ASUS"),
+    ("BNQ", "BenQ"),
+    ("BOE", "BOE"),
+    ("CMN", "Innolux"),
+    ("CMO", "Chi Mei"),
+    ("CPQ", "Compaq"),
+    ("DEL", "Dell"),
+    ("ENC", "EIZO"),
+    ("EIZ", "EIZO"),
+    ("FUS", "Fujitsu"),
+    ("GBT", "Gigabyte"),
+    ("GSM", "LG"),
+    ("HEC", "Hisense"),
+    ("HPN", "HP"),
+    ("HSD", "HannStar"),
+    ("HWP", "HP"),
+    ("IVM", "iiyama"),
+    ("LEN", "Lenovo"),
+    ("LGD", "LG Display"),
+    ("MEI", "Panasonic"),
+    ("MSI", "MSI"),
+    ("MS_", "Microsoft"),
+    ("NEC", "NEC"),
+    ("PHL", "Philips"),
+    ("SAM", "Samsung"),
+    ("SDC", "Samsung Display"),
+    ("SEC", "Seiko Epson"),
+    ("SHP", "Sharp"),
+    ("SNY", "Sony"),
+    ("TCL", "TCL"),
+    ("TSB", "Toshiba"),
+    ("VIZ", "Vizio"),
+    ("VSC", "ViewSonic"),
+    ("XMI", "Xiaomi"),
+];
+
+/// Modes of the established timings bitmap, most significant bit of byte 35 first.
+/// `None` for the interlaced 1024x768 mode and the reserved bits.
+const ESTABLISHED_TIMINGS: [Option<(u32, u32, u32)>; 17] = [
+    Some((720, 400, 70)),
+    Some((720, 400, 88)),
+    Some((640, 480, 60)),
+    Some((640, 480, 67)),
+    Some((640, 480, 72)),
+    Some((640, 480, 75)),
+    Some((800, 600, 56)),
+    Some((800, 600, 60)),
+    Some((800, 600, 72)),
+    Some((800, 600, 75)),
+    Some((832, 624, 75)),
+    None,
+    Some((1024, 768, 60)),
+    Some((1024, 768, 70)),
+    Some((1024, 768, 75)),
+    Some((1280, 1024, 75)),
+    Some((1152, 870, 75)),
+];
+
+/// A mode the monitor supports.
+#[derive(Serialize, Deserialize, JsonSchema, Debug, Clone, Copy, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct EdidTiming {
+    pub width: u32,
+    pub height: u32,
+    /// In Hz, rounded to two decimals.
+    pub refresh_rate: f64,
+}
+
+impl EdidTiming {
+    fn new(width: u32, height: u32, refresh_rate: f64) -> Self {
+        EdidTiming {
+            width,
+            height,
+            refresh_rate: (refresh_rate * 100.0).round() / 100.0,
+        }
+    }
+}
+
+impl std::fmt::Display for EdidTiming {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "{}x{} @ {:.2} Hz",
+            self.width, self.height, self.refresh_rate
+        )
+    }
+}
+
+/// Size of the visible area.
+#[derive(Serialize, Deserialize, JsonSchema, Debug, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "camelCase")]
+pub struct PhysicalSize {
+    pub width_cm: u8,
+    pub height_cm: u8,
+}
+
+/// The monitor as its EDID describes it. Only the manufacturer and product code are known
+/// without the raw EDID.
+#[derive(Serialize, Deserialize, JsonSchema, Debug, Clone, PartialEq, Default)]
+#[serde(rename_all = "camelCase")]
+pub struct EdidInfo {
+    /// Three-letter PNP id of the manufacturer, e.g. `DEL`.
+    pub manufacturer: String,
+    /// Name of the manufacturer, if it's a well-known one.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub vendor: Option<String>,
+    pub product_code: u16,
+    /// EDID version, e.g. `1.4`.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub version: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub serial_number: Option<u32>,
+    /// Serial number as printed on the monitor, if it has a serial descriptor.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub serial: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub manufacture_week: Option<u8>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub manufacture_year: Option<u16>,
+    /// Set instead of the manufacture date by some monitors.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub model_year: Option<u16>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub size: Option<PhysicalSize>,
+    /// The preferred mode, the panel's native resolution on flat panels.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub native_mode: Option<EdidTiming>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub supported_timings: Vec<EdidTiming>,
+}
+
+fn letters(word: u16) -> Option<String> {
+    if word & 0x8000 != 0 {
+        return None;
+    }
+    [10, 5, 0]
+        .iter()
+        .map(|shift| {
+            let letter = ((word >> shift) & 0x1F) as u8;
+            (1..=26)
+                .contains(&letter)
+                .then(|| (b'A' + letter - 1) as char)
+        })
+        .collect()
+}
+
+/// The three-letter PNP id packed into `manufacturer_id`, e.g. `DEL`.
+pub fn pnp_id(manufacturer_id: u16) -> Option<String> {
+    // Windows reports the two bytes of the EDID swapped, take it as is if that's no id
+    letters(manufacturer_id.swap_bytes()).or_else(|| letters(manufacturer_id))
+}
+
+/// Name of the manufacturer with the PNP id `pnp_id`, if it's a well-known one.
+pub fn vendor_name(pnp_id: &str) -> Option<&'static str> {
+    VENDORS
+        .iter()
+        .find(|(id, _)| id.eq_ignore_ascii_case(pnp_id))
+        .map(|&(_, name)| name)
+}
+
+/// Registry key, under `HKEY_LOCAL_MACHINE`, whose `EDID` value has the raw EDID of the
+/// monitor with the device interface path `device_path`.
+pub fn registry_key(device_path: &str) -> Option<String> {
+    // \\?\DISPLAY#DEL41B3#5&2f2b2d1d&0&UID4353#{e6f07b5f-ee97-4a90-b076-33f57bf4eaa7}
+    let path = device_path.strip_prefix(r"\\?\").unwrap_or(device_path);
+    let parts: Vec<&str> = path.split('#').collect();
+    match parts[..] {
+        [class, hardware_id, instance, ..] if !hardware_id.is_empty() && !instance.is_empty() => {
+            Some(format!(
+                r"SYSTEM\CurrentControlSet\Enum\{class}\{hardware_id}\{instance}\Device Parameters"
+            ))
+        }
+        _ => None,
+    }
+}
+
+fn detailed_timing(descriptor: &[u8]) -> Option<EdidTiming> {
+    let pixel_clock = u16::from_le_bytes([descriptor[0], descriptor[1]]) as f64 * 10_000.0;
+    let h_active = descriptor[2] as u32 | ((descriptor[4] as u32 & 0xF0) << 4);
+    let h_blank = descriptor[3] as u32 | ((descriptor[4] as u32 & 0x0F) << 8);
+    let v_active = descriptor[5] as u32 | ((descriptor[7] as u32 & 0xF0) << 4);
+    let v_blank = descriptor[6] as u32 | ((descriptor[7] as u32 & 0x0F) << 8);
+    let interlaced = descriptor[17] & 0x80 != 0;
+    let total = (h_active + h_blank) * (v_active + v_blank);
+    if total == 0 || interlaced {
+        return None;
+    }
+    Some(EdidTiming::new(
+        h_active,
+        v_active,
+        pixel_clock / total as f64,
+    ))
+}
+
+fn descriptor_text(descriptor: &[u8]) -> Option<String> {
+    let text: Vec<u8> = descriptor[5..18]
+        .iter()
+        .copied()
+        .take_while(|&b| b != 0x0A)
+        .collect();
+    let text = String::from_utf8_lossy(&text).trim().to_string();
+    (!text.is_empty()).then_some(text)
+}
+
+fn standard_timing(bytes: &[u8], version: (u8, u8)) -> Option<EdidTiming> {
+    if bytes == [0x01, 0x01] || bytes[0] == 0 {
+        return None;
+    }
+    let width = (bytes[0] as u32 + 31) * 8;
+    let height = match bytes[1] >> 6 {
+        // 1:1 before EDID 1.3
+        0 if version < (1, 3) => width,
+        0 => width * 10 / 16,
+        1 => width * 3 / 4,
+        2 => width * 4 / 5,
+        _ => width * 9 / 16,
+    };
+    Some(EdidTiming::new(
+        width,
+        height,
+        ((bytes[1] & 0x3F) + 60) as f64,
+    ))
+}
+
+/// Everything the base block of the raw EDID `blob` has to say.
+fn parse(blob: &[u8]) -> std::result::Result<EdidInfo, &'static str> {
+    let Some(block) = blob.get(..BLOCK_SIZE) else {
+        return Err("shorter than a block");
+    };
+    if block[..8] != HEADER {
+        return Err("no EDID header");
+    }
+    if block.iter().fold(0u8, |sum, &b| sum.wrapping_add(b)) != 0 {
+        return Err("wrong checksum");
+    }
+
+    let manufacturer_id = u16::from_be_bytes([block[8], block[9]]);
+    let manufacturer = letters(manufacturer_id).ok_or("invalid manufacturer id")?;
+    let version = (block[18], block[19]);
+    let mut info = EdidInfo {
+        vendor: vendor_name(&manufacturer).map(str::to_string),
+        manufacturer,
+        product_code: u16::from_le_bytes([block[10], block[11]]),
+        version: Some(format!("{}.{}", version.0, version.1)),
+        serial_number: Some(u32::from_le_bytes([
+            block[12], block[13], block[14], block[15],
+        ]))
+        .filter(|&serial| serial != 0),
+        ..Default::default()
+    };
+    let year = 1990 + block[17] as u16;
+    match block[16] {
+        0xFF => info.model_year = Some(year),
+        0 => info.manufacture_year = Some(year),
+        week => {
+            info.manufacture_week = Some(week);
+            info.manufacture_year = Some(year);
+        }
+    }
+    if block[21] != 0 && block[22] != 0 {
+        info.size = Some(PhysicalSize {
+            width_cm: block[21],
+            height_cm: block[22],
+        });
+    }
+
+    let established = u32::from_be_bytes([block[35], block[36], block[37], 0]);
+    let mut timings: Vec<EdidTiming> = (0..ESTABLISHED_TIMINGS.len())
+        .filter(|bit| established & (1 << (31 - bit)) != 0)
+        .filter_map(|bit| ESTABLISHED_TIMINGS[bit])
+        .map(|(width, height, refresh_rate)| EdidTiming::new(width, height, refresh_rate as f64))
+        .collect();
+    timings.extend(
+        block[38..54]
+            .chunks(2)
+            .filter_map(|bytes| standard_timing(bytes, version)),
+    );
+    for &offset in &DESCRIPTORS {
+        let descriptor = &block[offset..offset + 18];
+        if descriptor[0] != 0 || descriptor[1] != 0 {
+            let Some(timing) = detailed_timing(descriptor) else {
+                continue;
+            };
+            // The first detailed timing is the preferred one
+            info.native_mode.get_or_insert(timing);
+            timings.push(timing);
+        } else if descriptor[3] == SERIAL_DESCRIPTOR {
+            info.serial = descriptor_text(descriptor);
+        }
+    }
+    for timing in timings {
+        if !info.supported_timings.contains(&timing) {
+            info.supported_timings.push(timing);
+        }
+    }
+    Ok(info)
+}
+
+impl EdidInfo {
+    /// What the ids Windows reports tell, `None` if they aren't an EDID's.
+    pub fn from_ids(ids: EdidIds) -> Option<EdidInfo> {
+        let manufacturer = pnp_id(ids.manufacturer_id)?;
+        Some(EdidInfo {
+            vendor: vendor_name(&manufacturer).map(str::to_string),
+            manufacturer,
+            product_code: ids.product_code,
+            ..Default::default()
+        })
+    }
+
+    /// Describes the monitor with `ids`, with the details of its raw EDID `blob` if there's
+    /// one and it's valid.
+    pub fn decode(ids: EdidIds, blob: Option<&[u8]>) -> Option<EdidInfo> {
+        let from_ids = EdidInfo::from_ids(ids);
+        let parsed = match blob.map(parse) {
+            Some(Ok(parsed)) => parsed,
+            Some(Err(reason)) => {
+                debug!(reason; "Ignoring raw EDID");
+                return from_ids;
+            }
+            None => return from_ids,
+        };
+        // The EDID has to be the one of the monitor Windows reports
+        match &from_ids {
+            Some(info)
+                if info.manufacturer != parsed.manufacturer
+                    || info.product_code != parsed.product_code =>
+            {
+                debug!(
+                    reported = info.hardware_id().as_str(),
+                    edid = parsed.hardware_id().as_str();
+                    "Ignoring raw EDID of another monitor"
+                );
+                from_ids
+            }
+            _ => Some(parsed),
+        }
+    }
+
+    /// The id Windows uses for the model in device paths, e.g. `DEL41B3`.
+    pub fn hardware_id(&self) -> String {
+        format!("{}{:04X}", self.manufacturer, self.product_code)
+    }
+
+    /// One line for people, e.g. `Dell DEL41B3, serial ABC123, 60x34 cm, made 2019`.
+    pub fn summary(&self) -> String {
+        let mut summary = match &self.vendor {
+            Some(vendor) => format!("{vendor} {}", self.hardware_id()),
+            None => self.hardware_id(),
+        };
+        if let Some(serial) = &self.serial {
+            summary += &format!(", serial {serial}");
+        } else if let Some(serial_number) = self.serial_number {
+            summary += &format!(", serial {serial_number}");
+        }
+        if let Some(size) = self.size {
+            summary += &format!(", {}x{} cm", size.width_cm, size.height_cm);
+        }
+        match (
+            self.manufacture_week,
+            self.manufacture_year,
+            self.model_year,
+        ) {
+            (Some(week), Some(year), _) => summary += &format!(", made week {week} of {year}"),
+            (None, Some(year), _) => summary += &format!(", made {year}"),
+            (_, _, Some(year)) => summary += &format!(", model year {year}"),
+            _ => {}
+        }
+        if let Some(native_mode) = self.native_mode {
+            summary += &format!(", native {native_mode}");
+        }
+        summary
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Base block of a 27" 4K Dell: EDID 1.4, `DEL41B3`, made week 20 of 2020, 60x34 cm,
+    /// serial descriptor `ABC1234`, 3840x2160 @ 60 Hz preferred and one extension block.
+    const DELL_U2720Q: [u8; 128] = [
+        0x00, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0x00, 0x10, 0xAC, 0xB3, 0x41, 0x4C, 0x4A, 0x4B,
+        0x30, 0x14, 0x1E, 0x01, 0x04, 0xB5, 0x3C, 0x22, 0x78, 0x3A, 0xEE, 0x95, 0xA3, 0x54, 0x4C,
+        0x99, 0x26, 0x0F, 0x50, 0x54, 0xA5, 0x4B, 0x00, 0xD1, 0xC0, 0x81, 0x80, 0x81, 0xC0, 0x81,
+        0x00, 0xA9, 0xC0, 0xB3, 0x00, 0x95, 0x00, 0x01, 0x01, 0x4D, 0xD0, 0x00, 0xA0, 0xF0, 0x70,
+        0x3E, 0x80, 0x30, 0x20, 0x35, 0x00, 0x54, 0x4F, 0x21, 0x00, 0x00, 0x1A, 0x00, 0x00, 0x00,
+        0xFF, 0x00, 0x41, 0x42, 0x43, 0x31, 0x32, 0x33, 0x34, 0x0A, 0x20, 0x20, 0x20, 0x20, 0x20,
+        0x00, 0x00, 0x00, 0xFD, 0x00, 0x18, 0x4B, 0x1E, 0x8C, 0x3C, 0x00, 0x0A, 0x20, 0x20, 0x20,
+        0x20, 0x20, 0x20, 0x00, 0x00, 0x00, 0xFC, 0x00, 0x44, 0x45, 0x4C, 0x4C, 0x20, 0x55, 0x32,
+        0x37, 0x32, 0x30, 0x51, 0x0A, 0x20, 0x01, 0xBA,
+    ];
+
+    /// The ids as Windows reports them for [`DELL_U2720Q`].
+    const DELL_IDS: EdidIds = EdidIds {
+        manufacturer_id: 0xAC10,
+        product_code: 0x41B3,
+    };
+
+    fn timing(width: u32, height: u32, refresh_rate: f64) -> EdidTiming {
+        EdidTiming::new(width, height, refresh_rate)
+    }
+
+    #[test]
+    fn parses_a_base_block() {
+        let info = parse(&DELL_U2720Q).unwrap();
+        assert_eq!(info.hardware_id(), "DEL41B3");
+        assert_eq!(info.vendor.as_deref(), Some("Dell"));
+        assert_eq!(info.version.as_deref(), Some("1.4"));
+        assert_eq!(info.serial_number, Some(0x304B4A4C));
+        assert_eq!(info.serial.as_deref(), Some("ABC1234"));
+        assert_eq!(
+            (
+                info.manufacture_week,
+                info.manufacture_year,
+                info.model_year
+            ),
+            (Some(20), Some(2020), None)
+        );
+        assert_eq!(
+            info.size,
+            Some(PhysicalSize {
+                width_cm: 60,
+                height_cm: 34
+            })
+        );
+        assert_eq!(info.native_mode, Some(timing(3840, 2160, 60.0)));
+        assert_eq!(
+            info.summary(),
+            "Dell DEL41B3, serial ABC1234, 60x34 cm, made week 20 of 2020, \
+             native 3840x2160 @ 60.00 Hz"
+        );
+    }
+
+    #[test]
+    fn lists_established_then_standard_then_detailed_timings() {
+        let info = parse(&DELL_U2720Q).unwrap();
+        assert_eq!(
+            info.supported_timings,
+            [
+                // Established, 0xA5 0x4B
+                timing(720, 400, 70.0),
+                timing(640, 480, 60.0),
+                timing(640, 480, 75.0),
+                timing(800, 600, 60.0),
+                timing(800, 600, 75.0),
+                timing(1024, 768, 60.0),
+                timing(1024, 768, 75.0),
+                timing(1280, 1024, 75.0),
+                // Standard, the unused 0x01 0x01 slot is skipped
+                timing(1920, 1080, 60.0),
+                timing(1280, 1024, 60.0),
+                timing(1280, 720, 60.0),
+                timing(1280, 800, 60.0),
+                timing(1600, 900, 60.0),
+                timing(1680, 1050, 60.0),
+                timing(1440, 900, 60.0),
+                // Detailed
+                timing(3840, 2160, 60.0),
+            ]
+        );
+    }
+
+    #[test]
+    fn standard_timing_aspect_ratios() {
+        let v1_4 = (1, 4);
+        assert_eq!(
+            standard_timing(&[0x81, 0x00], v1_4),
+            Some(timing(1280, 800, 60.0))
+        );
+        assert_eq!(
+            standard_timing(&[0x81, 0x40], v1_4),
+            Some(timing(1280, 960, 60.0))
+        );
+        assert_eq!(
+            standard_timing(&[0x81, 0x80], v1_4),
+            Some(timing(1280, 1024, 60.0))
+        );
+        assert_eq!(
+            standard_timing(&[0x81, 0xCF], v1_4),
+            Some(timing(1280, 720, 75.0))
+        );
+        // 1:1 before EDID 1.3
+        assert_eq!(
+            standard_timing(&[0x81, 0x00], (1, 2)),
+            Some(timing(1280, 1280, 60.0))
+        );
+        assert_eq!(standard_timing(&[0x01, 0x01], v1_4), None);
+    }
+
+    #[test]
+    fn pnp_id_takes_both_byte_orders() {
+        // Windows reports the EDID bytes swapped
+        assert_eq!(pnp_id(0xAC10).as_deref(), Some("DEL"));
+        assert_eq!(pnp_id(0x10AC).as_deref(), Some("DEL"));
+        assert_eq!(pnp_id(0x6D1E).as_deref(), Some("GSM"));
+        assert_eq!(pnp_id(0), None);
+    }
+
+    #[test]
+    fn rejects_broken_blobs() {
+        assert_eq!(parse(&DELL_U2720Q[..100]), Err("shorter than a block"));
+
+        let mut no_header = DELL_U2720Q;
+        no_header[0] = 0x01;
+        no_header[127] = no_header[127].wrapping_sub(1);
+        assert_eq!(parse(&no_header), Err("no EDID header"));
+
+        let mut bad_checksum = DELL_U2720Q;
+        bad_checksum[127] ^= 0xFF;
+        assert_eq!(parse(&bad_checksum), Err("wrong checksum"));
+    }
+
+    #[test]
+    fn decode_falls_back_to_the_ids() {
+        let from_ids = EdidInfo::from_ids(DELL_IDS);
+        assert_eq!(
+            EdidInfo::decode(DELL_IDS, Some(&DELL_U2720Q[..64])),
+            from_ids
+        );
+        assert_eq!(EdidInfo::decode(DELL_IDS, None), from_ids);
+        assert_eq!(
+            EdidInfo::decode(DELL_IDS, Some(&DELL_U2720Q))
+                .unwrap()
+                .serial
+                .as_deref(),
+            Some("ABC1234")
+        );
+
+        // The EDID of another monitor
+        let other = EdidIds {
+            product_code: 0x41B4,
+            ..DELL_IDS
+        };
+        assert_eq!(
+            EdidInfo::decode(other, Some(&DELL_U2720Q)),
+            EdidInfo::from_ids(other)
+        );
+    }
+}
@@ -0,0 +1,200 @@
+use crate::comparison::Difference;
+use crate::report::ApplyReport;
+use crate::snapshot::Snapshot;
+use serde::Serialize;
+use std::path::{Path, PathBuf};
+
+type BoxedError = Box<dyn std::error::Error + Send + Sync>;
+
+/// Every error womp-core can return.
+#[derive(Debug, thiserror::Error)]
+pub enum WompError {
+    #[error("Couldn't access `{}`: {source}", path.display())]
+    Io {
+        path: PathBuf,
+        #[source]
+        source: std::io::Error,
+    },
+    #[error("Couldn't parse `{}`: {source}", path.display())]
+    Parse {
+        path: PathBuf,
+        #[source]
+        source: BoxedError,
+    },
+    #[error("Couldn't serialize {what}: {source}")]
+    Serialize {
+        what: String,
+        #[source]
+        source: BoxedError,
+    },
+    #[error("Couldn't determine the config directory")]
+    ConfigDirNotFound,
+    #[error("Profile `{0}` does not exist")]
+    ProfileNotFound(String),
+    #[error("Profile `{0}` already exists")]
+    ProfileExists(String),
+    #[error("{operation}: Win32 error {code}")]
+    Win32 { operation: String, code: u32 },
+    #[error("{operation}: HRESULT {hresult:#010x}")]
+    Com { operation: String, hresult: i32 },
+    #[error("{0}")]
+    Backend(String),
+    #[error("Capturing and applying display layouts is not supported on {0}")]
+    UnsupportedBackend(String),
+    #[error("Failed to run `{stage}` command: {source}")]
+    Hook {
+        stage: String,
+        #[source]
+        source: std::io::Error,
+    },
+    #[error(
+        "`{}` has format version {found}, this version of WOMP only supports up to {supported}",
+        path.display()
+    )]
+    UnsupportedVersion {
+        path: PathBuf,
+        found: u32,
+        supported: u32,
+    },
+    #[error("{0}")]
+    Validation(String),
+    #[error("{}", apply_failed(report))]
+    ApplyFailed {
+        report: Box<ApplyReport>,
+        /// The layout from before if the displays were left changed, to confirm or revert
+        /// like after applying successfully.
+        snapshot: Option<Box<Snapshot>>,
+    },
+    #[error("{}", describe_differences(.0))]
+    DisplaysDiffer(Vec<Difference>),
+    #[error("Could not restore the previous layout, couldn't set {}", describe_failures(.0))]
+    RevertFailed(Vec<StepFailure>),
+    #[error("{context}: {source}")]
+    Context {
+        context: String,
+        #[source]
+        source: Box<WompError>,
+    },
+}
+
+pub type Result<T, E = WompError> = std::result::Result<T, E>;
+
+/// A step of applying a profile that failed.
+#[derive(Serialize, Debug)]
+pub struct StepFailure {
+    /// What the step was setting, e.g. "the DPI scale of `DELL U2720Q`".
+    pub step: String,
+    pub error: WompError,
+}
+
+/// What applying a profile did after a step failed, see `on_failure` in `profile.toml`.
+#[derive(Serialize, Debug)]
+#[serde(tag = "outcome", content = "failures", rename_all = "snake_case")]
+pub enum AfterFailure {
+    /// The other steps were applied anyway.
+    KeptGoing,
+    /// Everything was set back to how it was before applying.
+    RolledBack,
+    /// Setting things back failed too, the displays are in between.
+    RollbackFailed(Vec<StepFailure>),
+}
+
+fn describe_failures(failures: &[StepFailure]) -> String {
+    failures
+        .iter()
+        .map(|failure| format!("{}: {}", failure.step, failure.error))
+        .collect::<Vec<_>>()
+        .join("; ")
+}
+
+fn describe_differences(differences: &[Difference]) -> String {
+    differences
+        .iter()
+        .map(ToString::to_string)
+        .collect::<Vec<_>>()
+        .join("; ")
+}
+
+fn apply_failed(report: &ApplyReport) -> String {
+    let failed = report
+        .failures()
+        .map(|(step, error)| format!("{step}: {error}"))
+        .collect::<Vec<_>>()
+        .join("; ");
+    match &report.after {
+        None | Some(AfterFailure::KeptGoing) => {
+            format!("Failed to set {failed}. Everything else was applied")
+        }
+        Some(AfterFailure::RolledBack) => {
+            format!("Failed to set {failed}. The previous layout was restored")
+        }
+        Some(AfterFailure::RollbackFailed(restore)) => format!(
+            "Failed to set {failed}. Restoring the previous layout failed too, couldn't set {}",
+            describe_failures(restore)
+        ),
+    }
+}
+
+impl WompError {
+    pub fn io(path: impl AsRef<Path>, source: std::io::Error) -> Self {
+        WompError::Io {
+            path: path.as_ref().to_path_buf(),
+            source,
+        }
+    }
+
+    pub fn parse(path: impl AsRef<Path>, source: impl Into<BoxedError>) -> Self {
+        WompError::Parse {
+            path: path.as_ref().to_path_buf(),
+            source: source.into(),
+        }
+    }
+
+    pub fn serialize(what: impl Into<String>, source: impl Into<BoxedError>) -> Self {
+        WompError::Serialize {
+            what: what.into(),
+            source: source.into(),
+        }
+    }
+
+    pub fn win32(operation: impl Into<String>, code: u32) -> Self {
+        WompError::Win32 {
+            operation: operation.into(),
+            code,
+        }
+    }
+
+    pub fn com(operation: impl Into<String>, hresult: i32) -> Self {
+        WompError::Com {
+            operation: operation.into(),
+            hresult,
+        }
+    }
+
+    /// Whether the error is a missing file, possibly wrapped in context.
+    pub fn is_not_found(&self) -> bool {
+        match self {
+            WompError::Io { source, .. } => source.kind() == std::io::ErrorKind::NotFound,
+            WompError::Context { source, .. } => source.is_not_found(),
+            _ => false,
+        }
+    }
+
+    /// Wraps the error with a description of what was being attempted.
+    pub fn context(self, context: impl Into<String>) -> Self {
+        WompError::Context {
+            context: context.into(),
+            source: Box::new(self),
+        }
+    }
+}
+
+// Errors cross the Tauri IPC boundary as plain strings
+impl Serialize for WompError {
+    fn serialize<S>(&self, serializer: S) -> std::result::Result<S::Ok, S::Error>
+    where
+        S: serde::Serializer,
+    {
+        serializer.serialize_str(self.to_string().as_str())
+    }
+}
@@ -0,0 +1,394 @@
+//! Recognising monitors across reboots, port changes and docks.
+//!
+//! A saved display refers to its monitor by adapter LUID and target id, but those belong
+//! to the output the monitor is plugged into: LUIDs change on every boot, target ids when
+//! the monitor moves to another port or a dock enumerates differently. Before a layout is
+//! applied, its displays are matched to the connected monitors by what the monitor *is*
+//! and rewritten to where it's connected now.
+
+use crate::error::Result;
+use crate::model::{EdidIds, OutputTechnology, wide_to_string};
+use crate::serde_types::{Display, DisplayLayout};
+use crate::win32_types::*;
+use log::debug;
+
+/// What a monitor is, as opposed to where it's connected.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct MonitorIdentity {
+    /// Zero if the monitor has no EDID.
+    pub edid: EdidIds,
+    /// Device interface path of the monitor, contains the EDID ids and an instance id.
+    pub device_path: String,
+    pub connector_instance: u32,
+    pub output_technology: OutputTechnology,
+    /// Friendly name from the EDID, e.g. `DELL U2720Q`.
+    pub name: String,
+}
+
+// Weights of the matching fields, each outranks all below it combined
+const DEVICE_PATH: u32 = 32;
+const EDID: u32 = 16;
+const NAME: u32 = 8;
+const OUTPUT_TECHNOLOGY: u32 = 4;
+const CONNECTOR_INSTANCE: u32 = 2;
+const TARGET_ID: u32 = 1;
+
+impl MonitorIdentity {
+    pub fn of(target_name: &DISPLAYCONFIG_TARGET_DEVICE_NAME) -> Self {
+        MonitorIdentity {
+            edid: EdidIds {
+                manufacturer_id: target_name.edidManufactureId,
+                product_code: target_name.edidProductCodeId,
+            },
+            device_path: wide_to_string(&target_name.monitorDevicePath),
+            connector_instance: target_name.connectorInstance,
+            output_technology: target_name.outputTechnology.0.into(),
+            name: wide_to_string(&target_name.monitorFriendlyDeviceName),
+        }
+    }
+
+    fn has_edid(&self) -> bool {
+        self.edid != EdidIds::default()
+    }
+
+    /// Whether there's anything to recognise the monitor by, virtual and some built-in
+    /// displays have neither an EDID nor a name.
+    fn is_anonymous(&self) -> bool {
+        self.device_path.is_empty() && !self.has_edid() && self.name.is_empty()
+    }
+
+    /// How much `self` and `other` look like the same monitor, 0 if they can't be. The
+    /// device path, EDID ids or name have to agree, the connector only breaks ties.
+    fn score(&self, other: &MonitorIdentity) -> u32 {
+        // Different models
+        if self.has_edid() && other.has_edid() && self.edid != other.edid {
+            return 0;
+        }
+        let same_path = !self.device_path.is_empty()
+            && self.device_path.eq_ignore_ascii_case(&other.device_path);
+        let mut score = 0;
+        if same_path {
+            score += DEVICE_PATH;
+        }
+        if self.has_edid() && self.edid == other.edid {
+            score += EDID;
+        }
+        if !self.name.is_empty() && self.name == other.name {
+            score += NAME;
+        }
+        if score == 0 {
+            return 0;
+        }
+        if self.output_technology == other.output_technology {
+            score += OUTPUT_TECHNOLOGY;
+        }
+        if self.connector_instance == other.connector_instance {
+            score += CONNECTOR_INSTANCE;
+        }
+        score
+    }
+}
+
+/// A monitor and the output it's, or was, connected to.
+#[derive(Debug, Clone)]
+pub struct KnownMonitor {
+    pub identity: MonitorIdentity,
+    pub adapter_id: LUID,
+    pub target_id: u32,
+}
+
+impl KnownMonitor {
+    pub fn of_display(display: &Display) -> Self {
+        KnownMonitor {
+            identity: MonitorIdentity::of(&display.additionalInfo.target),
+            adapter_id: display.pathInfo.targetInfo.adapterId,
+            target_id: display.pathInfo.targetInfo.id,
+        }
+    }
+
+    fn score(&self, other: &KnownMonitor) -> u32 {
+        let same_target = u32::from(self.target_id == other.target_id);
+        if self.identity.is_anonymous() && other.identity.is_anonymous() {
+            // Nothing else to go by, that's all the old target id matching did too
+            return same_target * TARGET_ID;
+        }
+        match self.identity.score(&other.identity) {
+            0 => 0,
+            score => score + same_target * TARGET_ID,
+        }
+    }
+}
+
+/// Pairs monitors of `saved` with the monitors of `connected` they most likely are, best
+/// matches first, so a monitor that's recognised by its device path isn't taken by one
+/// that only shares a name with it. Every monitor is used at most once. Returns pairs of
+/// indices into `saved` and `connected`.
+pub fn match_monitors(saved: &[KnownMonitor], connected: &[KnownMonitor]) -> Vec<(usize, usize)> {
+    let mut candidates: Vec<(u32, usize, usize)> = vec![];
+    for (i, s) in saved.iter().enumerate() {
+        for (j, c) in connected.iter().enumerate() {
+            let score = s.score(c);
+            if score > 0 {
+                candidates.push((score, i, j));
+            }
+        }
+    }
+    // Highest score first, ties go in order
+    candidates.sort_by_key(|&(score, i, j)| (std::cmp::Reverse(score), i, j));
+
+    let mut pairs: Vec<(usize, usize)> = vec![];
+    for (_, i, j) in candidates {
+        if !pairs.iter().any(|&(si, cj)| si == i || cj == j) {
+            pairs.push((i, j));
+        }
+    }
+    pairs.sort_unstable();
+    pairs
+}
+
+/// Rewrites the displays of `layout` to the adapter and target of the monitor in
+/// `connected` they were matched to. Displays without a match are left as they are.
+pub fn remap_layout(connected: &[KnownMonitor], layout: &mut DisplayLayout) {
+    let saved: Vec<KnownMonitor> = layout
+        .displays
+        .iter()
+        .map(KnownMonitor::of_display)
+        .collect();
+    let pairs = match_monitors(&saved, connected);
+    let sources: Vec<(LUID, u32)> = layout.displays.iter().map(source_of).collect();
+
+    for (i, display) in layout.displays.iter_mut().enumerate() {
+        let Some(&(_, j)) = pairs.iter().find(|&&(si, _)| si == i) else {
+            debug!(
+                target_id = saved[i].target_id,
+                name = saved[i].identity.name.as_str();
+                "Monitor of the layout isn't connected"
+            );
+            continue;
+        };
+        let monitor = &connected[j];
+        if monitor.target_id != saved[i].target_id {
+            debug!(
+                name = monitor.identity.name.as_str(),
+                from = saved[i].target_id,
+                to = monitor.target_id;
+                "Monitor moved to another target"
+            );
+        }
+        move_display(display, monitor.adapter_id, monitor.target_id);
+    }
+
+    // A display moved to another adapter keeps its source id, which a display already on
+    // that adapter may use. Sharing it would turn the two into clones, so the moved display
+    // and its own clones go to a source nobody uses instead
+    for i in 0..layout.displays.len() {
+        let (adapter_id, source_id) = source_of(&layout.displays[i]);
+        if adapter_id == sources[i].0 {
+            continue;
+        }
+        let taken = layout
+            .displays
+            .iter()
+            .zip(&sources)
+            .any(|(d, &was)| source_of(d) == (adapter_id, source_id) && was != sources[i]);
+        if !taken {
+            continue;
+        }
+        let used: Vec<(LUID, u32)> = layout.displays.iter().map(source_of).collect();
+        let free = (0..)
+            .find(|id| !used.contains(&(adapter_id, *id)))
+            .unwrap_or(source_id);
+        debug!(
+            name = saved[i].identity.name.as_str(),
+            from = source_id,
+            to = free;
+            "Monitor moved to another source"
+        );
+        for (display, &was) in layout.displays.iter_mut().zip(&sources) {
+            if was == sources[i] && source_of(display) == (adapter_id, source_id) {
+                display.pathInfo.sourceInfo.id = free;
+                display.modeInfo.sourceModeInfo.id = free;
+            }
+        }
+    }
+}
+
+pub(crate) fn source_of(display: &Display) -> (LUID, u32) {
+    (
+        display.pathInfo.sourceInfo.adapterId,
+        display.pathInfo.sourceInfo.id,
+    )
+}
+
+pub(crate) fn move_display(display: &mut Display, adapter_id: LUID, target_id: u32) {
+    display.pathInfo.sourceInfo.adapterId = adapter_id;
+    display.pathInfo.targetInfo.adapterId = adapter_id;
+    display.modeInfo.sourceModeInfo.adapterId = adapter_id;
+    display.modeInfo.targetModeInfo.adapterId = adapter_id;
+    display.additionalInfo.adapter.header.adapterId = adapter_id;
+    display.additionalInfo.target.header.adapterId = adapter_id;
+
+    let old_target_id = display.pathInfo.targetInfo.id;
+    display.pathInfo.targetInfo.id = target_id;
+    display.modeInfo.targetModeInfo.id = target_id;
+    // The device info headers were filled in for the old target, if at all
+    for header in [
+        &mut display.additionalInfo.adapter.header,
+        &mut display.additionalInfo.target.header,
+    ] {
+        if header.id == old_target_id {
+            header.id = target_id;
+        }
+    }
+}
+
+/// Every monitor the system can see, whether it's on or not.
+pub fn connected_monitors<B: crate::backend::DisplayBackend + ?Sized>(
+    backend: &mut B,
+) -> Result<Vec<KnownMonitor>> {
+    let (paths, _) = backend
+        .query_all_paths()
+        .map_err(|e| e.context("Could not query the connected monitors"))?;
+
+    let mut monitors: Vec<KnownMonitor> = vec![];
+    for path in &paths {
+        let target = &path.targetInfo;
+        let active = path.flags & DISPLAYCONFIG_PATH_ACTIVE != 0;
+        // Outputs without a monitor show up as paths too
+        if !active && !target.targetAvailable.as_bool() {
+            continue;
+        }
+        let known = monitors
+            .iter()
+            .any(|m| m.adapter_id == target.adapterId && m.target_id == target.id);
+        if known {
+            continue;
+        }
+        let target_name = backend.get_target_name(target.adapterId, target.id)?;
+        monitors.push(KnownMonitor {
+            identity: MonitorIdentity::of(&target_name),
+            adapter_id: target.adapterId,
+            target_id: target.id,
+        });
+    }
+    Ok(monitors)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::model::Monitor;
+    use crate::testing::{layout, monitor};
+
+    fn known(monitors: &[Monitor]) -> Vec<KnownMonitor> {
+        layout(monitors)
+            .displays
+            .iter()
+            .map(KnownMonitor::of_display)
+            .collect()
+    }
+
+    /// A monitor of the same model as every other `twin`, told apart by its device path.
+    fn twin(target_id: u32, x: i32, instance: u32) -> Monitor {
+        let mut twin = monitor(target_id, x, 60);
+        twin.name = "DELL U2720Q".to_string();
+        twin.edid.product_code = 0xA0B1;
+        twin.device_path = format!("\\\\?\\DISPLAY#DELA0B1#5&1a2b3c&0&UID{instance}");
+        twin
+    }
+
+    #[test]
+    fn identical_models_are_told_apart_by_device_path() {
+        let saved = known(&[twin(1, 0, 100), twin(2, 1920, 200)]);
+        // The cables were swapped
+        let connected = known(&[twin(1, 0, 200), twin(2, 1920, 100)]);
+        assert_eq!(match_monitors(&saved, &connected), [(0, 1), (1, 0)]);
+    }
+
+    #[test]
+    fn identical_models_fall_back_to_the_target() {
+        let saved = known(&[twin(1, 0, 100), twin(2, 1920, 200)]);
+        // New device paths, e.g. after a driver reinstall
+        let connected = known(&[twin(2, 0, 300), twin(1, 1920, 400)]);
+        assert_eq!(match_monitors(&saved, &connected), [(0, 1), (1, 0)]);
+    }
+
+    #[test]
+    fn monitor_moved_to_another_connector() {
+        let mut moved = monitor(1, 0, 60);
+        moved.target_id = 5;
+        moved.connector_instance = 2;
+        moved.output_technology = crate::model::OutputTechnology::Hdmi;
+        moved.device_path = moved.device_path.replace("UID1", "UID5");
+        moved.adapter.luid.low_part = 0x5678;
+        let connected = known(&[moved, monitor(2, 1920, 60)]);
+
+        let mut saved = layout(&[monitor(1, 0, 60), monitor(2, 1920, 60)]);
+        remap_layout(&connected, &mut saved);
+        let targets: Vec<(u32, u32)> = saved
+            .displays
+            .iter()
+            .map(|d| {
+                (
+                    d.pathInfo.targetInfo.adapterId.LowPart,
+                    d.pathInfo.targetInfo.id,
+                )
+            })
+            .collect();
+        assert_eq!(targets, [(0x5678, 5), (0x1234, 2)]);
+        let display = &saved.displays[0];
+        assert_eq!(display.modeInfo.targetModeInfo.id, 5);
+        assert_eq!(display.modeInfo.sourceModeInfo.adapterId.LowPart, 0x5678);
+    }
+
+    #[test]
+    fn other_models_are_not_matched() {
+        let mut other = monitor(1, 0, 60);
+        other.edid.product_code = 0x9999;
+        other.name = "Other".to_string();
+        other.device_path = "\\\\?\\DISPLAY#GSM9999#5&1a2b3c&0&UID1".to_string();
+        assert!(match_monitors(&known(&[monitor(1, 0, 60)]), &known(&[other])).is_empty());
+    }
+
+    fn on_adapter(mut monitor: Monitor, low_part: u32) -> Monitor {
+        monitor.adapter.luid.low_part = low_part;
+        monitor.ccd.source_adapter.low_part = low_part;
+        monitor.ccd.source_mode.adapter.low_part = low_part;
+        monitor.ccd.target_mode.adapter.low_part = low_part;
+        monitor
+    }
+
+    #[test]
+    fn monitor_moved_to_another_adapter_gets_a_free_source() {
+        // Monitor 3 is on the second adapter, on the same source id Monitor 2 has on the first
+        let mut third = on_adapter(monitor(3, 3840, 60), 0x5678);
+        third.source_id = 2;
+        third.ccd.source_mode.id = 2;
+        let mut saved = layout(&[monitor(1, 0, 60), monitor(2, 1920, 60), third.clone()]);
+
+        let mut moved = on_adapter(monitor(2, 1920, 60), 0x5678);
+        moved.target_id = 7;
+        let connected = known(&[monitor(1, 0, 60), moved, third]);
+        remap_layout(&connected, &mut saved);
+
+        let sources: Vec<(u32, u32)> = saved
+            .displays
+            .iter()
+            .map(|d| {
+                let (adapter_id, source_id) = source_of(d);
+                (adapter_id.LowPart, source_id)
+            })
+            .collect();
+        assert_eq!(sources, [(0x1234, 1), (0x5678, 0), (0x5678, 2)]);
+        assert_eq!(saved.displays[1].modeInfo.sourceModeInfo.id, 0);
+        assert_eq!(saved.displays[1].pathInfo.targetInfo.id, 7);
+    }
+
+    #[test]
+    fn unmatched_displays_are_left_alone() {
+        let connected = known(&[monitor(2, 0, 60)]);
+        let mut saved = layout(&[monitor(1, 0, 60)]);
+        remap_layout(&connected, &mut saved);
+        assert_eq!(saved.displays[0].pathInfo.targetInfo.id, 1);
+    }
+}
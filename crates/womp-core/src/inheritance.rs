@@ -0,0 +1,323 @@
+//! Profiles that build on another one with `extends = "<profile>"` in their `profile.toml`.
+//!
+//! The effective profile is resolved from the base up: the displays in the profile's own
+//! `displays.json` replace the matching displays of its base, extras and `profile.toml`
+//! settings it sets win over the base's, everything else is inherited. Saving a profile
+//! that extends another only stores what differs from the base.
+
+use crate::backend::renumber_mode_indices;
+use crate::canonical;
+use crate::error::{Result, WompError};
+use crate::identity::{KnownMonitor, match_monitors, move_display};
+use crate::layout_spec::LayoutSpec;
+use crate::profile_store::ProfileStore;
+use crate::scope::merge_layouts;
+use crate::serde_types::config::{Config, Run, RunCommand};
+use crate::serde_types::{Display, DisplayLayout, GlobalInfo};
+use log::debug;
+
+/// A profile with everything it inherits filled in.
+#[derive(Clone)]
+pub struct ResolvedProfile {
+    pub name: String,
+    /// The merged `profile.toml`, `extends` is cleared.
+    pub config: Config,
+    pub layout: Option<DisplayLayout>,
+    /// The `layout.toml` of the nearest profile in the chain that has one.
+    pub layout_spec: Option<LayoutSpec>,
+    /// The profile and the ones it extends, the profile itself first.
+    pub chain: Vec<String>,
+}
+
+fn optional<T>(result: Result<T>) -> Result<Option<T>> {
+    match result {
+        Ok(value) => Ok(Some(value)),
+        Err(e) if e.is_not_found() => Ok(None),
+        Err(e) => Err(e),
+    }
+}
+
+type Parts = (Config, Option<DisplayLayout>, Option<LayoutSpec>);
+
+/// The profile's own `profile.toml`, `displays.json` and `layout.toml`, whichever exist.
+fn load_parts(store: &dyn ProfileStore, name: &str) -> Result<Parts> {
+    Ok((
+        optional(store.load_config(name))?.unwrap_or_default(),
+        optional(store.load_layout(name))?,
+        optional(store.load_layout_spec(name))?,
+    ))
+}
+
+/// The profile `name` as it's stored, without anything it inherits. `extends` is kept.
+pub fn unresolved_profile(store: &dyn ProfileStore, name: &str) -> Result<ResolvedProfile> {
+    if !store.exists(name)? {
+        return Err(WompError::ProfileNotFound(name.to_string()));
+    }
+    let (config, layout, layout_spec) = load_parts(store, name)?;
+    Ok(ResolvedProfile {
+        name: name.to_string(),
+        config,
+        layout,
+        layout_spec,
+        chain: vec![name.to_string()],
+    })
+}
+
+/// Resolves the profile `name` and everything it extends. Fails if a profile in the chain
+/// doesn't exist or the chain loops back on itself.
+pub fn resolve_profile(store: &dyn ProfileStore, name: &str) -> Result<ResolvedProfile> {
+    let mut chain: Vec<String> = vec![];
+    let mut parts = vec![];
+    let mut next = name.to_string();
+    loop {
+        if chain.contains(&next) {
+            chain.push(next);
+            return Err(WompError::Validation(format!(
+                "Profile `{name}` extends itself: {}",
+                chain.join(" -> ")
+            )));
+        }
+        if !store.exists(&next)? {
+            return Err(match chain.last() {
+                Some(child) => WompError::Validation(format!(
+                    "Profile `{child}` extends `{next}`, which doesn't exist"
+                )),
+                None => WompError::ProfileNotFound(next),
+            });
+        }
+        let part = load_parts(store, &next)?;
+        let extends = part.0.extends.clone();
+        chain.push(next);
+        parts.push(part);
+        if extends.is_empty() {
+            break;
+        }
+        next = extends;
+    }
+    if chain.len() > 1 {
+        debug!(profile = name, chain:% = chain.join(" -> "); "Resolved profile");
+    }
+
+    // Fold from the root of the chain down to the profile itself
+    let mut parts = parts.into_iter().rev();
+    let (mut config, mut layout, mut layout_spec) = parts.next().unwrap_or_default();
+    for (own_config, own_layout, own_spec) in parts {
+        config = inherit_config(&config, own_config);
+        layout = match (layout, own_layout) {
+            (Some(base), Some(own)) => Some(inherit_layout(&base, &own)),
+            (base, own) => own.or(base),
+        };
+        layout_spec = own_spec.or(layout_spec);
+    }
+    config.extends.clear();
+
+    Ok(ResolvedProfile {
+        name: name.to_string(),
+        config,
+        layout,
+        layout_spec,
+        chain,
+    })
+}
+
+fn inherit_command(base: &RunCommand, own: RunCommand) -> RunCommand {
+    if own.is_empty() { base.clone() } else { own }
+}
+
+fn inherit_config(base: &Config, own: Config) -> Config {
+    Config {
+        run: Run {
+            before: inherit_command(&base.run.before, own.run.before),
+            after: inherit_command(&base.run.after, own.run.after),
+        },
+        icon: if own.icon.is_empty() {
+            base.icon.clone()
+        } else {
+            own.icon
+        },
+        scope: if own.scope.is_full() {
+            base.scope.clone()
+        } else {
+            own.scope
+        },
+        on_failure: own.on_failure.or(base.on_failure),
+        ..own
+    }
+}
+
+fn inherit_global_info(base: &GlobalInfo, own: &GlobalInfo) -> GlobalInfo {
+    GlobalInfo::from(
+        own.iconSize.or(base.iconSize),
+        own.wallpaperInfo
+            .clone()
+            .or_else(|| base.wallpaperInfo.clone()),
+        own.audioOutput.clone().or_else(|| base.audioOutput.clone()),
+    )
+}
+
+/// Pairs the displays of `layout` with the displays of `base` that show the same monitor.
+/// The two may have been saved in different boots or with the monitor on another port.
+fn pair_with_base(layout: &DisplayLayout, base: &DisplayLayout) -> Vec<(usize, usize)> {
+    let known = |l: &DisplayLayout| -> Vec<KnownMonitor> {
+        l.displays.iter().map(KnownMonitor::of_display).collect()
+    };
+    match_monitors(&known(layout), &known(base))
+}
+
+/// `base` with the displays and extras of `own` put in.
+pub fn inherit_layout(base: &DisplayLayout, own: &DisplayLayout) -> DisplayLayout {
+    let mut own = own.clone();
+    for (i, j) in pair_with_base(&own, base) {
+        let (display, base_display) = (&mut own.displays[i], &base.displays[j]);
+        let base_target = &base_display.pathInfo.targetInfo;
+        move_display(display, base_target.adapterId, base_target.id);
+        let (info, base_info) = (&mut display.optionalInfo, base_display.optionalInfo);
+        info.dpiScale = info.dpiScale.or(base_info.dpiScale);
+        info.hdrEnabled = info.hdrEnabled.or(base_info.hdrEnabled);
+        info.hdrSupported = info.hdrSupported.or(base_info.hdrSupported);
+        info.sdrWhiteLevel = info.sdrWhiteLevel.or(base_info.sdrWhiteLevel);
+    }
+    let mut layout = merge_layouts(base, &own);
+    layout.globalInfo = inherit_global_info(&base.globalInfo, &own.globalInfo);
+    layout
+}
+
+/// The display as it's compared between profiles, without anything boot-specific.
+fn comparable(display: &Display) -> Option<serde_json::Value> {
+    let mut layout = DisplayLayout::from(vec![display.clone()], GlobalInfo::from(None, None, None));
+    canonical::canonicalize(&mut layout);
+    // Bases saved before WOMP decoded EDIDs don't have them, that's no change to the display
+    layout.displays[0].edidInfo = None;
+    serde_json::to_value(&layout.displays).ok()
+}
+
+/// What has to be stored for a profile extending `base` to resolve to `captured`: the
+/// displays and extras that differ from the base. Fails if `captured` lacks a display of
+/// the base, inheriting can only change displays, not turn them off.
+pub fn diff_from_base(base: &DisplayLayout, captured: &DisplayLayout) -> Result<DisplayLayout> {
+    let pairs = pair_with_base(captured, base);
+    if let Some(missing) = (0..base.displays.len())
+        .find(|j| !pairs.iter().any(|&(_, pj)| pj == *j))
+        .map(|j| &base.displays[j])
+    {
+        return Err(WompError::Validation(format!(
+            "Display {} of the base profile is off, a profile can't turn off displays of the \
+             profile it extends",
+            missing.describe()
+        )));
+    }
+
+    let displays = captured
+        .displays
+        .iter()
+        .enumerate()
+        .filter(|&(i, d)| {
+            let Some(&(_, j)) = pairs.iter().find(|&&(pi, _)| pi == i) else {
+                return true;
+            };
+            // Where the monitor is connected now doesn't make it a change
+            let base_display = &base.displays[j];
+            let mut moved = d.clone();
+            let base_target = &base_display.pathInfo.targetInfo;
+            move_display(&mut moved, base_target.adapterId, base_target.id);
+            comparable(&moved) != comparable(base_display)
+        })
+        .map(|(_, d)| d.clone())
+        .collect();
+
+    let (base_info, info) = (&base.globalInfo, &captured.globalInfo);
+    let wallpaper_changed = serde_json::to_value(&info.wallpaperInfo).ok()
+        != serde_json::to_value(&base_info.wallpaperInfo).ok();
+    let global_info = GlobalInfo::from(
+        info.iconSize
+            .filter(|size| base_info.iconSize != Some(*size)),
+        info.wallpaperInfo.clone().filter(|_| wallpaper_changed),
+        info.audioOutput
+            .clone()
+            .filter(|audio| base_info.audioOutput.as_ref() != Some(audio)),
+    );
+
+    let mut layout = DisplayLayout::from(displays, global_info);
+    renumber_mode_indices(&mut layout);
+    Ok(layout)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::comparison::layouts_match;
+    use crate::model::Monitor;
+    use crate::profile_store::MemoryProfileStore;
+    use crate::testing::{layout, monitor};
+
+    fn extending(base: &str) -> Config {
+        Config {
+            extends: base.to_string(),
+            ..Config::default()
+        }
+    }
+
+    fn desk() -> [Monitor; 2] {
+        [monitor(1, 0, 60), monitor(2, 1920, 60)]
+    }
+
+    #[test]
+    fn profiles_extending_each_other_fail() {
+        let mut store = MemoryProfileStore::new();
+        store.save_config("a", &extending("b")).unwrap();
+        store.save_config("b", &extending("a")).unwrap();
+
+        let Err(WompError::Validation(message)) = resolve_profile(&store, "a") else {
+            panic!("resolved a profile that extends itself");
+        };
+        assert!(message.ends_with("a -> b -> a"), "{message}");
+    }
+
+    #[test]
+    fn base_display_missing_from_the_capture_fails() {
+        let base = layout(&desk());
+        let captured = layout(&[monitor(1, 0, 60)]);
+        let Err(WompError::Validation(message)) = diff_from_base(&base, &captured) else {
+            panic!("a profile turned off a display of its base");
+        };
+        assert!(message.contains("Monitor 2"), "{message}");
+    }
+
+    #[test]
+    fn only_changed_displays_are_stored_and_inherited_back() {
+        let base = layout(&desk());
+        let [left, mut right] = desk();
+        right.position.x = 2560;
+        let mut scaled = monitor(1, 0, 60);
+        scaled.extras.dpiScale = Some(150);
+
+        for (captured, changed) in [
+            (layout(&[left, right]), "`Monitor 2`"),
+            (layout(&[scaled, monitor(2, 1920, 60)]), "`Monitor 1`"),
+        ] {
+            let own = diff_from_base(&base, &captured).unwrap();
+            let stored: Vec<_> = own.displays.iter().map(Display::describe).collect();
+            assert_eq!(stored, [changed]);
+            assert!(layouts_match(
+                &captured,
+                &inherit_layout(&base, &own),
+                false
+            ));
+        }
+    }
+
+    #[test]
+    fn resolves_the_displays_the_base_has() {
+        let mut store = MemoryProfileStore::new();
+        store.save_layout("desk", &layout(&desk())).unwrap();
+        let captured = layout(&[monitor(1, 0, 144), monitor(2, 1920, 60)]);
+        let own = diff_from_base(&layout(&desk()), &captured).unwrap();
+        store.save_layout("gaming", &own).unwrap();
+        store.save_config("gaming", &extending("desk")).unwrap();
+
+        let resolved = resolve_profile(&store, "gaming").unwrap();
+        assert_eq!(resolved.chain, ["gaming", "desk"]);
+        assert!(resolved.config.extends.is_empty());
+        assert!(layouts_match(&captured, &resolved.layout.unwrap(), false));
+    }
+}
@@ -0,0 +1,292 @@
+//! Every monitor WOMP has captured, kept in `monitors.toml` in the config directory.
+//!
+//! Profiles only know a monitor by what it reports about itself, which doesn't tell two
+//! monitors of the same model apart for a person. The inventory remembers each one, when
+//! it was first and last seen and which profiles use it, and lets the user give it an
+//! alias like "Left Dell" that's shown instead of its name:
+//!
+//! ```toml
+//! [[monitors]]
+//! id = 1
+//! alias = "Left Dell"
+//! name = "DELL U2720Q"
+//! ```
+
+use crate::config_manager::get_config_dir;
+use crate::edid::EdidInfo;
+use crate::error::{Result, WompError};
+use crate::identity::{KnownMonitor, MonitorIdentity, match_monitors};
+use crate::inheritance::resolve_profile;
+use crate::model::{EdidIds, OutputTechnology, wide_to_string};
+use crate::profile_store::ProfileStore;
+use crate::serde_types::Display;
+use crate::win32_types::LUID;
+use log::debug;
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::path::{Path, PathBuf};
+
+pub const INVENTORY_FILE_NAME: &str = "monitors.toml";
+
+/// The contents of `monitors.toml`.
+#[derive(Serialize, Deserialize, JsonSchema, Debug, Clone, Default)]
+pub struct Inventory {
+    #[serde(default)]
+    pub monitors: Vec<InventoryMonitor>,
+}
+
+/// A monitor WOMP has seen.
+#[derive(Serialize, Deserialize, JsonSchema, Debug, Clone, PartialEq)]
+pub struct InventoryMonitor {
+    /// Number the monitor is referred to by.
+    pub id: u32,
+    /// Name given by the user, shown instead of the monitor's own name.
+    #[serde(default, skip_serializing_if = "String::is_empty")]
+    pub alias: String,
+    /// Friendly name from the EDID, e.g. `DELL U2720Q`.
+    #[serde(default)]
+    pub name: String,
+    #[serde(default)]
+    pub device_path: String,
+    #[serde(default)]
+    pub manufacturer_id: u16,
+    #[serde(default)]
+    pub product_code: u16,
+    #[serde(default)]
+    pub connector_instance: u32,
+    pub output_technology: OutputTechnology,
+    /// What its EDID says about it, as of when it was last seen.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub edid: Option<EdidInfo>,
+    /// Target id it was last connected to, tells identical monitors apart.
+    #[serde(default)]
+    pub target_id: u32,
+    /// When a profile first captured the monitor (RFC 3339).
+    pub first_seen: String,
+    /// When a profile last captured the monitor (RFC 3339).
+    pub last_seen: String,
+    /// Profiles that have the monitor in their layout.
+    #[serde(default)]
+    pub profiles: Vec<String>,
+}
+
+impl InventoryMonitor {
+    pub fn identity(&self) -> MonitorIdentity {
+        MonitorIdentity {
+            edid: EdidIds {
+                manufacturer_id: self.manufacturer_id,
+                product_code: self.product_code,
+            },
+            device_path: self.device_path.clone(),
+            connector_instance: self.connector_instance,
+            output_technology: self.output_technology,
+            name: self.name.clone(),
+        }
+    }
+
+    fn known(&self) -> KnownMonitor {
+        KnownMonitor {
+            identity: self.identity(),
+            adapter_id: LUID::default(),
+            target_id: self.target_id,
+        }
+    }
+
+    fn update(&mut self, monitor: &KnownMonitor) {
+        let identity = &monitor.identity;
+        self.name = identity.name.clone();
+        self.device_path = identity.device_path.clone();
+        self.manufacturer_id = identity.edid.manufacturer_id;
+        self.product_code = identity.edid.product_code;
+        self.connector_instance = identity.connector_instance;
+        self.output_technology = identity.output_technology;
+        self.target_id = monitor.target_id;
+    }
+
+    /// The alias if it has one, its name otherwise.
+    pub fn label(&self) -> String {
+        match (self.alias.is_empty(), self.name.is_empty()) {
+            (false, _) => self.alias.clone(),
+            (true, false) => self.name.clone(),
+            (true, true) => format!("Monitor {}", self.id),
+        }
+    }
+
+    fn is_called(&self, selector: &str) -> bool {
+        let selector = selector.trim();
+        self.id.to_string() == selector
+            || [&self.alias, &self.name, &self.device_path]
+                .iter()
+                .any(|name| !name.is_empty() && name.eq_ignore_ascii_case(selector))
+    }
+}
+
+fn known_displays(displays: &[Display]) -> Vec<KnownMonitor> {
+    displays.iter().map(KnownMonitor::of_display).collect()
+}
+
+impl Inventory {
+    pub fn default_path() -> Result<PathBuf> {
+        Ok(get_config_dir()?.join(INVENTORY_FILE_NAME))
+    }
+
+    /// Reads the inventory at `path`, an empty one if there's no file yet.
+    pub fn load(path: &Path) -> Result<Inventory> {
+        match fs::read_to_string(path) {
+            Ok(contents) => toml::from_str(&contents).map_err(|e| WompError::parse(path, e)),
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(Inventory::default()),
+            Err(e) => Err(WompError::io(path, e)),
+        }
+    }
+
+    pub fn save(&self, path: &Path) -> Result<()> {
+        let contents =
+            toml::to_string(self).map_err(|e| WompError::serialize("monitor inventory", e))?;
+        fs::write(path, contents).map_err(|e| WompError::io(path, e))
+    }
+
+    pub fn load_default() -> Result<Inventory> {
+        Inventory::load(&Inventory::default_path()?)
+    }
+
+    pub fn save_default(&self) -> Result<()> {
+        self.save(&Inventory::default_path()?)
+    }
+
+    fn known(&self) -> Vec<KnownMonitor> {
+        self.monitors.iter().map(InventoryMonitor::known).collect()
+    }
+
+    /// Adds the monitors of `displays` that aren't known yet and marks all of them as seen
+    /// at `now`.
+    pub fn record(&mut self, displays: &[Display], now: &str) {
+        let captured = known_displays(displays);
+        let pairs = match_monitors(&captured, &self.known());
+        for (i, monitor) in captured.iter().enumerate() {
+            let edid = displays[i].edidInfo.clone();
+            match pairs.iter().find(|&&(ci, _)| ci == i) {
+                Some(&(_, j)) => {
+                    let entry = &mut self.monitors[j];
+                    entry.update(monitor);
+                    // Profiles saved before EDIDs were decoded don't know better
+                    entry.edid = edid.or(entry.edid.take());
+                    entry.last_seen = now.to_string();
+                }
+                None => {
+                    let id = self.monitors.iter().map(|m| m.id).max().unwrap_or(0) + 1;
+                    debug!(id = id, name = monitor.identity.name.as_str(); "New monitor");
+                    let mut entry = InventoryMonitor {
+                        id,
+                        alias: String::new(),
+                        name: String::new(),
+                        device_path: String::new(),
+                        manufacturer_id: 0,
+                        product_code: 0,
+                        connector_instance: 0,
+                        output_technology: monitor.identity.output_technology,
+                        edid,
+                        target_id: 0,
+                        first_seen: now.to_string(),
+                        last_seen: now.to_string(),
+                        profiles: vec![],
+                    };
+                    entry.update(monitor);
+                    self.monitors.push(entry);
+                }
+            }
+        }
+    }
+
+    /// Records the monitors of the profile `profile_name` as seen at `now` and fills in which
+    /// profiles use each monitor.
+    pub fn record_profile(
+        &mut self,
+        store: &dyn ProfileStore,
+        profile_name: &str,
+        now: &str,
+    ) -> Result<()> {
+        if let Some(layout) = resolve_profile(store, profile_name)?.layout {
+            self.record(&layout.displays, now);
+        }
+        self.refresh_profiles(store)
+    }
+
+    /// Fills in which profiles use each monitor. Profiles that can't be resolved are left
+    /// out.
+    pub fn refresh_profiles(&mut self, store: &dyn ProfileStore) -> Result<()> {
+        for monitor in &mut self.monitors {
+            monitor.profiles.clear();
+        }
+        let known = self.known();
+        for profile in store.list()? {
+            let Some(layout) = resolve_profile(store, &profile).ok().and_then(|p| p.layout) else {
+                continue;
+            };
+            for (_, j) in match_monitors(&known_displays(&layout.displays), &known) {
+                self.monitors[j].profiles.push(profile.clone());
+            }
+        }
+        Ok(())
+    }
+
+    /// The monitor with the id, alias, name or device path `selector`. Fails if there's
+    /// none or more than one.
+    pub fn find_mut(&mut self, selector: &str) -> Result<&mut InventoryMonitor> {
+        let matching: Vec<usize> = (0..self.monitors.len())
+            .filter(|&i| self.monitors[i].is_called(selector))
+            .collect();
+        match matching[..] {
+            [i] => Ok(&mut self.monitors[i]),
+            [] => Err(WompError::Validation(format!(
+                "No monitor is called `{selector}`, see `womp-cli monitors`"
+            ))),
+            _ => Err(WompError::Validation(format!(
+                "More than one monitor is called `{selector}`, use its number: {}",
+                matching
+                    .iter()
+                    .map(|&i| self.monitors[i].id.to_string())
+                    .collect::<Vec<_>>()
+                    .join(", ")
+            ))),
+        }
+    }
+
+    /// Gives the monitor `selector` the alias `alias`, an empty alias removes it.
+    pub fn set_alias(&mut self, selector: &str, alias: &str) -> Result<()> {
+        let alias = alias.trim();
+        let id = self.find_mut(selector)?.id;
+        let taken_by = self
+            .monitors
+            .iter()
+            .find(|m| !alias.is_empty() && m.id != id && m.alias.eq_ignore_ascii_case(alias));
+        if let Some(other) = taken_by {
+            return Err(WompError::Validation(format!(
+                "Monitor {} is already called `{alias}`",
+                other.id
+            )));
+        }
+        self.find_mut(selector)?.alias = alias.to_string();
+        Ok(())
+    }
+
+    /// The inventory entry of the monitor shown by `display`.
+    pub fn monitor_of(&self, display: &Display) -> Option<&InventoryMonitor> {
+        match_monitors(&[KnownMonitor::of_display(display)], &self.known())
+            .first()
+            .map(|&(_, j)| &self.monitors[j])
+    }
+
+    /// How to call the monitor of `display`: its alias, its name or its target id.
+    pub fn display_name(&self, display: &Display) -> String {
+        if let Some(monitor) = self.monitor_of(display).filter(|m| !m.alias.is_empty()) {
+            return monitor.alias.clone();
+        }
+        let name = wide_to_string(&display.additionalInfo.target.monitorFriendlyDeviceName);
+        if name.is_empty() {
+            format!("Target {}", display.pathInfo.targetInfo.id)
+        } else {
+            name
+        }
+    }
+}
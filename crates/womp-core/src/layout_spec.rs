@@ -0,0 +1,237 @@
+//! Hand-written `layout.toml` profiles.
+//!
+//! `displays.json` is a raw dump of the CCD topology and only re-applies cleanly on the
+//! machine it was captured on. A [`LayoutSpec`] instead describes each monitor by who it
+//! is (name, device path, EDID ids, connector, ...) and what it should look like, and is
+//! resolved against whatever is connected when the profile is applied:
+//!
+//! ```toml
+//! [[monitor]]
+//! name = "LG TV SSCR2"
+//! resolution = "3840x2160"
+//! refresh_rate = 23.976
+//! primary = true
+//!
+//! [[monitor]]
+//! name = "DELL U2720Q"
+//! position = { x = -2560, y = 0 }
+//! rotation = 90
+//! dpi_scale = 150
+//! ```
+//!
+//! Anything a monitor doesn't specify keeps its current value, monitors that aren't listed
+//! are turned off.
+
+use crate::error::{Result, WompError};
+use crate::model::{OutputTechnology, Position, RefreshRate};
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+use std::fmt;
+use std::path::Path;
+
+mod resolve;
+
+pub use resolve::{apply_layout_spec, plan_layout_spec};
+
+/// Format version of `layout.toml` files this build understands.
+pub const LAYOUT_SPEC_VERSION: u32 = 1;
+
+fn current_version() -> u32 {
+    LAYOUT_SPEC_VERSION
+}
+
+/// A whole `layout.toml`.
+#[derive(Serialize, Deserialize, JsonSchema, Debug, Clone)]
+pub struct LayoutSpec {
+    /// File format version
+    #[serde(default = "current_version")]
+    pub version: u32,
+    /// The monitors to turn on, every other connected monitor is turned off
+    #[serde(rename = "monitor", default)]
+    pub monitors: Vec<MonitorSpec>,
+}
+
+impl Default for LayoutSpec {
+    fn default() -> Self {
+        LayoutSpec {
+            version: LAYOUT_SPEC_VERSION,
+            monitors: vec![],
+        }
+    }
+}
+
+impl LayoutSpec {
+    /// Parses a `layout.toml`, `path` is only used for errors.
+    pub fn parse(path: &Path, contents: &str) -> Result<Self> {
+        let spec: LayoutSpec = toml::from_str(contents).map_err(|e| WompError::parse(path, e))?;
+        if spec.version > LAYOUT_SPEC_VERSION {
+            return Err(WompError::UnsupportedVersion {
+                path: path.to_path_buf(),
+                found: spec.version,
+                supported: LAYOUT_SPEC_VERSION,
+            });
+        }
+        Ok(spec)
+    }
+}
+
+/// One monitor of a [`LayoutSpec`]. The identity fields pick the monitor, at least one of
+/// them has to be set and all that are set have to match.
+#[derive(Serialize, Deserialize, JsonSchema, Debug, Clone, Default)]
+pub struct MonitorSpec {
+    /// Friendly name reported by the monitor, e.g. `DELL U2720Q` (case-insensitive)
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub name: Option<String>,
+    /// Device interface path of the monitor (case-insensitive)
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub device_path: Option<String>,
+    /// Manufacturer id from the monitor's EDID
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub manufacturer_id: Option<u16>,
+    /// Product code from the monitor's EDID
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub product_code: Option<u16>,
+    /// CCD target id of the adapter output the monitor is connected to
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub target_id: Option<u32>,
+    /// How the monitor is connected, e.g. `hdmi` or `displayPort`
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub connector: Option<OutputTechnology>,
+
+    /// Desktop size as `<width>x<height>`. A monitor rotated by 90 or 270 degrees is
+    /// taller than it is wide, e.g. `1440x2560`
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub resolution: Option<Resolution>,
+    /// Refresh rate in Hz, e.g. `144` or `23.976`
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub refresh_rate: Option<f64>,
+    /// Top-left corner in desktop coordinates
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub position: Option<Position>,
+    /// Clockwise rotation in degrees: 0, 90, 180 or 270
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub rotation: Option<u32>,
+    /// Make this the primary monitor, the others are moved so it ends up at (0, 0)
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub primary: bool,
+    /// DPI scale in percent, e.g. `150`
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub dpi_scale: Option<u32>,
+    /// Turn HDR on or off
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub hdr: Option<bool>,
+}
+
+impl MonitorSpec {
+    fn has_identity(&self) -> bool {
+        self.name.is_some()
+            || self.device_path.is_some()
+            || self.manufacturer_id.is_some()
+            || self.product_code.is_some()
+            || self.target_id.is_some()
+            || self.connector.is_some()
+    }
+}
+
+// Describes the monitor by its identity fields, e.g. `name = "DELL U2720Q", connector = hdmi`
+impl fmt::Display for MonitorSpec {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let mut fields = vec![];
+        if let Some(name) = &self.name {
+            fields.push(format!("name = {name:?}"));
+        }
+        if let Some(device_path) = &self.device_path {
+            fields.push(format!("device_path = {device_path:?}"));
+        }
+        if let Some(manufacturer_id) = self.manufacturer_id {
+            fields.push(format!("manufacturer_id = {manufacturer_id}"));
+        }
+        if let Some(product_code) = self.product_code {
+            fields.push(format!("product_code = {product_code}"));
+        }
+        if let Some(target_id) = self.target_id {
+            fields.push(format!("target_id = {target_id}"));
+        }
+        if let Some(connector) = self.connector {
+            fields.push(format!("connector = {connector:?}"));
+        }
+        if fields.is_empty() {
+            write!(f, "monitor without identity")
+        } else {
+            write!(f, "{}", fields.join(", "))
+        }
+    }
+}
+
+/// Width and height in pixels, written as `3840x2160`.
+#[derive(Serialize, Deserialize, JsonSchema, Debug, Clone, Copy, PartialEq, Eq)]
+#[serde(try_from = "String", into = "String")]
+#[schemars(with = "String")]
+pub struct Resolution {
+    pub width: u32,
+    pub height: u32,
+}
+
+impl fmt::Display for Resolution {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}x{}", self.width, self.height)
+    }
+}
+
+impl TryFrom<String> for Resolution {
+    type Error = String;
+
+    fn try_from(value: String) -> std::result::Result<Self, Self::Error> {
+        let invalid = || format!("`{value}` is not a resolution like `1920x1080`");
+        let (width, height) = value.split_once(['x', 'X']).ok_or_else(invalid)?;
+        let width: u32 = width.trim().parse().map_err(|_| invalid())?;
+        let height: u32 = height.trim().parse().map_err(|_| invalid())?;
+        if width == 0 || height == 0 {
+            return Err(invalid());
+        }
+        Ok(Resolution { width, height })
+    }
+}
+
+impl From<Resolution> for String {
+    fn from(value: Resolution) -> Self {
+        value.to_string()
+    }
+}
+
+/// Turns a rate in Hz into the fraction the driver expects. Broadcast rates like 23.976 and
+/// 59.94 are really `n * 1000 / 1001`, everything else is kept to a thousandth of a Hz.
+pub fn refresh_rate_from_hz(hz: f64) -> RefreshRate {
+    let ntsc = hz * 1001.0 / 1000.0;
+    if hz.fract().abs() > 0.001 && (ntsc - ntsc.round()).abs() < 0.005 {
+        RefreshRate::new(ntsc.round() as u32 * 1000, 1001)
+    } else {
+        RefreshRate::new((hz * 1000.0).round() as u32, 1000)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn fraction(hz: f64) -> (u32, u32) {
+        let rate = refresh_rate_from_hz(hz);
+        (rate.numerator, rate.denominator)
+    }
+
+    #[test]
+    fn broadcast_rates_are_ntsc_fractions() {
+        assert_eq!(fraction(23.976), (24000, 1001));
+        assert_eq!(fraction(29.97), (30000, 1001));
+        assert_eq!(fraction(59.94), (60000, 1001));
+        assert_eq!(fraction(119.88), (120000, 1001));
+    }
+
+    #[test]
+    fn other_rates_are_kept_to_a_thousandth() {
+        assert_eq!(fraction(60.0), (60000, 1000));
+        assert_eq!(fraction(144.0), (144000, 1000));
+        // Close to 59.94, but not what a broadcast rate rounds to
+        assert_eq!(fraction(59.95), (59950, 1000));
+    }
+}
@@ -0,0 +1,687 @@
+use super::{LayoutSpec, MonitorSpec, refresh_rate_from_hz};
+use crate::backend::{DisplayBackend, get_modes};
+use crate::comparison::{Setting, on_off, rotation};
+use crate::error::{Result, WompError};
+use crate::model::{
+    AdapterLuid, OutputTechnology, Position, RefreshRate, Rotation, wide_to_string,
+};
+use crate::plan::{Change, describe_mode};
+use crate::serde_types::optional_info::OptionalInfo;
+use crate::serde_types::{Display, DisplayLayout, GlobalInfo};
+use crate::win32_types::*;
+use log::{debug, info};
+use std::collections::HashSet;
+
+/// A connected monitor, with the path it's currently shown on or could be shown on.
+struct Candidate {
+    /// The active path if there is one, otherwise the first path to the monitor.
+    path: DISPLAYCONFIG_PATH_INFO,
+    /// Every source the monitor could be driven by.
+    source_ids: Vec<u32>,
+    active: bool,
+    source_mode: Option<DISPLAYCONFIG_SOURCE_MODE>,
+    target_mode: Option<DISPLAYCONFIG_MODE_INFO>,
+    target_name: DISPLAYCONFIG_TARGET_DEVICE_NAME,
+    name: String,
+    device_path: String,
+    manufacturer_id: u16,
+    product_code: u16,
+}
+
+impl Candidate {
+    fn target_id(&self) -> u32 {
+        self.path.targetInfo.id
+    }
+
+    fn connector(&self) -> OutputTechnology {
+        self.path.targetInfo.outputTechnology.0.into()
+    }
+
+    fn matches(&self, spec: &MonitorSpec) -> bool {
+        spec.name
+            .as_ref()
+            .is_none_or(|name| name.eq_ignore_ascii_case(&self.name))
+            && spec
+                .device_path
+                .as_ref()
+                .is_none_or(|path| path.eq_ignore_ascii_case(&self.device_path))
+            && spec
+                .manufacturer_id
+                .is_none_or(|id| id == self.manufacturer_id)
+            && spec
+                .product_code
+                .is_none_or(|code| code == self.product_code)
+            && spec.target_id.is_none_or(|id| id == self.target_id())
+            && spec.connector.is_none_or(|c| c == self.connector())
+    }
+
+    /// What the monitor is called in plans.
+    fn label(&self) -> String {
+        if self.name.is_empty() {
+            format!("Target {}", self.target_id())
+        } else {
+            self.name.clone()
+        }
+    }
+
+    fn describe(&self) -> String {
+        format!(
+            "`{}` (target {}, {:?})",
+            self.name,
+            self.target_id(),
+            self.connector()
+        )
+    }
+}
+
+/// The monitor a spec resolved to and the settings it will get.
+struct Planned<'a> {
+    spec: &'a MonitorSpec,
+    candidate: &'a Candidate,
+    source_id: u32,
+    width: u32,
+    height: u32,
+    position: Position,
+    rotation: Rotation,
+    refresh_rate: RefreshRate,
+    /// Whether the current target mode still fits, otherwise the driver picks one.
+    keep_target_mode: bool,
+}
+
+/// Resolves `spec` against `candidates`, failing if it can't be applied.
+fn resolve<'a>(spec: &'a LayoutSpec, candidates: &'a [Candidate]) -> Result<Vec<Planned<'a>>> {
+    if spec.monitors.is_empty() {
+        return Err(WompError::Validation(
+            "The layout doesn't list any monitors".to_string(),
+        ));
+    }
+    if spec.monitors.iter().filter(|m| m.primary).count() > 1 {
+        return Err(WompError::Validation(
+            "Only one monitor can be primary".to_string(),
+        ));
+    }
+
+    let mut planned = spec
+        .monitors
+        .iter()
+        .map(|monitor| plan_monitor(monitor, find_candidate(monitor, candidates)?))
+        .collect::<Result<Vec<_>>>()?;
+
+    for (i, p) in planned.iter().enumerate() {
+        if planned[..i]
+            .iter()
+            .any(|other| std::ptr::eq(other.candidate, p.candidate))
+        {
+            return Err(WompError::Validation(format!(
+                "{} is listed more than once",
+                p.candidate.describe()
+            )));
+        }
+    }
+
+    place_primary(&mut planned)?;
+    assign_sources(&mut planned)?;
+    Ok(planned)
+}
+
+/// Resolves `spec` against the connected monitors and applies it. Listed monitors are
+/// turned on with the requested settings, every other monitor is turned off. `save` works
+/// like for [`DisplayBackend::apply_topology`]. Returns the flags the displays were set
+/// with and the layout they were set to, to check them against once they settle.
+pub fn apply_layout_spec(
+    backend: &mut dyn DisplayBackend,
+    spec: &LayoutSpec,
+    save: bool,
+) -> Result<(SET_DISPLAY_CONFIG_FLAGS, DisplayLayout)> {
+    let candidates = connected_monitors(backend)?;
+    let planned = resolve(spec, &candidates)?;
+
+    let (paths, modes) = build_config(&planned);
+    backend
+        .set_display_config(
+            &paths,
+            &modes,
+            SDC_USE_SUPPLIED_DISPLAY_CONFIG | SDC_VALIDATE,
+        )
+        .map_err(|e| e.context("The layout was rejected"))?;
+    let mut flags = SDC_APPLY | SDC_USE_SUPPLIED_DISPLAY_CONFIG | SDC_ALLOW_CHANGES;
+    if save {
+        flags |= SDC_SAVE_TO_DATABASE;
+    }
+    backend.set_display_config(&paths, &modes, flags)?;
+
+    for p in &planned {
+        let path = &p.candidate.path;
+        if let Some(dpi_scale) = p.spec.dpi_scale {
+            backend.set_display_dpi(path.sourceInfo.adapterId, p.source_id, dpi_scale)?;
+        }
+        if let Some(hdr) = p.spec.hdr {
+            backend.set_display_hdr(path.targetInfo.adapterId, path.targetInfo.id, hdr)?;
+        }
+    }
+
+    info!(monitors = planned.len(); "Applied layout");
+    Ok((flags, requested_layout(&planned, &paths, &modes)))
+}
+
+/// What [`apply_layout_spec`] would change, without changing anything.
+pub fn plan_layout_spec(
+    backend: &mut dyn DisplayBackend,
+    spec: &LayoutSpec,
+) -> Result<Vec<Change>> {
+    let candidates = connected_monitors(backend)?;
+    let planned = resolve(spec, &candidates)?;
+
+    let mut changes: Vec<Change> = candidates
+        .iter()
+        .filter(|c| c.active && !planned.iter().any(|p| std::ptr::eq(p.candidate, *c)))
+        .map(|c| Change::TurnOff { display: c.label() })
+        .collect();
+
+    for p in &planned {
+        let (candidate, path) = (p.candidate, &p.candidate.path);
+        let display = Some(candidate.label());
+        let display = display.as_deref();
+        match candidate.source_mode {
+            Some(mode) if candidate.active => {
+                let current_rotation: Rotation = path.targetInfo.rotation.0.into();
+                let current_refresh_rate = RefreshRate::from(path.targetInfo.refreshRate);
+                let current_position = Position {
+                    x: mode.position.x,
+                    y: mode.position.y,
+                };
+                let settings = [
+                    (
+                        Setting::Resolution,
+                        format!("{}x{}", mode.width, mode.height),
+                        format!("{}x{}", p.width, p.height),
+                    ),
+                    (
+                        Setting::RefreshRate,
+                        current_refresh_rate.to_string(),
+                        p.refresh_rate.to_string(),
+                    ),
+                    (
+                        Setting::Position,
+                        format!("({}, {})", current_position.x, current_position.y),
+                        format!("({}, {})", p.position.x, p.position.y),
+                    ),
+                    (
+                        Setting::Rotation,
+                        rotation(current_rotation),
+                        rotation(p.rotation),
+                    ),
+                ];
+                changes.extend(settings.into_iter().filter(|(_, from, to)| from != to).map(
+                    |(setting, from, to)| Change::Set {
+                        display: display.map(str::to_string),
+                        setting,
+                        from: Some(from),
+                        to,
+                    },
+                ));
+            }
+            _ => changes.push(Change::TurnOn {
+                display: candidate.label(),
+                to: describe_mode((p.width, p.height), p.refresh_rate, p.position, p.rotation),
+            }),
+        }
+
+        // Displays that are off don't have a DPI scale or HDR state yet
+        let active = candidate.active;
+        if let Some(dpi_scale) = p.spec.dpi_scale {
+            let current = backend
+                .get_display_dpi_info(path.sourceInfo.adapterId, p.source_id)
+                .ok()
+                .filter(|_| active)
+                .map(|info| info.current);
+            if current != Some(dpi_scale) {
+                changes.push(Change::Set {
+                    display: display.map(str::to_string),
+                    setting: Setting::DpiScale,
+                    from: current.map(|dpi| format!("{dpi}%")),
+                    to: format!("{dpi_scale}%"),
+                });
+            }
+        }
+        if let Some(hdr) = p.spec.hdr {
+            let current = backend
+                .get_display_hdr_info(path.targetInfo.adapterId, path.targetInfo.id)
+                .ok()
+                .filter(|_| active)
+                .map(|info| info.advanced_color_enabled);
+            if current != Some(hdr) {
+                changes.push(Change::Set {
+                    display: display.map(str::to_string),
+                    setting: Setting::Hdr,
+                    from: current.map(on_off),
+                    to: on_off(hdr),
+                });
+            }
+        }
+    }
+    Ok(changes)
+}
+
+/// Every monitor that is connected, whether it's turned on or not.
+fn connected_monitors(backend: &mut dyn DisplayBackend) -> Result<Vec<Candidate>> {
+    let (paths, modes) = backend
+        .query_all_paths()
+        .map_err(|e| e.context("Could not query the connected monitors"))?;
+
+    let mut candidates: Vec<Candidate> = vec![];
+    for path in &paths {
+        let active = path.flags & DISPLAYCONFIG_PATH_ACTIVE != 0;
+        // Outputs without a monitor show up as paths too
+        if !active && !path.targetInfo.targetAvailable.as_bool() {
+            continue;
+        }
+
+        let existing = candidates.iter_mut().find(|c| {
+            c.path.targetInfo.adapterId == path.targetInfo.adapterId
+                && c.path.targetInfo.id == path.targetInfo.id
+        });
+        let candidate = match existing {
+            Some(candidate) => candidate,
+            None => {
+                let target_name =
+                    backend.get_target_name(path.targetInfo.adapterId, path.targetInfo.id)?;
+                candidates.push(Candidate {
+                    path: *path,
+                    source_ids: vec![],
+                    active: false,
+                    source_mode: None,
+                    target_mode: None,
+                    target_name,
+                    name: wide_to_string(&target_name.monitorFriendlyDeviceName),
+                    device_path: wide_to_string(&target_name.monitorDevicePath),
+                    manufacturer_id: target_name.edidManufactureId,
+                    product_code: target_name.edidProductCodeId,
+                });
+                candidates.last_mut().unwrap()
+            }
+        };
+
+        if !candidate.source_ids.contains(&path.sourceInfo.id) {
+            candidate.source_ids.push(path.sourceInfo.id);
+        }
+        if active && !candidate.active {
+            let [target_mode, source_mode] = get_modes(&modes, path);
+            candidate.path = *path;
+            candidate.active = true;
+            candidate.target_mode = target_mode.copied();
+            candidate.source_mode = source_mode.map(|m| unsafe { m.Anonymous.sourceMode });
+        }
+    }
+    Ok(candidates)
+}
+
+fn find_candidate<'a>(spec: &MonitorSpec, candidates: &'a [Candidate]) -> Result<&'a Candidate> {
+    if !spec.has_identity() {
+        return Err(WompError::Validation(
+            "Every monitor needs at least one of name, device_path, manufacturer_id, \
+             product_code, target_id or connector"
+                .to_string(),
+        ));
+    }
+    let matching: Vec<&Candidate> = candidates.iter().filter(|c| c.matches(spec)).collect();
+    match matching.as_slice() {
+        [candidate] => Ok(candidate),
+        [] => Err(WompError::Validation(format!(
+            "No connected monitor matches {spec}. Connected monitors: {}",
+            candidates
+                .iter()
+                .map(Candidate::describe)
+                .collect::<Vec<_>>()
+                .join(", ")
+        ))),
+        _ => Err(WompError::Validation(format!(
+            "{spec} matches {} monitors ({}), add more fields to tell them apart",
+            matching.len(),
+            matching
+                .iter()
+                .map(|c| c.describe())
+                .collect::<Vec<_>>()
+                .join(", ")
+        ))),
+    }
+}
+
+fn rotation_from_degrees(degrees: u32) -> Option<Rotation> {
+    match degrees {
+        0 => Some(Rotation::Identity),
+        90 => Some(Rotation::Rotate90),
+        180 => Some(Rotation::Rotate180),
+        270 => Some(Rotation::Rotate270),
+        _ => None,
+    }
+}
+
+fn is_portrait(rotation: Rotation) -> bool {
+    matches!(rotation, Rotation::Rotate90 | Rotation::Rotate270)
+}
+
+fn plan_monitor<'a>(spec: &'a MonitorSpec, candidate: &'a Candidate) -> Result<Planned<'a>> {
+    let path = &candidate.path;
+    let current_rotation = match path.targetInfo.rotation.0 {
+        // Inactive paths don't always have one
+        0 => Rotation::Identity,
+        rotation => rotation.into(),
+    };
+    let rotation = match spec.rotation {
+        Some(degrees) => rotation_from_degrees(degrees).ok_or_else(|| {
+            WompError::Validation(format!(
+                "Rotation of {spec} has to be 0, 90, 180 or 270, not {degrees}"
+            ))
+        })?,
+        None => current_rotation,
+    };
+
+    let (width, height) = match (spec.resolution, candidate.source_mode) {
+        (Some(resolution), _) => (resolution.width, resolution.height),
+        // Turning the monitor on its side swaps the sides of the desktop
+        (None, Some(mode)) if is_portrait(rotation) != is_portrait(current_rotation) => {
+            (mode.height, mode.width)
+        }
+        (None, Some(mode)) => (mode.width, mode.height),
+        (None, None) => {
+            return Err(WompError::Validation(format!(
+                "{spec} is turned off, so it needs a resolution"
+            )));
+        }
+    };
+    let current_size = candidate.source_mode.map(|m| (m.width, m.height));
+
+    let current_refresh_rate = RefreshRate::from(path.targetInfo.refreshRate);
+    let refresh_rate = match spec.refresh_rate {
+        Some(hz) if !hz.is_finite() || hz <= 0.0 => {
+            return Err(WompError::Validation(format!(
+                "Refresh rate of {spec} has to be a positive number of Hz"
+            )));
+        }
+        // Drivers report the same rate as different fractions, so compare the rates
+        Some(hz)
+            if candidate.active
+                && current_refresh_rate
+                    .as_hz()
+                    .is_some_and(|current| (current - hz).abs() < 0.01) =>
+        {
+            current_refresh_rate
+        }
+        Some(hz) => refresh_rate_from_hz(hz),
+        None if candidate.active => current_refresh_rate,
+        // 0/0 lets the driver pick
+        None => RefreshRate::default(),
+    };
+
+    let position = match (spec.position, candidate.source_mode) {
+        (Some(position), _) => position,
+        (None, Some(mode)) => Position {
+            x: mode.position.x,
+            y: mode.position.y,
+        },
+        (None, None) if spec.primary => Position::default(),
+        (None, None) => {
+            return Err(WompError::Validation(format!(
+                "{spec} is turned off, so it needs a position"
+            )));
+        }
+    };
+
+    let keep_target_mode = candidate.target_mode.is_some()
+        && current_size == Some((width, height))
+        && refresh_rate == current_refresh_rate
+        && rotation == current_rotation;
+
+    debug!(
+        target_id = path.targetInfo.id,
+        name = candidate.name.as_str(),
+        width,
+        height,
+        refresh_rate:% = refresh_rate,
+        x = position.x,
+        y = position.y;
+        "Resolved monitor"
+    );
+
+    Ok(Planned {
+        spec,
+        candidate,
+        source_id: path.sourceInfo.id,
+        width,
+        height,
+        position,
+        rotation,
+        refresh_rate,
+        keep_target_mode,
+    })
+}
+
+/// Moves every monitor so the primary one ends up at the desktop origin.
+fn place_primary(planned: &mut [Planned]) -> Result<()> {
+    let origin = match planned.iter().find(|p| p.spec.primary) {
+        Some(primary) => primary.position,
+        None if planned.iter().any(|p| p.position == Position::default()) => return Ok(()),
+        None => {
+            return Err(WompError::Validation(
+                "No monitor is at position (0, 0), mark one of them with `primary = true`"
+                    .to_string(),
+            ));
+        }
+    };
+    for p in planned {
+        p.position.x -= origin.x;
+        p.position.y -= origin.y;
+    }
+    Ok(())
+}
+
+/// Monitors that are on keep their source, the others get one nobody else uses.
+fn assign_sources(planned: &mut [Planned]) -> Result<()> {
+    let source_key = |p: &Planned, source_id: u32| {
+        (
+            AdapterLuid::from(p.candidate.path.sourceInfo.adapterId),
+            source_id,
+        )
+    };
+    let mut used: HashSet<(AdapterLuid, u32)> = planned
+        .iter()
+        .filter(|p| p.candidate.active)
+        .map(|p| source_key(p, p.source_id))
+        .collect();
+
+    for p in planned.iter_mut().filter(|p| !p.candidate.active) {
+        let source_id = p
+            .candidate
+            .source_ids
+            .iter()
+            .copied()
+            .find(|&id| !used.contains(&source_key(p, id)))
+            .ok_or_else(|| {
+                WompError::Validation(format!(
+                    "{} can't be turned on, all of its outputs are in use",
+                    p.candidate.describe()
+                ))
+            })?;
+        used.insert(source_key(p, source_id));
+        p.source_id = source_id;
+    }
+    Ok(())
+}
+
+fn build_config(
+    planned: &[Planned],
+) -> (Vec<DISPLAYCONFIG_PATH_INFO>, Vec<DISPLAYCONFIG_MODE_INFO>) {
+    let mut paths = vec![];
+    let mut modes = vec![];
+
+    for p in planned {
+        let mut path = p.candidate.path;
+        path.flags = DISPLAYCONFIG_PATH_ACTIVE;
+        path.sourceInfo.id = p.source_id;
+        path.targetInfo.rotation = DISPLAYCONFIG_ROTATION(p.rotation.into());
+        path.targetInfo.refreshRate = p.refresh_rate.into();
+
+        let pixel_format = p
+            .candidate
+            .source_mode
+            .map_or(DISPLAYCONFIG_PIXELFORMAT_32BPP, |m| m.pixelFormat);
+        path.sourceInfo.Anonymous.modeInfoIdx = modes.len() as u32;
+        modes.push(DISPLAYCONFIG_MODE_INFO {
+            infoType: DISPLAYCONFIG_MODE_INFO_TYPE_SOURCE,
+            id: p.source_id,
+            adapterId: path.sourceInfo.adapterId,
+            Anonymous: DISPLAYCONFIG_MODE_INFO_0 {
+                sourceMode: DISPLAYCONFIG_SOURCE_MODE {
+                    width: p.width,
+                    height: p.height,
+                    pixelFormat: pixel_format,
+                    position: POINTL {
+                        x: p.position.x,
+                        y: p.position.y,
+                    },
+                },
+            },
+        });
+
+        match p.candidate.target_mode {
+            Some(target_mode) if p.keep_target_mode => {
+                path.targetInfo.Anonymous.modeInfoIdx = modes.len() as u32;
+                modes.push(target_mode);
+            }
+            _ => path.targetInfo.Anonymous.modeInfoIdx = DISPLAYCONFIG_PATH_MODE_IDX_INVALID,
+        }
+
+        paths.push(path);
+    }
+
+    (paths, modes)
+}
+
+/// The layout `paths` and `modes` set `planned` to. Only what comparing it with the
+/// current layout looks at is filled in, a target mode the driver picks is left empty.
+fn requested_layout(
+    planned: &[Planned],
+    paths: &[DISPLAYCONFIG_PATH_INFO],
+    modes: &[DISPLAYCONFIG_MODE_INFO],
+) -> DisplayLayout {
+    let picked_by_driver = DISPLAYCONFIG_MODE_INFO::default();
+    let displays = planned
+        .iter()
+        .zip(paths)
+        .filter_map(|(p, path)| {
+            let [target_mode, Some(source_mode)] = get_modes(modes, path) else {
+                return None;
+            };
+            Some(Display::from(
+                path,
+                target_mode.unwrap_or(&picked_by_driver),
+                source_mode,
+                &p.candidate.target_name,
+                &DISPLAYCONFIG_ADAPTER_NAME::default(),
+                &OptionalInfo::default(),
+            ))
+        })
+        .collect();
+    DisplayLayout::from(displays, GlobalInfo::from(None, None, None))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::backend::simulated::SimulatedBackend;
+    use crate::comparison::layouts_match;
+    use crate::model::Layout;
+    use crate::serde_types::global_config::GlobalConfig;
+    use crate::testing::{layout, monitor};
+    use std::path::Path;
+
+    /// Two monitors side by side at 60 Hz.
+    fn desk() -> SimulatedBackend {
+        SimulatedBackend::from_layout(&layout(&[monitor(1, 0, 60), monitor(2, 1920, 60)]))
+    }
+
+    fn spec(contents: &str) -> LayoutSpec {
+        LayoutSpec::parse(Path::new("layout.toml"), contents).unwrap()
+    }
+
+    /// Target id, position and refresh rate of every active monitor.
+    fn current(backend: &mut SimulatedBackend) -> Vec<(u32, Position, RefreshRate)> {
+        let layout = backend
+            .get_display_layout(&GlobalConfig::default())
+            .unwrap();
+        Layout::from(&layout)
+            .monitors
+            .iter()
+            .map(|m| (m.target_id, m.position, m.mode.refresh_rate))
+            .collect()
+    }
+
+    #[test]
+    fn two_monitors_swap_places() {
+        let mut backend = desk();
+        let spec = spec(
+            r#"
+            [[monitor]]
+            name = "Monitor 1"
+            position = { x = 1920, y = 0 }
+            refresh_rate = 144
+
+            [[monitor]]
+            name = "Monitor 2"
+            position = { x = 0, y = 0 }
+            "#,
+        );
+
+        let (flags, expected) = apply_layout_spec(&mut backend, &spec, false).unwrap();
+        assert_eq!(flags.0 & SDC_SAVE_TO_DATABASE.0, 0);
+        assert_eq!(
+            current(&mut backend),
+            [
+                (1, Position { x: 1920, y: 0 }, RefreshRate::new(144, 1)),
+                (2, Position { x: 0, y: 0 }, RefreshRate::new(60, 1)),
+            ]
+        );
+        let applied = backend
+            .get_display_layout(&GlobalConfig::default())
+            .unwrap();
+        assert!(layouts_match(&expected, &applied, false));
+    }
+
+    #[test]
+    fn unknown_monitor_fails_without_changing_anything() {
+        let mut backend = desk();
+        let before = current(&mut backend);
+        let spec = spec(
+            r#"
+            [[monitor]]
+            name = "LG TV SSCR2"
+            "#,
+        );
+
+        let Err(WompError::Validation(message)) = apply_layout_spec(&mut backend, &spec, false)
+        else {
+            panic!("applied a layout with a monitor that isn't connected");
+        };
+        assert!(message.contains("name = \"LG TV SSCR2\""), "{message}");
+        assert!(message.contains("`Monitor 2` (target 2"), "{message}");
+        assert!(backend.applied_flags().is_empty());
+        assert_eq!(current(&mut backend), before);
+    }
+
+    #[test]
+    fn ntsc_and_whole_rates_are_told_apart() {
+        let mut backend = desk();
+        let rate_of_monitor_1 = |backend: &mut SimulatedBackend, hz: &str| {
+            let spec = spec(&format!(
+                "[[monitor]]\nname = \"Monitor 1\"\nrefresh_rate = {hz}\n"
+            ));
+            apply_layout_spec(backend, &spec, false).unwrap();
+            let rate = current(backend)[0].2;
+            (rate.numerator, rate.denominator)
+        };
+
+        assert_eq!(rate_of_monitor_1(&mut backend, "59.94"), (60000, 1001));
+        assert_eq!(rate_of_monitor_1(&mut backend, "60"), (60000, 1000));
+    }
+}
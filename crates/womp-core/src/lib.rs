@@ -1,183 +1,786 @@
-use ccd_manager::CCDWrapper;
+use backend::{DisplayBackend, system_backend};
+use comparison::ProfileMatch;
 use config_manager::*;
-use serde_types::config::Config;
-use serde_types::{DisplayLayout, global_config::GlobalConfig};
-use std::{fs, io::BufReader, os::windows::process::CommandExt, process::Command};
+use error::{AfterFailure, Result, WompError};
+use inventory::Inventory;
+use log::{debug, error, info, trace, warn};
+use plan::Plan;
+use profile_store::{FsProfileStore, ProfileStore};
+use report::{ApplyReport, HookReport};
+use serde_types::DisplayLayout;
+use serde_types::config::{Config, Extra, OnFailure, RunCommand, Scope};
+use serde_types::global_config::GlobalConfig;
+use snapshot::Snapshot;
+#[cfg(windows)]
+use std::os::windows::process::CommandExt;
+use std::ops::ControlFlow;
+use std::time::{Duration, Instant};
+use std::{fs, process::Command};
+use validation::ValidationReport;
+pub mod backend;
+pub mod bundle;
+pub mod canonical;
+pub mod comparison;
+#[cfg(windows)]
 pub mod ccd_manager;
 pub mod config_manager;
+pub mod edid;
+pub mod error;
+pub mod identity;
+pub mod inheritance;
+pub mod inventory;
+pub mod layout_spec;
+pub mod logging;
+pub mod migration;
+pub mod model;
+pub mod plan;
+pub mod profile_store;
+pub mod report;
+pub mod schema;
+pub mod scope;
 pub mod serde_types;
+pub mod snapshot;
+#[cfg(test)]
+mod testing;
+pub mod validation;
+pub mod verify;
+pub mod win32_types;
 
-pub fn get_global_config() -> GlobalConfig {
-    let global_config_file = get_config_dir().unwrap().join("settings.toml");
+pub fn get_global_config() -> Result<GlobalConfig> {
+    let global_config_file = get_config_dir()?.join("settings.toml");
     // create file if it doesn't exist
     if !global_config_file.exists() {
-        fs::create_dir_all(get_config_dir().unwrap()).unwrap();
-        fs::write(&global_config_file, toml::to_string(&GlobalConfig::new()).unwrap())
-            .unwrap();
-    }
-    let global_config = match fs::read_to_string(&global_config_file) {
-        Ok(s) => toml::from_str(&s)
-            .map_err(|e| format!("Failed to parse global config: {e}"))
-            .unwrap(),
-        Err(_) => GlobalConfig::new(),
-    };
-    global_config
-}
-
-pub fn set_global_config(global_config: &GlobalConfig) -> Result<(), String> {
-    let global_config_file = get_config_dir().unwrap().join("settings.toml");
-    fs::write(global_config_file, toml::to_string(global_config).unwrap())
-        .map_err(|e| format!("Failed to save global config: {e}"))
-}
-
-pub fn get_active_profile(global_config: &GlobalConfig) -> Result<Option<String>, String> {
-    let mut wrapper = CCDWrapper::new(true, false);
-    let current_display_layout = wrapper.get_display_layout(global_config).unwrap();
-    let current_display_layout = serde_json::to_string(&current_display_layout).unwrap();
-    let profiles = get_profiles_and_configs().unwrap();
-    let profiles: Vec<String> = profiles
-        .into_iter()
-        .map(|(name, _)| name)
-        .collect();
-    let saved_layouts: Vec<(String, DisplayLayout)> = profiles.iter().map(|profile| {
-        let display_layout_file = get_display_layout_file_path(profile).unwrap();
-        let display_layout = fs::read_to_string(display_layout_file).unwrap();
-        let display_layout: DisplayLayout = serde_json::from_str(&display_layout).unwrap();
-        (profile.clone(), display_layout)
-    }).collect();
-    for (profile, saved_layout) in saved_layouts {
-        let saved_layout = serde_json::to_string(&saved_layout).unwrap();
-        if saved_layout == current_display_layout {
-            return Ok(Some(profile));
-        }
+        set_global_config(&GlobalConfig::new())?;
+    }
+    let contents = fs::read_to_string(&global_config_file)
+        .map_err(|e| WompError::io(&global_config_file, e))?;
+    toml::from_str(&contents).map_err(|e| WompError::parse(&global_config_file, e))
+}
+
+pub fn set_global_config(global_config: &GlobalConfig) -> Result<()> {
+    let global_config_file = get_config_dir()?.join("settings.toml");
+    let contents =
+        toml::to_string(global_config).map_err(|e| WompError::serialize("global config", e))?;
+    fs::write(&global_config_file, contents).map_err(|e| WompError::io(&global_config_file, e))
+}
+
+pub fn get_active_profile(global_config: &GlobalConfig) -> Result<Option<String>> {
+    let mut backend = system_backend()?;
+    let store = FsProfileStore::open_default()?;
+    get_active_profile_with(backend.as_mut(), &store, global_config)
+}
+
+pub fn get_active_profile_with(
+    backend: &mut dyn DisplayBackend,
+    store: &dyn ProfileStore,
+    global_config: &GlobalConfig,
+) -> Result<Option<String>> {
+    // Only the names in the differences come from the inventory
+    let closest = get_closest_profile_with(backend, store, global_config, &Inventory::default())?;
+    Ok(closest.filter(ProfileMatch::is_active).map(|m| m.profile))
+}
+
+/// The profile most like the current layout, whether it's active or not. `None` if no
+/// profile has a layout.
+pub fn get_closest_profile(global_config: &GlobalConfig) -> Result<Option<ProfileMatch>> {
+    let mut backend = system_backend()?;
+    let store = FsProfileStore::open_default()?;
+    get_closest_profile_with(backend.as_mut(), &store, global_config, &load_inventory())
+}
+
+pub fn get_closest_profile_with(
+    backend: &mut dyn DisplayBackend,
+    store: &dyn ProfileStore,
+    global_config: &GlobalConfig,
+    inventory: &Inventory,
+) -> Result<Option<ProfileMatch>> {
+    Ok(rank_profiles_with(backend, store, global_config, inventory)?.into_iter().next())
+}
+
+/// The current layout with every extra, whether the settings save it or not.
+fn capture_everything(
+    backend: &mut dyn DisplayBackend,
+    global_config: &GlobalConfig,
+) -> Result<DisplayLayout> {
+    let all_extras = Scope { displays: None, extras: Some(Extra::ALL.to_vec()) };
+    backend.get_display_layout(&all_extras.effective_config(global_config))
+}
+
+pub fn rank_profiles(global_config: &GlobalConfig) -> Result<Vec<ProfileMatch>> {
+    let mut backend = system_backend()?;
+    let store = FsProfileStore::open_default()?;
+    rank_profiles_with(backend.as_mut(), &store, global_config, &load_inventory())
+}
+
+/// Every profile with a layout compared to the current layout, the closest first. Of
+/// equally close profiles, the ones describing the whole setup come first, partial ones
+/// only describe part of it. Monitors are called by their alias in `inventory`, if any.
+pub fn rank_profiles_with(
+    backend: &mut dyn DisplayBackend,
+    store: &dyn ProfileStore,
+    global_config: &GlobalConfig,
+    inventory: &Inventory,
+) -> Result<Vec<ProfileMatch>> {
+    // Whatever extras a profile captured have to be compared, whether settings save them or not
+    let current_display_layout = capture_everything(backend, global_config)?;
+    let mut ranking = vec![];
+    for profile in store.list()? {
+        // Profiles that can't be resolved or have no layout can't be compared
+        let Ok(resolved) = inheritance::resolve_profile(store, &profile) else {
+            continue;
+        };
+        let Some(saved_layout) = resolved.layout else {
+            continue;
+        };
+        let partial = resolved.config.scope.selects_displays();
+        let comparison = comparison::compare_layouts(
+            &saved_layout,
+            &current_display_layout,
+            partial,
+            inventory,
+        );
+        ranking.push(ProfileMatch { profile, partial, comparison });
+    }
+    // Stable, ties stay in the order of the store
+    ranking.sort_by_key(|m| (std::cmp::Reverse(m.comparison.score), m.partial));
+    Ok(ranking)
+}
+
+pub fn save_current_display_layout(profile_name: &str, global_config: &GlobalConfig) -> Result<()> {
+    let mut backend = system_backend()?;
+    let mut store = FsProfileStore::open_default()?;
+    save_current_display_layout_with(backend.as_mut(), &mut store, profile_name, global_config)?;
+    update_inventory(&store, profile_name);
+    Ok(())
+}
+
+/// Saves the current layout, keeping to the scope the profile already has, if any.
+pub fn save_current_display_layout_with(
+    backend: &mut dyn DisplayBackend,
+    store: &mut dyn ProfileStore,
+    profile_name: &str,
+    global_config: &GlobalConfig,
+) -> Result<()> {
+    config_manager::validate_profile_name(profile_name)?;
+    let scope = store
+        .load_config(profile_name)
+        .map(|config| config.scope)
+        .unwrap_or_default();
+    save_scoped_display_layout_with(backend, store, profile_name, global_config, &scope)
+}
+
+/// Saves only the displays and extras in `scope` and records the scope in the profile.
+pub fn save_scoped_display_layout(
+    profile_name: &str,
+    global_config: &GlobalConfig,
+    scope: &Scope,
+) -> Result<()> {
+    let mut backend = system_backend()?;
+    let mut store = FsProfileStore::open_default()?;
+    save_scoped_display_layout_with(
+        backend.as_mut(),
+        &mut store,
+        profile_name,
+        global_config,
+        scope,
+    )?;
+    update_inventory(&store, profile_name);
+    Ok(())
+}
+
+/// The inventory for naming monitors, an empty one if it can't be read.
+fn load_inventory() -> Inventory {
+    Inventory::load_default().unwrap_or_else(|e| {
+        warn!(error:% = e; "Could not read the monitor inventory");
+        Inventory::default()
+    })
+}
+
+/// Adds the monitors of a profile that was just saved to the inventory. The profile is
+/// saved either way, a broken inventory is only logged.
+fn update_inventory(store: &dyn ProfileStore, profile_name: &str) {
+    let now = chrono::Local::now().to_rfc3339();
+    let updated = Inventory::load_default().and_then(|mut inventory| {
+        inventory.record_profile(store, profile_name, &now)?;
+        inventory.save_default()
+    });
+    if let Err(e) = updated {
+        warn!(profile = profile_name, error:% = e; "Could not update the monitor inventory");
     }
-    Ok(None)
 }
 
-pub fn save_current_display_layout(
-    profile_name: &String,
+/// Every monitor a profile has captured, with the profiles that use it now.
+pub fn get_monitor_inventory() -> Result<Inventory> {
+    let store = FsProfileStore::open_default()?;
+    let mut inventory = Inventory::load_default()?;
+    inventory.refresh_profiles(&store)?;
+    Ok(inventory)
+}
+
+/// Gives the monitor with the id, alias, name or device path `monitor` the alias `alias`,
+/// an empty alias removes it.
+pub fn set_monitor_alias(monitor: &str, alias: &str) -> Result<()> {
+    let mut inventory = Inventory::load_default()?;
+    inventory.set_alias(monitor, alias)?;
+    inventory.save_default()
+}
+
+pub fn save_scoped_display_layout_with(
+    backend: &mut dyn DisplayBackend,
+    store: &mut dyn ProfileStore,
+    profile_name: &str,
     global_config: &GlobalConfig,
-    debug: bool,
-) -> Result<(), String> {
-    let mut wrapper = CCDWrapper::new(true, debug);
-
-    let display_layout_file = get_display_layout_file_path(profile_name).unwrap();
-    fs::create_dir_all(display_layout_file.parent().unwrap()).unwrap();
-
-    let result = wrapper.get_display_layout(global_config);
-    if debug {
-        println!("Retrieved {} displays", result.as_ref().unwrap().displays.len());
-    }
-    match result {
-        Ok(ref display_layout) => {
-            let serialized = serde_json::to_string_pretty(display_layout).unwrap();
-            fs::write(display_layout_file, serialized)
-                .map_err(|e| format!("Failed to save display layout: {e}"))
+    scope: &Scope,
+) -> Result<()> {
+    config_manager::validate_profile_name(profile_name)?;
+
+    let mut display_layout = backend
+        .get_display_layout(&scope.effective_config(global_config))
+        .map_err(|e| e.context("Failed to retrieve displays"))?;
+    scope::select_displays(&mut display_layout, scope)?;
+    debug!(
+        profile = profile_name,
+        displays = display_layout.displays.len();
+        "Retrieved display layout"
+    );
+
+    let mut config = store.load_config(profile_name).unwrap_or_default();
+    // A profile that extends another only keeps what differs from it
+    if !config.extends.is_empty() && !scope.selects_displays() {
+        let base = inheritance::resolve_profile(&*store, &config.extends)?;
+        if let Some(base_layout) = &base.layout {
+            display_layout = inheritance::diff_from_base(base_layout, &display_layout)?;
+            debug!(
+                profile = profile_name,
+                base = config.extends.as_str(),
+                displays = display_layout.displays.len();
+                "Reduced display layout to the changes from its base"
+            );
         }
-        Err(ref e) => {
-            return Err(format!("Failed to retrieve displays: {e}"));
+    }
+
+    if global_config.canonical_layouts {
+        canonical::canonicalize(&mut display_layout);
+    }
+    store.save_layout(profile_name, &display_layout)?;
+
+    // Only touch profile.toml if the scope changed, a full scope isn't written at all
+    if config.scope != *scope {
+        config.scope = scope.clone();
+        store.save_config(profile_name, &config)?;
+    }
+    info!(profile = profile_name; "Saved display layout");
+    Ok(())
+}
+
+#[cfg(windows)]
+fn shell_command(command: &RunCommand) -> Command {
+    let mut cmd = Command::new("cmd");
+    cmd.raw_arg("/C")
+        .raw_arg(&command.target)
+        .raw_arg(&command.args);
+    cmd
+}
+
+#[cfg(not(windows))]
+fn shell_command(command: &RunCommand) -> Command {
+    let mut cmd = Command::new("sh");
+    cmd.arg("-c")
+        .arg(format!("{} {}", command.target, command.args));
+    cmd
+}
+
+/// Runs a hook and reports how it went. A hook that fails or can't even be started doesn't
+/// stop the profile from being applied.
+fn run_command(stage: &str, command: &RunCommand) -> HookReport {
+    debug!(
+        stage,
+        target = command.target.as_str(),
+        args = command.args.as_str();
+        "Running hook"
+    );
+    let started = Instant::now();
+    let output = match shell_command(command).output() {
+        Ok(output) => output,
+        Err(e) => {
+            let error = WompError::Hook {
+                stage: stage.to_string(),
+                source: e,
+            };
+            warn!(stage, error:% = error; "Could not run hook");
+            return HookReport {
+                stage: stage.to_string(),
+                command: command.command_line(),
+                exit_code: None,
+                stdout: String::new(),
+                stderr: String::new(),
+                error: Some(error),
+                duration: started.elapsed(),
+            };
         }
+    };
+
+    if output.status.success() {
+        debug!(stage, status:% = output.status; "Hook finished");
+    } else {
+        warn!(stage, status:% = output.status; "Hook failed");
+    }
+    let stdout = String::from_utf8_lossy(&output.stdout).into_owned();
+    let stderr = String::from_utf8_lossy(&output.stderr).into_owned();
+    trace!(stage, stdout:% = stdout; "Hook stdout");
+    trace!(stage, stderr:% = stderr; "Hook stderr");
+
+    HookReport {
+        stage: stage.to_string(),
+        command: command.command_line(),
+        exit_code: output.status.code(),
+        stdout,
+        stderr,
+        error: None,
+        duration: started.elapsed(),
     }
 }
 
+/// Applies the profile `profile_name` and reports what was done. A step that fails makes
+/// it return [`WompError::ApplyFailed`] with the report.
 pub fn apply_display_layout(
-    profile_name: &String,
+    profile_name: &str,
     global_config: &GlobalConfig,
-    debug: bool,
-) -> Result<(), String> {
-    let mut wrapper = CCDWrapper::new(true, debug);
-
-    let config_file = get_config_file_path(profile_name).unwrap();
-    let display_layout_file = get_display_layout_file_path(profile_name).unwrap();
-    let mut config_exists = fs::exists(&config_file).unwrap();
-    let mut config = Config::default();
-
-    // Load config if it exists
-    if config_exists {
-        config = match read_display_config(&profile_name) {
-            Ok(c) => c,
-            Err(_) => {
-                config_exists = false;
-                Config::default()
-            }
-        };
+) -> Result<ApplyReport> {
+    let mut backend = system_backend()?;
+    let store = FsProfileStore::open_default()?;
+    apply_display_layout_with(backend.as_mut(), &store, profile_name, global_config)
+}
+
+pub fn apply_display_layout_with(
+    backend: &mut dyn DisplayBackend,
+    store: &dyn ProfileStore,
+    profile_name: &str,
+    global_config: &GlobalConfig,
+) -> Result<ApplyReport> {
+    apply_profile(backend, store, profile_name, global_config, true).map(|(report, _)| report)
+}
+
+/// Applies the profile `profile_name` and returns the layout from before, to go back to
+/// with [`revert_display_layout`] unless the user confirms the new one with
+/// [`confirm_display_layout`]. If a step fails but the displays were changed,
+/// [`WompError::ApplyFailed`] carries the layout instead.
+pub fn apply_display_layout_unconfirmed(
+    profile_name: &str,
+    global_config: &GlobalConfig,
+) -> Result<(ApplyReport, Snapshot)> {
+    let mut backend = system_backend()?;
+    let store = FsProfileStore::open_default()?;
+    apply_profile(backend.as_mut(), &store, profile_name, global_config, false)
+}
+
+pub fn apply_display_layout_unconfirmed_with(
+    backend: &mut dyn DisplayBackend,
+    store: &dyn ProfileStore,
+    profile_name: &str,
+    global_config: &GlobalConfig,
+) -> Result<(ApplyReport, Snapshot)> {
+    apply_profile(backend, store, profile_name, global_config, false)
+}
+
+/// Keeps what applying a profile with [`apply_display_layout_unconfirmed`] changed.
+pub fn confirm_display_layout(snapshot: &Snapshot) -> Result<()> {
+    let mut backend = system_backend()?;
+    snapshot.confirm_with(backend.as_mut())
+}
+
+/// Sets back what applying a profile with [`apply_display_layout_unconfirmed`] changed.
+pub fn revert_display_layout(snapshot: &Snapshot) -> Result<()> {
+    let mut backend = system_backend()?;
+    snapshot.revert_with(backend.as_mut())
+}
+
+/// Runs the `stage` command of a profile if it has one and commands are enabled.
+fn run_hook(
+    stage: &str,
+    command: &RunCommand,
+    global_config: &GlobalConfig,
+    report: &mut ApplyReport,
+) {
+    if global_config.run_commands && !command.is_empty() {
+        report.hooks.push(run_command(stage, command));
+    }
+}
+
+/// Applies the profile `profile_name`, the displays are only saved to the Windows display
+/// database with `save`, otherwise that waits for [`Snapshot::confirm_with`].
+fn apply_profile(
+    backend: &mut dyn DisplayBackend,
+    store: &dyn ProfileStore,
+    profile_name: &str,
+    global_config: &GlobalConfig,
+    save: bool,
+) -> Result<(ApplyReport, Snapshot)> {
+    let started = Instant::now();
+    // A missing profile.toml just means there's nothing to run or inherit, a broken one
+    // fails, it may extend another profile
+    let resolved = inheritance::resolve_profile(store, profile_name)?;
+    let config = &resolved.config;
+    let display_name = if !config.name.is_empty() {
+        &config.name
+    } else {
+        profile_name
+    };
+    let mut report = ApplyReport::new(profile_name);
+
+    info!(profile = profile_name, name = display_name; "Applying profile");
+
+    let previous = capture_everything(backend, global_config)
+        .map_err(|e| e.context("Could not save the current layout to go back to"))?;
+    // displays.json wins, a hand-written layout.toml is only used if there's no capture
+    let on_failure = config.on_failure.unwrap_or_default();
+    let applied = match resolved.layout {
+        Some(display_layout) => {
+            prepare_layout(backend, profile_name, display_layout, &config.scope, global_config)
+                .map(|prepared| {
+                    let snapshot = Snapshot::new(
+                        profile_name,
+                        previous,
+                        prepared.topology.is_some(),
+                        prepared.extras_config.clone(),
+                    );
+                    // Only once the layout turned out to be applicable
+                    run_hook("before", &config.run.before, global_config, &mut report);
+                    apply_prepared(backend, &prepared, on_failure, save, &snapshot, &mut report);
+                    snapshot
+                })
+        }
+        None => layout_spec_of(&resolved, profile_name).map(|spec| {
+            // A layout.toml only sets DPI scales and HDR besides the displays
+            let extras = Scope {
+                displays: None,
+                extras: Some(vec![Extra::DpiScale, Extra::HdrState]),
+            };
+            let snapshot =
+                Snapshot::new(profile_name, previous, true, extras.effective_config(global_config));
+            let settle_timeout = Duration::from_millis(global_config.settle_timeout_ms.into());
+            run_hook("before", &config.run.before, global_config, &mut report);
+            apply_layout_spec(
+                backend,
+                spec,
+                on_failure,
+                save,
+                settle_timeout,
+                &snapshot,
+                &mut report,
+            );
+            snapshot
+        }),
+    };
+
+    let snapshot = match applied {
+        Ok(snapshot) if !report.failed() => {
+            info!(profile = profile_name; "Applied profile");
+            snapshot
+        }
+        Ok(snapshot) => {
+            report.duration = started.elapsed();
+            // Unless they were set back, the new displays still need confirming
+            let changed = report.display_config_flags.is_some()
+                && !matches!(report.after, Some(AfterFailure::RolledBack));
+            let e = WompError::ApplyFailed {
+                report: Box::new(report),
+                snapshot: changed.then(|| Box::new(snapshot)),
+            };
+            error!(profile = profile_name, error:% = e; "Failed to apply profile");
+            return Err(e);
+        }
+        Err(e) => {
+            error!(profile = profile_name, error:% = e; "Failed to apply profile");
+            return Err(e);
+        }
+    };
+
+    run_hook("after", &config.run.after, global_config, &mut report);
+
+    report.duration = started.elapsed();
+    Ok((report, snapshot))
+}
+
+/// What applying a `displays.json` comes down to.
+struct PreparedLayout {
+    /// The complete layout to set, unset if the displays are left alone.
+    topology: Option<DisplayLayout>,
+    /// The displays and desktop-wide settings the extras are taken from.
+    extras: DisplayLayout,
+    /// Which extras are applied.
+    extras_config: GlobalConfig,
+}
+
+/// Checks `display_layout` and moves it onto the monitors connected now. With a scope,
+/// its displays are merged onto the live layout if it selects displays, and only the
+/// extras in its scope are applied.
+fn prepare_layout(
+    backend: &mut dyn DisplayBackend,
+    profile_name: &str,
+    mut display_layout: DisplayLayout,
+    scope: &Scope,
+    global_config: &GlobalConfig,
+) -> Result<PreparedLayout> {
+    let report = if scope.selects_displays() {
+        validation::validate_partial_layout(&display_layout)
+    } else {
+        validation::validate_layout(&display_layout)
+    };
+    check_layout(profile_name, report)?;
+
+    let connected = identity::connected_monitors(backend)?;
+    identity::remap_layout(&connected, &mut display_layout);
+
+    let topology = if !scope.selects_displays() {
+        Some(display_layout.clone())
+    } else if !display_layout.displays.is_empty() {
+        // An empty list of displays means the profile only carries extras
+        let live_layout = backend
+            .get_display_layout(&GlobalConfig::default())
+            .map_err(|e| e.context("Could not load current display config"))?;
+        let merged = scope::merge_layouts(&live_layout, &display_layout);
+        debug!(
+            profile = profile_name,
+            changed = display_layout.displays.len(),
+            kept = merged.displays.len() - display_layout.displays.len();
+            "Merged partial layout"
+        );
+        check_layout(profile_name, validation::validate_layout(&merged))?;
+        Some(merged)
+    } else {
+        None
+    };
+
+    Ok(PreparedLayout {
+        topology,
+        extras: display_layout,
+        extras_config: scope.effective_config(global_config),
+    })
+}
 
-        // Run "before" commands if enabled
-        if global_config.run_commands && !config.run.is_empty() {
-            let output = Command::new("cmd")
-                .raw_arg("/C")
-                .raw_arg(&config.run.before.target)
-                .raw_arg(&config.run.before.args)
-                .output()
-                .expect("Failed to run `before` command.");
-
-            if debug {
-                println!("Running before command...");
-                println!("Status: `{}`", output.status);
-                println!("Stdout: `{}`", String::from_utf8_lossy(&output.stdout));
-                println!("Stderr: `{}`", String::from_utf8_lossy(&output.stderr));
+/// Applies `prepared` step by step and adds the steps to `report`. When a step fails,
+/// everything is set back to `snapshot` with [`OnFailure::Rollback`], the other steps are
+/// still made with [`OnFailure::BestEffort`].
+fn apply_prepared(
+    backend: &mut dyn DisplayBackend,
+    prepared: &PreparedLayout,
+    on_failure: OnFailure,
+    save: bool,
+    snapshot: &Snapshot,
+    report: &mut ApplyReport,
+) {
+    let go_on = |result: &Result<()>| match (result, on_failure) {
+        (Err(_), OnFailure::Rollback) => ControlFlow::Break(()),
+        _ => ControlFlow::Continue(()),
+    };
+    let topology = match &prepared.topology {
+        Some(topology) => {
+            let started = Instant::now();
+            let result = backend.apply_topology(topology, save).map(|flags| {
+                report.display_config_flags = Some(report::display_config_flag_names(flags));
+            });
+            let mut flow = go_on(&result);
+            let applied = result.is_ok();
+            report.record("the display layout".to_string(), result, false, started.elapsed());
+            // Drivers settle after SetDisplayConfig returns, the extras need the final modes
+            if applied {
+                let settle_timeout_ms = prepared.extras_config.settle_timeout_ms.into();
+                let timeout = Duration::from_millis(settle_timeout_ms);
+                report.verification = verify::verify_topology(backend, topology, timeout, save)
+                    .inspect_err(|e| warn!(error:% = e; "Could not check the displays"))
+                    .ok();
+                if record_differences(report) && on_failure == OnFailure::Rollback {
+                    flow = ControlFlow::Break(());
+                }
             }
+            flow
         }
+        None => ControlFlow::Continue(()),
+    };
+    if topology.is_continue() {
+        let _ = backend.apply_extras_with(&prepared.extras, &prepared.extras_config, &mut |made| {
+            let flow = go_on(&made.result);
+            let unchanged = report::unchanged(snapshot.layout(), &prepared.extras, &made);
+            report.record(made.describe(), made.result, unchanged, made.duration);
+            flow
+        });
+    }
+    report.record_disabled(&prepared.extras_config);
+
+    if report.failed() {
+        report.after = Some(after_failure(backend, on_failure, snapshot));
+    }
+}
 
-        if !config.name.is_empty() {
-            println!("Applying profile `{}`...", config.name);
-        } else {
-            println!("Applying profile `{}`...", profile_name);
+/// Applies a `layout.toml` as a single step and adds it to `report`, going back to
+/// `snapshot` if it fails with [`OnFailure::Rollback`]. The displays get `settle_timeout`
+/// to settle before they're checked.
+fn apply_layout_spec(
+    backend: &mut dyn DisplayBackend,
+    spec: &layout_spec::LayoutSpec,
+    on_failure: OnFailure,
+    save: bool,
+    settle_timeout: Duration,
+    snapshot: &Snapshot,
+    report: &mut ApplyReport,
+) {
+    let started = Instant::now();
+    let (result, requested) = match layout_spec::apply_layout_spec(backend, spec, save) {
+        Ok((flags, requested)) => {
+            report.display_config_flags = Some(report::display_config_flag_names(flags));
+            (Ok(()), Some(requested))
         }
+        Err(e) => (Err(e), None),
+    };
+    report.record("the layout".to_string(), result, false, started.elapsed());
+    if let Some(requested) = requested {
+        let set_again = |backend: &mut dyn DisplayBackend| {
+            layout_spec::apply_layout_spec(backend, spec, save).map(|(flags, _)| flags)
+        };
+        report.verification = verify::verify_with(backend, &requested, settle_timeout, set_again)
+            .inspect_err(|e| warn!(error:% = e; "Could not check the displays"))
+            .ok();
+        record_differences(report);
     }
+    if report.failed() {
+        report.after = Some(after_failure(backend, on_failure, snapshot));
+    }
+}
 
-    // Apply display layout
-    let display_layout_file = fs::File::open(&display_layout_file).map_err(|e| {
-        format!(
-            "Couldn't open display layout at {:?}: {}",
-            &display_layout_file, e
-        )
-    })?;
-    let display_layout_reader = BufReader::new(&display_layout_file);
-    let mut display_layout: DisplayLayout = serde_json::from_reader(display_layout_reader)
-        .map_err(|e| format!("Couldn't parse JSON from {:?}: {}", display_layout_file, e))?;
-
-    match wrapper.apply_display_layout(&mut display_layout, global_config) {
-        Ok(_) => println!(
-            "Successfully applied profile: `{}`",
-            if config_exists && !config.name.is_empty() {
-                &config.name
-            } else {
-                profile_name
-            }
-        ),
-        Err(e) => {
-            eprintln!("Failed to apply profile: {e}");
-            return Err(e);
+/// Adds a failed step to `report` if the displays still differ from the profile after
+/// they were checked, so that's handled like any other failed step. Returns whether they
+/// differ.
+fn record_differences(report: &mut ApplyReport) -> bool {
+    let Some(verification) = report.verification.as_ref().filter(|v| !v.is_match()) else {
+        return false;
+    };
+    let error = WompError::DisplaysDiffer(verification.differences.clone());
+    let waited = verification.waited;
+    report.record("the displays as in the profile".to_string(), Err(error), false, waited);
+    true
+}
+
+fn after_failure(
+    backend: &mut dyn DisplayBackend,
+    on_failure: OnFailure,
+    snapshot: &Snapshot,
+) -> AfterFailure {
+    match on_failure {
+        OnFailure::Rollback => snapshot.restore(backend),
+        OnFailure::BestEffort => AfterFailure::KeptGoing,
+    }
+}
+
+fn layout_spec_of<'a>(
+    resolved: &'a inheritance::ResolvedProfile,
+    profile_name: &str,
+) -> Result<&'a layout_spec::LayoutSpec> {
+    resolved.layout_spec.as_ref().ok_or_else(|| {
+        WompError::Validation(format!(
+            "Profile `{profile_name}` has neither a displays.json nor a layout.toml"
+        ))
+    })
+}
+
+/// What applying the profile `profile_name` would change, without changing anything.
+pub fn plan_display_layout(profile_name: &str, global_config: &GlobalConfig) -> Result<Plan> {
+    let mut backend = system_backend()?;
+    let store = FsProfileStore::open_default()?;
+    let inventory = load_inventory();
+    plan_display_layout_with(backend.as_mut(), &store, profile_name, global_config, &inventory)
+}
+
+/// Works out what [`apply_display_layout_with`] would do. Fails where applying would fail
+/// before changing anything, e.g. for an invalid layout. Monitors are called by their
+/// alias in `inventory`, if any.
+pub fn plan_display_layout_with(
+    backend: &mut dyn DisplayBackend,
+    store: &dyn ProfileStore,
+    profile_name: &str,
+    global_config: &GlobalConfig,
+    inventory: &Inventory,
+) -> Result<Plan> {
+    let resolved = inheritance::resolve_profile(store, profile_name)?;
+    let config = &resolved.config;
+    let mut changes = vec![];
+    for (stage, command) in [("before", &config.run.before), ("after", &config.run.after)] {
+        if global_config.run_commands && !command.is_empty() {
+            changes.push(plan::Change::RunHook {
+                stage: stage.to_string(),
+                command: command.command_line(),
+            });
         }
     }
 
-    // Run "after" commands if enabled
-    if global_config.run_commands && config_exists && !config.run.is_empty() {
-        let output = Command::new("cmd")
-            .raw_arg("/C")
-            .raw_arg(&config.run.after.target)
-            .raw_arg(&config.run.after.args)
-            .output()
-            .expect("Failed to run `after` command.");
-
-        if debug {
-            println!("Running after command...");
-            println!("Status: `{}`", output.status);
-            println!("Stdout: `{}`", String::from_utf8_lossy(&output.stdout));
-            println!("Stderr: `{}`", String::from_utf8_lossy(&output.stderr));
+    match &resolved.layout {
+        Some(display_layout) => {
+            let prepared = prepare_layout(
+                backend,
+                profile_name,
+                display_layout.clone(),
+                &config.scope,
+                global_config,
+            )?;
+            // Every extra, to tell which of the ones the profile sets change
+            let live_layout = capture_everything(backend, global_config)?;
+            changes.extend(plan::plan_layout(
+                &live_layout,
+                prepared.topology.as_ref(),
+                &prepared.extras,
+                &prepared.extras_config,
+                inventory,
+            ));
+        }
+        None => {
+            let spec = layout_spec_of(&resolved, profile_name)?;
+            changes.extend(layout_spec::plan_layout_spec(backend, spec)?);
         }
     }
 
+    Ok(Plan::new(profile_name, changes))
+}
+
+/// Refuses layouts that `SetDisplayConfig` would reject anyway, with a better explanation.
+fn check_layout(profile_name: &str, report: ValidationReport) -> Result<()> {
+    for issue in report.warnings() {
+        warn!(profile = profile_name, code = issue.code; "{}", issue.message);
+    }
+    if report.has_errors() {
+        let errors: Vec<String> = report.errors().map(|issue| issue.message.clone()).collect();
+        return Err(WompError::Validation(format!(
+            "Profile `{profile_name}` is invalid: {}",
+            errors.join("; ")
+        )));
+    }
     Ok(())
 }
 
-pub fn turn_off_all_displays(debug: bool) -> Result<(), String> {
-    let mut wrapper = CCDWrapper::new(true, debug);
-    wrapper.turn_off_all_displays()
+/// Checks the `displays.json` of a profile without applying it.
+pub fn validate_profile(profile_name: &str) -> Result<ValidationReport> {
+    let store = FsProfileStore::open_default()?;
+    validate_profile_with(&store, profile_name)
 }
 
-pub fn get_profiles() -> Result<Vec<(String, Option<Config>)>, String> {
-    let profiles = get_profiles_and_configs().unwrap();
-    Ok(profiles)
-}
\ No newline at end of file
+pub fn validate_profile_with(
+    store: &dyn ProfileStore,
+    profile_name: &str,
+) -> Result<ValidationReport> {
+    let resolved = inheritance::resolve_profile(store, profile_name)?;
+    let layout = resolved.layout.ok_or_else(|| {
+        WompError::Validation(format!("Profile `{profile_name}` has no displays.json"))
+    })?;
+    if resolved.config.scope.selects_displays() {
+        Ok(validation::validate_partial_layout(&layout))
+    } else {
+        Ok(validation::validate_layout(&layout))
+    }
+}
+
+pub fn turn_off_all_displays() -> Result<()> {
+    system_backend()?.turn_off_all_displays()
+}
+
+pub fn get_profiles() -> Result<Vec<(String, Option<Config>)>> {
+    get_profiles_and_configs()
+}
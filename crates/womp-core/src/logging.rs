@@ -0,0 +1,181 @@
+use crate::config_manager::get_config_dir;
+use crate::error::{Result, WompError};
+use crate::serde_types::global_config::LogLevel;
+use log::kv::{self, Key, Value, VisitSource};
+use log::{Log, Metadata, Record};
+use std::fmt::Write as _;
+use std::fs::{self, File, OpenOptions};
+use std::io::{self, Write};
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+
+pub const LOG_DIR: &str = "logs";
+pub const LOG_FILE_NAME: &str = "womp.log";
+
+/// `womp.log` is rotated to `womp.1.log` once it would grow past this.
+const MAX_LOG_FILE_SIZE: u64 = 1024 * 1024;
+/// How many rotated files (`womp.1.log` ... `womp.N.log`) are kept.
+const MAX_ROTATED_FILES: u32 = 3;
+
+/// Where log events end up.
+pub enum LogTarget {
+    Stderr,
+    /// A log file that's rotated once it gets too big.
+    File(PathBuf),
+}
+
+/// `logs/womp.log` in the resolved config root.
+pub fn log_file_path() -> Result<PathBuf> {
+    Ok(get_config_dir()?.join(LOG_DIR).join(LOG_FILE_NAME))
+}
+
+/// Installs the WOMP logger. Only the first call installs it, later calls just change the level.
+pub fn init(target: LogTarget, level: LogLevel) -> Result<()> {
+    let sink = match target {
+        LogTarget::Stderr => Sink::Stderr,
+        LogTarget::File(path) => Sink::File(RotatingFile::open(path)?),
+    };
+    // Fails if a logger is already installed, in which case we keep that one
+    let _ = log::set_boxed_logger(Box::new(WompLogger {
+        sink: Mutex::new(sink),
+    }));
+    set_level(level);
+    Ok(())
+}
+
+pub fn set_level(level: LogLevel) {
+    log::set_max_level(level.into());
+}
+
+struct WompLogger {
+    sink: Mutex<Sink>,
+}
+
+enum Sink {
+    Stderr,
+    File(RotatingFile),
+}
+
+impl Log for WompLogger {
+    fn enabled(&self, metadata: &Metadata) -> bool {
+        // Dependencies only get to log their info and above, their debug output is noise to us
+        metadata.level() <= log::max_level()
+            && (metadata.target().starts_with("womp") || metadata.level() <= log::Level::Info)
+    }
+
+    fn log(&self, record: &Record) {
+        if !self.enabled(record.metadata()) {
+            return;
+        }
+
+        let mut line = format!(
+            "{} {:<5} {}: {}",
+            chrono::Local::now().format("%Y-%m-%d %H:%M:%S%.3f"),
+            record.level(),
+            record.target(),
+            record.args()
+        );
+        let _ = record.key_values().visit(&mut KeyValues(&mut line));
+        line.push('\n');
+
+        let Ok(mut sink) = self.sink.lock() else {
+            return;
+        };
+        match &mut *sink {
+            Sink::Stderr => {
+                let _ = io::stderr().lock().write_all(line.as_bytes());
+            }
+            Sink::File(file) => {
+                if let Err(e) = file.write_line(&line) {
+                    eprintln!("Failed to write to {}: {e}", file.path.display());
+                }
+            }
+        }
+    }
+
+    fn flush(&self) {
+        if let Ok(mut sink) = self.sink.lock() {
+            match &mut *sink {
+                Sink::Stderr => {
+                    let _ = io::stderr().flush();
+                }
+                Sink::File(file) => {
+                    let _ = file.file.flush();
+                }
+            }
+        }
+    }
+}
+
+/// Appends the structured fields of an event as ` key=value`.
+struct KeyValues<'a>(&'a mut String);
+
+impl<'kvs> VisitSource<'kvs> for KeyValues<'_> {
+    fn visit_pair(
+        &mut self,
+        key: Key<'kvs>,
+        value: Value<'kvs>,
+    ) -> std::result::Result<(), kv::Error> {
+        let _ = write!(self.0, " {key}={value}");
+        Ok(())
+    }
+}
+
+struct RotatingFile {
+    path: PathBuf,
+    file: File,
+    size: u64,
+}
+
+impl RotatingFile {
+    fn open(path: PathBuf) -> Result<Self> {
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent).map_err(|e| WompError::io(parent, e))?;
+        }
+        let file = OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(&path)
+            .map_err(|e| WompError::io(&path, e))?;
+        let size = file.metadata().map(|m| m.len()).unwrap_or(0);
+        Ok(RotatingFile { path, file, size })
+    }
+
+    fn write_line(&mut self, line: &str) -> io::Result<()> {
+        if self.size > 0 && self.size + line.len() as u64 > MAX_LOG_FILE_SIZE {
+            self.rotate()?;
+        }
+        self.file.write_all(line.as_bytes())?;
+        self.size += line.len() as u64;
+        Ok(())
+    }
+
+    /// `womp.log` -> `womp.1.log` -> `womp.2.log` ..., the oldest one is dropped.
+    fn rotate(&mut self) -> io::Result<()> {
+        for n in (1..MAX_ROTATED_FILES).rev() {
+            rename_if_exists(
+                &rotated_path(&self.path, n),
+                &rotated_path(&self.path, n + 1),
+            )?;
+        }
+        rename_if_exists(&self.path, &rotated_path(&self.path, 1))?;
+        self.file = OpenOptions::new()
+            .create(true)
+            .write(true)
+            .truncate(true)
+            .open(&self.path)?;
+        self.size = 0;
+        Ok(())
+    }
+}
+
+fn rotated_path(path: &Path, n: u32) -> PathBuf {
+    path.with_extension(format!("{n}.log"))
+}
+
+fn rename_if_exists(from: &Path, to: &Path) -> io::Result<()> {
+    match fs::rename(from, to) {
+        Err(e) if e.kind() != io::ErrorKind::NotFound => Err(e),
+        _ => Ok(()),
+    }
+}
@@ -0,0 +1,275 @@
+use crate::error::{Result, WompError};
+use crate::serde_types::{DisplayLayout, config::Config};
+use log::info;
+use serde_json::json;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+/// Upgrades a `displays.json` by one version, entry `n` goes from version `n` to `n + 1`.
+type LayoutMigration = fn(&mut serde_json::Map<String, serde_json::Value>);
+/// Upgrades a `profile.toml` by one version, entry `n` goes from version `n` to `n + 1`.
+type ConfigMigration = fn(&mut toml::Table);
+
+const LAYOUT_MIGRATIONS: &[LayoutMigration] = &[layout_v0_to_v1];
+const CONFIG_MIGRATIONS: &[ConfigMigration] = &[config_v0_to_v1];
+
+/// Format version of `displays.json` files written by this build.
+pub const DISPLAY_LAYOUT_VERSION: u32 = LAYOUT_MIGRATIONS.len() as u32;
+/// Format version of `profile.toml` files written by this build.
+pub const CONFIG_VERSION: u32 = CONFIG_MIGRATIONS.len() as u32;
+
+const VERSION_KEY: &str = "version";
+
+// Layouts from before versioning. The oldest ones don't have `globalInfo` yet.
+fn layout_v0_to_v1(layout: &mut serde_json::Map<String, serde_json::Value>) {
+    layout.entry("globalInfo").or_insert_with(|| json!({}));
+}
+
+// Configs from before versioning only gain the version field.
+fn config_v0_to_v1(_config: &mut toml::Table) {}
+
+/// A file that was upgraded to the current format.
+#[derive(Debug, Clone)]
+pub struct Migration {
+    pub path: PathBuf,
+    pub from: u32,
+    pub to: u32,
+    /// Copy of the file as it was before the upgrade.
+    pub backup: PathBuf,
+}
+
+/// Upgrades `displays.json` at `path` in place if an older WOMP wrote it.
+/// Returns `None` if the file is missing or already current.
+pub fn migrate_layout_file(path: &Path) -> Result<Option<Migration>> {
+    let Some(contents) = read_if_exists(path)? else {
+        return Ok(None);
+    };
+    let mut value: serde_json::Value =
+        serde_json::from_str(&contents).map_err(|e| WompError::parse(path, e))?;
+    let Some(from) = migrate_layout_value(path, &mut value)? else {
+        return Ok(None);
+    };
+    let layout: DisplayLayout =
+        serde_json::from_value(value).map_err(|e| WompError::parse(path, e))?;
+    let upgraded = serde_json::to_string_pretty(&layout)
+        .map_err(|e| WompError::serialize("display layout", e))?;
+    write_upgraded(path, &contents, &upgraded, from, DISPLAY_LAYOUT_VERSION).map(Some)
+}
+
+/// Upgrades `profile.toml` at `path` in place if an older WOMP wrote it.
+/// Returns `None` if the file is missing or already current.
+pub fn migrate_config_file(path: &Path) -> Result<Option<Migration>> {
+    let Some(contents) = read_if_exists(path)? else {
+        return Ok(None);
+    };
+    let mut table: toml::Table =
+        toml::from_str(&contents).map_err(|e| WompError::parse(path, e))?;
+    let Some(from) = migrate_config_table(path, &mut table)? else {
+        return Ok(None);
+    };
+    let config: Config = table.try_into().map_err(|e| WompError::parse(path, e))?;
+    let upgraded = toml::to_string(&config)
+        .map_err(|e| WompError::serialize(format!("`{}`", path.display()), e))?;
+    write_upgraded(path, &contents, &upgraded, from, CONFIG_VERSION).map(Some)
+}
+
+/// Runs the layout migrations on a parsed `displays.json` and returns the version it had,
+/// or `None` if it was already current. `path` is only used for errors.
+pub fn migrate_layout_value(path: &Path, value: &mut serde_json::Value) -> Result<Option<u32>> {
+    let Some(layout) = value.as_object_mut() else {
+        return Err(WompError::parse(path, "expected a JSON object"));
+    };
+    let version = match layout.get(VERSION_KEY) {
+        None => 0,
+        Some(version) => version
+            .as_u64()
+            .and_then(|v| u32::try_from(v).ok())
+            .ok_or_else(|| WompError::parse(path, "`version` is not a valid version number"))?,
+    };
+    if !needs_migration(path, version, DISPLAY_LAYOUT_VERSION)? {
+        return Ok(None);
+    }
+    for migrate in &LAYOUT_MIGRATIONS[version as usize..] {
+        migrate(layout);
+    }
+    layout.insert(VERSION_KEY.to_string(), json!(DISPLAY_LAYOUT_VERSION));
+    Ok(Some(version))
+}
+
+/// Runs the config migrations on a parsed `profile.toml` and returns the version it had,
+/// or `None` if it was already current. `path` is only used for errors.
+pub fn migrate_config_table(path: &Path, table: &mut toml::Table) -> Result<Option<u32>> {
+    let version = match table.get(VERSION_KEY) {
+        None => 0,
+        Some(version) => version
+            .as_integer()
+            .and_then(|v| u32::try_from(v).ok())
+            .ok_or_else(|| WompError::parse(path, "`version` is not a valid version number"))?,
+    };
+    if !needs_migration(path, version, CONFIG_VERSION)? {
+        return Ok(None);
+    }
+    for migrate in &CONFIG_MIGRATIONS[version as usize..] {
+        migrate(table);
+    }
+    table.insert(VERSION_KEY.to_string(), i64::from(CONFIG_VERSION).into());
+    Ok(Some(version))
+}
+
+fn needs_migration(path: &Path, found: u32, supported: u32) -> Result<bool> {
+    if found > supported {
+        return Err(WompError::UnsupportedVersion {
+            path: path.to_path_buf(),
+            found,
+            supported,
+        });
+    }
+    Ok(found < supported)
+}
+
+fn read_if_exists(path: &Path) -> Result<Option<String>> {
+    match fs::read_to_string(path) {
+        Ok(contents) => Ok(Some(contents)),
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(None),
+        Err(e) => Err(WompError::io(path, e)),
+    }
+}
+
+/// `displays.json` -> `displays.json.v0.bak`
+fn backup_path(path: &Path, version: u32) -> PathBuf {
+    let mut file_name = path.file_name().unwrap_or_default().to_os_string();
+    file_name.push(format!(".v{version}.bak"));
+    path.with_file_name(file_name)
+}
+
+fn write_upgraded(
+    path: &Path,
+    original: &str,
+    upgraded: &str,
+    from: u32,
+    to: u32,
+) -> Result<Migration> {
+    let backup = backup_path(path, from);
+    fs::write(&backup, original).map_err(|e| WompError::io(&backup, e))?;
+    fs::write(path, upgraded).map_err(|e| WompError::io(path, e))?;
+    info!(path:% = path.display(), from, to; "Upgraded file format");
+    Ok(Migration {
+        path: path.to_path_buf(),
+        from,
+        to,
+        backup,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing::{layout, monitor};
+
+    /// An empty directory of its own for each test.
+    fn scratch_dir(name: &str) -> PathBuf {
+        let dir =
+            std::env::temp_dir().join(format!("womp-migration-{}-{name}", std::process::id()));
+        let _ = fs::remove_dir_all(&dir);
+        fs::create_dir_all(&dir).unwrap();
+        dir
+    }
+
+    /// A `displays.json` like WOMP wrote it before versioning and `globalInfo`.
+    fn layout_v0() -> serde_json::Value {
+        // Captured with the DPI scale saved
+        let mut captured = monitor(1, 0, 60);
+        captured.extras.dpiScale = Some(100);
+        let mut value = serde_json::to_value(layout(&[captured])).unwrap();
+        let layout = value.as_object_mut().unwrap();
+        layout.remove(VERSION_KEY);
+        layout.remove("globalInfo");
+        value
+    }
+
+    #[test]
+    fn v0_layouts_gain_global_info() {
+        let mut layout = serde_json::Map::new();
+        layout_v0_to_v1(&mut layout);
+        assert_eq!(layout["globalInfo"], json!({}));
+
+        let mut layout = serde_json::Map::new();
+        layout.insert("globalInfo".to_string(), json!({ "iconSize": 32 }));
+        layout_v0_to_v1(&mut layout);
+        assert_eq!(layout["globalInfo"], json!({ "iconSize": 32 }));
+    }
+
+    #[test]
+    fn layout_value_is_upgraded_once() {
+        let path = Path::new("displays.json");
+        let mut value = layout_v0();
+        assert_eq!(migrate_layout_value(path, &mut value).unwrap(), Some(0));
+        assert_eq!(value[VERSION_KEY], json!(DISPLAY_LAYOUT_VERSION));
+        assert_eq!(migrate_layout_value(path, &mut value).unwrap(), None);
+    }
+
+    #[test]
+    fn newer_layouts_are_refused() {
+        let mut value = layout_v0();
+        value[VERSION_KEY] = json!(DISPLAY_LAYOUT_VERSION + 1);
+        assert!(matches!(
+            migrate_layout_value(Path::new("displays.json"), &mut value),
+            Err(WompError::UnsupportedVersion { .. })
+        ));
+    }
+
+    #[test]
+    fn layout_file_is_backed_up_before_upgrading() {
+        let dir = scratch_dir("layout");
+        let path = dir.join("displays.json");
+        let original = serde_json::to_string(&layout_v0()).unwrap();
+        fs::write(&path, &original).unwrap();
+
+        let migration = migrate_layout_file(&path).unwrap().unwrap();
+        assert_eq!((migration.from, migration.to), (0, DISPLAY_LAYOUT_VERSION));
+        assert_eq!(migration.backup, dir.join("displays.json.v0.bak"));
+        assert_eq!(fs::read_to_string(&migration.backup).unwrap(), original);
+        let upgraded: DisplayLayout =
+            serde_json::from_str(&fs::read_to_string(&path).unwrap()).unwrap();
+        assert_eq!(upgraded.version, DISPLAY_LAYOUT_VERSION);
+        assert_eq!(upgraded.displays.len(), 1);
+
+        // Already current, nothing is written
+        fs::remove_file(&migration.backup).unwrap();
+        assert!(migrate_layout_file(&path).unwrap().is_none());
+        assert!(!migration.backup.exists());
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn config_file_is_backed_up_before_upgrading() {
+        let dir = scratch_dir("config");
+        let path = dir.join("profile.toml");
+        let original = "name = \"Desk\"\n";
+        fs::write(&path, original).unwrap();
+
+        let migration = migrate_config_file(&path).unwrap().unwrap();
+        assert_eq!(migration.backup, dir.join("profile.toml.v0.bak"));
+        assert_eq!(fs::read_to_string(&migration.backup).unwrap(), original);
+        let upgraded: Config = toml::from_str(&fs::read_to_string(&path).unwrap()).unwrap();
+        assert_eq!(upgraded.version, CONFIG_VERSION);
+        assert_eq!(upgraded.name, "Desk");
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn missing_files_are_left_alone() {
+        let dir = scratch_dir("missing");
+        assert!(
+            migrate_layout_file(&dir.join("displays.json"))
+                .unwrap()
+                .is_none()
+        );
+        assert!(
+            migrate_config_file(&dir.join("profile.toml"))
+                .unwrap()
+                .is_none()
+        );
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
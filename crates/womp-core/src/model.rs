@@ -0,0 +1,370 @@
+//! Platform-neutral view of a display layout.
+//!
+//! [`Layout`] and [`Monitor`] describe what a profile *means* (resolution, refresh rate,
+//! position, rotation, ...) without any Win32 unions, so they can be inspected and edited
+//! on any OS. Everything the CCD API needs to re-apply a layout that has no obvious meaning
+//! lives in [`CcdDetails`], which makes the conversion to and from
+//! [`crate::serde_types::DisplayLayout`] lossless. Only names longer than the Win32
+//! buffers hold, which can't come from Windows, are cut off on the way back.
+
+use crate::edid::EdidInfo;
+use crate::serde_types::{GlobalInfo, optional_info::OptionalInfo};
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+use std::fmt;
+
+mod ccd;
+
+pub(crate) use ccd::wide_to_string;
+
+/// A whole display layout, i.e. everything stored in a profile's `displays.json`.
+#[derive(Serialize, Deserialize, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct Layout {
+    pub monitors: Vec<Monitor>,
+    pub global: GlobalInfo,
+}
+
+impl Layout {
+    /// The monitor whose desktop origin is at (0, 0), if any.
+    pub fn primary(&self) -> Option<&Monitor> {
+        self.monitors.iter().find(|m| m.is_primary())
+    }
+}
+
+/// A single display path: one monitor driven by one adapter output.
+#[derive(Serialize, Deserialize, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct Monitor {
+    pub adapter: Adapter,
+    pub source_id: u32,
+    pub target_id: u32,
+    /// Friendly name reported by the monitor, e.g. `DELL U2720Q`.
+    pub name: String,
+    /// Device interface path of the monitor, stable across reboots.
+    pub device_path: String,
+    pub edid: EdidIds,
+    /// What the EDID says about the monitor, if it was captured.
+    pub edid_info: Option<EdidInfo>,
+    pub connector_instance: u32,
+    pub output_technology: OutputTechnology,
+    pub mode: Mode,
+    pub position: Position,
+    pub rotation: Rotation,
+    pub extras: OptionalInfo,
+    pub ccd: CcdDetails,
+}
+
+impl Monitor {
+    /// Windows makes the display at the desktop origin the primary one.
+    pub fn is_primary(&self) -> bool {
+        self.position == Position { x: 0, y: 0 }
+    }
+}
+
+/// The graphics adapter a monitor is connected to.
+///
+/// The LUID is only unique until the next reboot; the device path is stable.
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, Default, PartialEq, Eq, Hash)]
+#[serde(rename_all = "camelCase")]
+pub struct AdapterLuid {
+    pub low_part: u32,
+    pub high_part: i32,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, Default, PartialEq, Eq)]
+#[serde(rename_all = "camelCase")]
+pub struct Adapter {
+    pub luid: AdapterLuid,
+    pub device_path: String,
+}
+
+/// Manufacturer and product codes from the monitor's EDID.
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, Default, PartialEq, Eq, Hash)]
+#[serde(rename_all = "camelCase")]
+pub struct EdidIds {
+    pub manufacturer_id: u16,
+    pub product_code: u16,
+}
+
+/// Resolution of the desktop source and refresh rate of the target.
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, Default, PartialEq, Eq)]
+#[serde(rename_all = "camelCase")]
+pub struct Mode {
+    pub width: u32,
+    pub height: u32,
+    pub refresh_rate: RefreshRate,
+}
+
+/// Top-left corner of a monitor in desktop coordinates.
+#[derive(Serialize, Deserialize, JsonSchema, Clone, Copy, Debug, Default, PartialEq, Eq, Hash)]
+pub struct Position {
+    pub x: i32,
+    pub y: i32,
+}
+
+/// A frequency stored as a fraction, exactly as the driver reports it (e.g. 59.94 Hz is
+/// `60000 / 1001`).
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, Default)]
+#[serde(rename_all = "camelCase")]
+pub struct RefreshRate {
+    pub numerator: u32,
+    pub denominator: u32,
+}
+
+impl RefreshRate {
+    pub fn new(numerator: u32, denominator: u32) -> Self {
+        RefreshRate {
+            numerator,
+            denominator,
+        }
+    }
+
+    /// The rate in Hz, or `None` if the denominator is zero (rate not specified).
+    pub fn as_hz(&self) -> Option<f64> {
+        if self.denominator == 0 {
+            None
+        } else {
+            Some(self.numerator as f64 / self.denominator as f64)
+        }
+    }
+}
+
+// 120/2 and 60/1 are the same rate, an unspecified rate is only the same as another one
+impl PartialEq for RefreshRate {
+    fn eq(&self, other: &Self) -> bool {
+        match (self.denominator, other.denominator) {
+            (0, 0) => true,
+            (0, _) | (_, 0) => false,
+            _ => {
+                self.numerator as u64 * other.denominator as u64
+                    == other.numerator as u64 * self.denominator as u64
+            }
+        }
+    }
+}
+
+impl Eq for RefreshRate {}
+
+impl fmt::Display for RefreshRate {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.as_hz() {
+            Some(hz) => write!(f, "{hz:.2} Hz"),
+            None => write!(f, "unspecified"),
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq, Eq, Hash)]
+#[serde(rename_all = "camelCase")]
+pub enum Rotation {
+    Identity,
+    Rotate90,
+    Rotate180,
+    Rotate270,
+    /// A value this model doesn't know about, kept as-is.
+    Other(i32),
+}
+
+impl From<i32> for Rotation {
+    fn from(value: i32) -> Self {
+        match value {
+            1 => Rotation::Identity,
+            2 => Rotation::Rotate90,
+            3 => Rotation::Rotate180,
+            4 => Rotation::Rotate270,
+            other => Rotation::Other(other),
+        }
+    }
+}
+
+impl From<Rotation> for i32 {
+    fn from(value: Rotation) -> Self {
+        match value {
+            Rotation::Identity => 1,
+            Rotation::Rotate90 => 2,
+            Rotation::Rotate180 => 3,
+            Rotation::Rotate270 => 4,
+            Rotation::Other(other) => other,
+        }
+    }
+}
+
+/// How the monitor is connected, as reported by the driver.
+#[derive(Serialize, Deserialize, JsonSchema, Clone, Copy, Debug, PartialEq, Eq, Hash)]
+#[serde(rename_all = "camelCase")]
+pub enum OutputTechnology {
+    Hd15,
+    Dvi,
+    Hdmi,
+    DisplayPort,
+    Internal,
+    Miracast,
+    Other(i32),
+}
+
+impl From<i32> for OutputTechnology {
+    fn from(value: i32) -> Self {
+        match value {
+            0 => OutputTechnology::Hd15,
+            4 => OutputTechnology::Dvi,
+            5 => OutputTechnology::Hdmi,
+            10 => OutputTechnology::DisplayPort,
+            15 => OutputTechnology::Miracast,
+            i32::MIN => OutputTechnology::Internal,
+            other => OutputTechnology::Other(other),
+        }
+    }
+}
+
+impl From<OutputTechnology> for i32 {
+    fn from(value: OutputTechnology) -> Self {
+        match value {
+            OutputTechnology::Hd15 => 0,
+            OutputTechnology::Dvi => 4,
+            OutputTechnology::Hdmi => 5,
+            OutputTechnology::DisplayPort => 10,
+            OutputTechnology::Miracast => 15,
+            OutputTechnology::Internal => i32::MIN,
+            OutputTechnology::Other(other) => other,
+        }
+    }
+}
+
+impl fmt::Display for OutputTechnology {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            OutputTechnology::Hd15 => write!(f, "VGA"),
+            OutputTechnology::Dvi => write!(f, "DVI"),
+            OutputTechnology::Hdmi => write!(f, "HDMI"),
+            OutputTechnology::DisplayPort => write!(f, "DisplayPort"),
+            OutputTechnology::Internal => write!(f, "internal"),
+            OutputTechnology::Miracast => write!(f, "Miracast"),
+            OutputTechnology::Other(value) => write!(f, "other ({value})"),
+        }
+    }
+}
+
+/// Width and height of a region in pixels.
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct Size {
+    pub cx: u32,
+    pub cy: u32,
+}
+
+/// Full timing information of a target mode.
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, Default, PartialEq, Eq)]
+#[serde(rename_all = "camelCase")]
+pub struct VideoSignal {
+    pub pixel_rate: u64,
+    pub h_sync_freq: RefreshRate,
+    pub v_sync_freq: RefreshRate,
+    pub active_size: Size,
+    pub total_size: Size,
+    pub video_standard: u32,
+    pub scan_line_ordering: i32,
+}
+
+/// CCD specific values that have to survive a round trip but that nobody should need to
+/// look at. Enum-like values are kept as their raw Win32 numbers.
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, Default, PartialEq, Eq)]
+#[serde(rename_all = "camelCase")]
+pub struct CcdDetails {
+    pub path_flags: u32,
+    pub source_adapter: AdapterLuid,
+    pub source_status_flags: u32,
+    pub source_mode_info_idx: u32,
+    pub target_status_flags: u32,
+    pub target_mode_info_idx: u32,
+    pub target_available: bool,
+    pub scaling: i32,
+    pub scan_line_ordering: i32,
+    pub source_mode: ModeHeader,
+    pub pixel_format: i32,
+    pub target_mode: ModeHeader,
+    pub video_signal: VideoSignal,
+    pub target_name_header: DeviceInfoHeader,
+    pub target_name_flags: u32,
+    pub target_name_output_technology: i32,
+    pub adapter_name_header: DeviceInfoHeader,
+}
+
+/// The non-union part of a `DISPLAYCONFIG_MODE_INFO`.
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, Default, PartialEq, Eq)]
+#[serde(rename_all = "camelCase")]
+pub struct ModeHeader {
+    pub info_type: i32,
+    pub id: u32,
+    pub adapter: AdapterLuid,
+}
+
+/// A `DISPLAYCONFIG_DEVICE_INFO_HEADER`.
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, Default, PartialEq, Eq)]
+#[serde(rename_all = "camelCase")]
+pub struct DeviceInfoHeader {
+    pub info_type: i32,
+    pub size: u32,
+    pub adapter: AdapterLuid,
+    pub id: u32,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::serde_types::DisplayLayout;
+    use crate::testing::{layout, monitor};
+
+    #[test]
+    fn refresh_rates_compare_as_fractions() {
+        assert_eq!(RefreshRate::new(120, 2), RefreshRate::new(60, 1));
+        assert_ne!(RefreshRate::new(60000, 1001), RefreshRate::new(60, 1));
+        assert_ne!(RefreshRate::new(60, 1), RefreshRate::new(50, 1));
+    }
+
+    #[test]
+    fn unspecified_refresh_rate_is_only_equal_to_itself() {
+        let unspecified = RefreshRate::default();
+        assert_eq!(unspecified, RefreshRate::new(0, 0));
+        assert_ne!(unspecified, RefreshRate::new(60, 1));
+        assert_ne!(RefreshRate::new(50, 1), unspecified);
+        assert_ne!(unspecified, RefreshRate::new(0, 1));
+    }
+
+    #[test]
+    fn display_layout_round_trips_through_the_model() {
+        let mut rotated = monitor(1, 0, 60);
+        rotated.rotation = Rotation::Rotate90;
+        rotated.mode.refresh_rate = RefreshRate::new(60000, 1001);
+        rotated.name = "N".repeat(63);
+        rotated.extras.dpiScale = Some(150);
+        rotated.extras.hdrEnabled = Some(true);
+        rotated.ccd.source_status_flags = 1;
+        rotated.ccd.target_status_flags = 3;
+        rotated.ccd.target_name_flags = 2;
+        rotated.ccd.target_name_header = DeviceInfoHeader {
+            info_type: 2,
+            size: 420,
+            adapter: rotated.adapter.luid,
+            id: 1,
+        };
+        // A virtual mode, the driver picks the rate
+        let mut virtual_mode = monitor(2, 1080, 0);
+        virtual_mode.mode.refresh_rate = RefreshRate::default();
+        virtual_mode.ccd.video_signal.v_sync_freq = RefreshRate::default();
+        let display_layout = layout(&[rotated, virtual_mode]);
+
+        let round_tripped: DisplayLayout = (&Layout::from(&display_layout)).into();
+        assert_eq!(
+            serde_json::to_value(&round_tripped).unwrap(),
+            serde_json::to_value(&display_layout).unwrap()
+        );
+    }
+
+    #[test]
+    fn too_long_names_are_cut_off() {
+        let mut long_name = monitor(1, 0, 60);
+        long_name.name = "N".repeat(100);
+        let display: crate::serde_types::Display = (&long_name).into();
+        assert_eq!(Monitor::from(&display).name, "N".repeat(63));
+    }
+}
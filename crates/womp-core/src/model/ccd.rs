@@ -0,0 +1,327 @@
+//! Conversions between the neutral model and the CCD structs stored in `displays.json`.
+
+use super::*;
+use crate::serde_types::{Display, DisplayLayout};
+use crate::win32_types::*;
+use widestring::U16CString;
+
+impl From<LUID> for AdapterLuid {
+    fn from(luid: LUID) -> Self {
+        AdapterLuid {
+            low_part: luid.LowPart,
+            high_part: luid.HighPart,
+        }
+    }
+}
+
+impl From<AdapterLuid> for LUID {
+    fn from(luid: AdapterLuid) -> Self {
+        LUID {
+            LowPart: luid.low_part,
+            HighPart: luid.high_part,
+        }
+    }
+}
+
+impl From<DISPLAYCONFIG_RATIONAL> for RefreshRate {
+    fn from(rational: DISPLAYCONFIG_RATIONAL) -> Self {
+        RefreshRate::new(rational.Numerator, rational.Denominator)
+    }
+}
+
+impl From<RefreshRate> for DISPLAYCONFIG_RATIONAL {
+    fn from(rate: RefreshRate) -> Self {
+        DISPLAYCONFIG_RATIONAL {
+            Numerator: rate.numerator,
+            Denominator: rate.denominator,
+        }
+    }
+}
+
+impl From<DISPLAYCONFIG_2DREGION> for Size {
+    fn from(region: DISPLAYCONFIG_2DREGION) -> Self {
+        Size {
+            cx: region.cx,
+            cy: region.cy,
+        }
+    }
+}
+
+impl From<Size> for DISPLAYCONFIG_2DREGION {
+    fn from(size: Size) -> Self {
+        DISPLAYCONFIG_2DREGION {
+            cx: size.cx,
+            cy: size.cy,
+        }
+    }
+}
+
+impl From<DISPLAYCONFIG_VIDEO_SIGNAL_INFO> for VideoSignal {
+    fn from(signal: DISPLAYCONFIG_VIDEO_SIGNAL_INFO) -> Self {
+        VideoSignal {
+            pixel_rate: signal.pixelRate,
+            h_sync_freq: signal.hSyncFreq.into(),
+            v_sync_freq: signal.vSyncFreq.into(),
+            active_size: signal.activeSize.into(),
+            total_size: signal.totalSize.into(),
+            // Same as the serde helper, the union is treated as the plain u32
+            video_standard: unsafe { signal.Anonymous.videoStandard },
+            scan_line_ordering: signal.scanLineOrdering.0,
+        }
+    }
+}
+
+impl From<VideoSignal> for DISPLAYCONFIG_VIDEO_SIGNAL_INFO {
+    fn from(signal: VideoSignal) -> Self {
+        DISPLAYCONFIG_VIDEO_SIGNAL_INFO {
+            pixelRate: signal.pixel_rate,
+            hSyncFreq: signal.h_sync_freq.into(),
+            vSyncFreq: signal.v_sync_freq.into(),
+            activeSize: signal.active_size.into(),
+            totalSize: signal.total_size.into(),
+            Anonymous: DISPLAYCONFIG_VIDEO_SIGNAL_INFO_0 {
+                videoStandard: signal.video_standard,
+            },
+            scanLineOrdering: DISPLAYCONFIG_SCANLINE_ORDERING(signal.scan_line_ordering),
+        }
+    }
+}
+
+impl From<DISPLAYCONFIG_DEVICE_INFO_HEADER> for DeviceInfoHeader {
+    fn from(header: DISPLAYCONFIG_DEVICE_INFO_HEADER) -> Self {
+        DeviceInfoHeader {
+            info_type: header.r#type.0,
+            size: header.size,
+            adapter: header.adapterId.into(),
+            id: header.id,
+        }
+    }
+}
+
+impl From<DeviceInfoHeader> for DISPLAYCONFIG_DEVICE_INFO_HEADER {
+    fn from(header: DeviceInfoHeader) -> Self {
+        DISPLAYCONFIG_DEVICE_INFO_HEADER {
+            r#type: DISPLAYCONFIG_DEVICE_INFO_TYPE(header.info_type),
+            size: header.size,
+            adapterId: header.adapter.into(),
+            id: header.id,
+        }
+    }
+}
+
+fn mode_header(mode: &DISPLAYCONFIG_MODE_INFO) -> ModeHeader {
+    ModeHeader {
+        info_type: mode.infoType.0,
+        id: mode.id,
+        adapter: mode.adapterId.into(),
+    }
+}
+
+fn empty_mode(header: ModeHeader) -> DISPLAYCONFIG_MODE_INFO {
+    DISPLAYCONFIG_MODE_INFO {
+        infoType: DISPLAYCONFIG_MODE_INFO_TYPE(header.info_type),
+        id: header.id,
+        adapterId: header.adapter.into(),
+        Anonymous: unsafe { std::mem::zeroed() },
+    }
+}
+
+pub(crate) fn wide_to_string<const N: usize>(wide: &[u16; N]) -> String {
+    U16CString::from_vec_truncate(wide).to_string_lossy()
+}
+
+/// `s` as a nul-terminated Win32 string, cut off if it doesn't fit.
+fn string_to_wide<const N: usize>(s: &str) -> [u16; N] {
+    let mut wide = [0u16; N];
+    // Leave room for the terminating nul
+    for (dst, src) in wide.iter_mut().take(N - 1).zip(s.encode_utf16()) {
+        *dst = src;
+    }
+    wide
+}
+
+impl From<&Display> for Monitor {
+    fn from(display: &Display) -> Self {
+        let path = &display.pathInfo;
+        let source_mode = &display.modeInfo.sourceModeInfo;
+        let target_mode = &display.modeInfo.targetModeInfo;
+        let target_name = &display.additionalInfo.target;
+        let adapter_name = &display.additionalInfo.adapter;
+
+        // Only read the union variant the mode says it holds, like the serde helpers do
+        let source = if source_mode.infoType == DISPLAYCONFIG_MODE_INFO_TYPE_SOURCE {
+            Some(unsafe { source_mode.Anonymous.sourceMode })
+        } else {
+            None
+        };
+        let video_signal = if target_mode.infoType == DISPLAYCONFIG_MODE_INFO_TYPE_TARGET {
+            unsafe { target_mode.Anonymous.targetMode.targetVideoSignalInfo }.into()
+        } else {
+            VideoSignal::default()
+        };
+
+        let (width, height, pixel_format, position) = match source {
+            Some(source) => (
+                source.width,
+                source.height,
+                source.pixelFormat.0,
+                Position {
+                    x: source.position.x,
+                    y: source.position.y,
+                },
+            ),
+            None => (0, 0, 0, Position::default()),
+        };
+
+        Monitor {
+            adapter: Adapter {
+                luid: path.targetInfo.adapterId.into(),
+                device_path: wide_to_string(&adapter_name.adapterDevicePath),
+            },
+            source_id: path.sourceInfo.id,
+            target_id: path.targetInfo.id,
+            name: wide_to_string(&target_name.monitorFriendlyDeviceName),
+            device_path: wide_to_string(&target_name.monitorDevicePath),
+            edid: EdidIds {
+                manufacturer_id: target_name.edidManufactureId,
+                product_code: target_name.edidProductCodeId,
+            },
+            edid_info: display.edidInfo.clone(),
+            connector_instance: target_name.connectorInstance,
+            output_technology: path.targetInfo.outputTechnology.0.into(),
+            mode: Mode {
+                width,
+                height,
+                refresh_rate: path.targetInfo.refreshRate.into(),
+            },
+            position,
+            rotation: path.targetInfo.rotation.0.into(),
+            extras: display.optionalInfo,
+            ccd: CcdDetails {
+                path_flags: path.flags,
+                source_adapter: path.sourceInfo.adapterId.into(),
+                source_status_flags: path.sourceInfo.statusFlags,
+                // Both unions are plain u32s, whichever variant is active
+                source_mode_info_idx: unsafe { path.sourceInfo.Anonymous.modeInfoIdx },
+                target_status_flags: path.targetInfo.statusFlags,
+                target_mode_info_idx: unsafe { path.targetInfo.Anonymous.modeInfoIdx },
+                target_available: path.targetInfo.targetAvailable.as_bool(),
+                scaling: path.targetInfo.scaling.0,
+                scan_line_ordering: path.targetInfo.scanLineOrdering.0,
+                source_mode: mode_header(source_mode),
+                pixel_format,
+                target_mode: mode_header(target_mode),
+                video_signal,
+                target_name_header: target_name.header.into(),
+                target_name_flags: unsafe { target_name.flags.Anonymous.value },
+                target_name_output_technology: target_name.outputTechnology.0,
+                adapter_name_header: adapter_name.header.into(),
+            },
+        }
+    }
+}
+
+impl From<&Monitor> for Display {
+    fn from(monitor: &Monitor) -> Self {
+        let ccd = &monitor.ccd;
+
+        let path = DISPLAYCONFIG_PATH_INFO {
+            sourceInfo: DISPLAYCONFIG_PATH_SOURCE_INFO {
+                adapterId: ccd.source_adapter.into(),
+                id: monitor.source_id,
+                Anonymous: DISPLAYCONFIG_PATH_SOURCE_INFO_0 {
+                    modeInfoIdx: ccd.source_mode_info_idx,
+                },
+                statusFlags: ccd.source_status_flags,
+            },
+            targetInfo: DISPLAYCONFIG_PATH_TARGET_INFO {
+                adapterId: monitor.adapter.luid.into(),
+                id: monitor.target_id,
+                Anonymous: DISPLAYCONFIG_PATH_TARGET_INFO_0 {
+                    modeInfoIdx: ccd.target_mode_info_idx,
+                },
+                outputTechnology: DISPLAYCONFIG_VIDEO_OUTPUT_TECHNOLOGY(
+                    monitor.output_technology.into(),
+                ),
+                rotation: DISPLAYCONFIG_ROTATION(monitor.rotation.into()),
+                scaling: DISPLAYCONFIG_SCALING(ccd.scaling),
+                refreshRate: monitor.mode.refresh_rate.into(),
+                scanLineOrdering: DISPLAYCONFIG_SCANLINE_ORDERING(ccd.scan_line_ordering),
+                targetAvailable: BOOL::from(ccd.target_available),
+                statusFlags: ccd.target_status_flags,
+            },
+            flags: ccd.path_flags,
+        };
+
+        let mut source_mode = empty_mode(ccd.source_mode);
+        if source_mode.infoType == DISPLAYCONFIG_MODE_INFO_TYPE_SOURCE {
+            source_mode.Anonymous.sourceMode = DISPLAYCONFIG_SOURCE_MODE {
+                width: monitor.mode.width,
+                height: monitor.mode.height,
+                pixelFormat: DISPLAYCONFIG_PIXELFORMAT(ccd.pixel_format),
+                position: POINTL {
+                    x: monitor.position.x,
+                    y: monitor.position.y,
+                },
+            };
+        }
+
+        let mut target_mode = empty_mode(ccd.target_mode);
+        if target_mode.infoType == DISPLAYCONFIG_MODE_INFO_TYPE_TARGET {
+            target_mode.Anonymous.targetMode = DISPLAYCONFIG_TARGET_MODE {
+                targetVideoSignalInfo: ccd.video_signal.into(),
+            };
+        }
+
+        let target_name = DISPLAYCONFIG_TARGET_DEVICE_NAME {
+            header: ccd.target_name_header.into(),
+            flags: DISPLAYCONFIG_TARGET_DEVICE_NAME_FLAGS {
+                Anonymous: DISPLAYCONFIG_TARGET_DEVICE_NAME_FLAGS_0 {
+                    value: ccd.target_name_flags,
+                },
+            },
+            outputTechnology: DISPLAYCONFIG_VIDEO_OUTPUT_TECHNOLOGY(
+                ccd.target_name_output_technology,
+            ),
+            edidManufactureId: monitor.edid.manufacturer_id,
+            edidProductCodeId: monitor.edid.product_code,
+            connectorInstance: monitor.connector_instance,
+            monitorFriendlyDeviceName: string_to_wide(&monitor.name),
+            monitorDevicePath: string_to_wide(&monitor.device_path),
+        };
+
+        let adapter_name = DISPLAYCONFIG_ADAPTER_NAME {
+            header: ccd.adapter_name_header.into(),
+            adapterDevicePath: string_to_wide(&monitor.adapter.device_path),
+        };
+
+        let mut display = Display::from(
+            &path,
+            &target_mode,
+            &source_mode,
+            &target_name,
+            &adapter_name,
+            &monitor.extras,
+        );
+        display.edidInfo = monitor.edid_info.clone();
+        display
+    }
+}
+
+impl From<&DisplayLayout> for Layout {
+    fn from(layout: &DisplayLayout) -> Self {
+        Layout {
+            monitors: layout.displays.iter().map(Monitor::from).collect(),
+            global: layout.globalInfo.clone(),
+        }
+    }
+}
+
+impl From<&Layout> for DisplayLayout {
+    fn from(layout: &Layout) -> Self {
+        DisplayLayout::from(
+            layout.monitors.iter().map(Into::into).collect(),
+            layout.global.clone(),
+        )
+    }
+}
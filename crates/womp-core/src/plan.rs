@@ -0,0 +1,323 @@
+//! What applying a profile would change, worked out without changing anything.
+//!
+//! A plan lists the changes in the order applying the profile makes them: the `before`
+//! hook, the displays that are turned off and on, their modes, positions and rotations,
+//! the extras and finally the `after` hook. Settings that already have the value the
+//! profile wants aren't listed.
+
+use crate::comparison::{Hz, Setting, on_off, rotation};
+use crate::inventory::Inventory;
+use crate::model::{Monitor, Position, RefreshRate, Rotation};
+use crate::serde_types::global_config::GlobalConfig;
+use crate::serde_types::{Display, DisplayLayout, WallpaperInfo};
+use serde::Serialize;
+use std::fmt;
+
+/// Something applying a profile does.
+#[derive(Serialize, Debug, Clone, PartialEq)]
+#[serde(tag = "action", rename_all = "snake_case")]
+pub enum Change {
+    /// Runs the `run.before` or `run.after` command of the profile.
+    RunHook {
+        stage: String,
+        command: String,
+    },
+    TurnOff {
+        display: String,
+    },
+    /// Turns on a display that's off, with the mode and position in `to`.
+    TurnOn {
+        display: String,
+        to: String,
+    },
+    /// Changes a setting of a display, or a desktop-wide one if `display` is unset.
+    Set {
+        #[serde(skip_serializing_if = "Option::is_none")]
+        display: Option<String>,
+        setting: Setting,
+        /// The value now, unset if there's none, e.g. for a display that's turned on.
+        #[serde(skip_serializing_if = "Option::is_none")]
+        from: Option<String>,
+        to: String,
+    },
+}
+
+impl Change {
+    /// Position of the change in the plan.
+    fn order(&self) -> u32 {
+        match self {
+            Change::RunHook { stage, .. } if stage == "before" => 0,
+            Change::TurnOff { .. } => 1,
+            Change::TurnOn { .. } => 2,
+            Change::Set { setting, .. } => 3 + *setting as u32,
+            Change::RunHook { .. } => u32::MAX,
+        }
+    }
+
+    fn set(display: Option<&str>, setting: Setting, from: Option<String>, to: String) -> Self {
+        Change::Set {
+            display: display.map(str::to_string),
+            setting,
+            from,
+            to,
+        }
+    }
+}
+
+impl fmt::Display for Change {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Change::RunHook { stage, command } => write!(f, "Run the {stage} hook `{command}`"),
+            Change::TurnOff { display } => write!(f, "Turn off {display}"),
+            Change::TurnOn { display, to } => write!(f, "Turn on {display} at {to}"),
+            Change::Set {
+                display,
+                setting,
+                from,
+                to,
+            } => {
+                if let Some(display) = display {
+                    write!(f, "{display}: ")?;
+                }
+                write!(f, "set {setting} to {to}")?;
+                if let Some(from) = from {
+                    write!(f, ", it's {from} now")?;
+                }
+                Ok(())
+            }
+        }
+    }
+}
+
+/// What applying a profile would do, see [`crate::plan_display_layout`].
+#[derive(Serialize, Debug, Clone, PartialEq)]
+pub struct Plan {
+    pub profile: String,
+    pub changes: Vec<Change>,
+}
+
+impl Plan {
+    /// A plan with `changes` put in the order they're made.
+    pub fn new(profile: &str, mut changes: Vec<Change>) -> Self {
+        changes.sort_by_key(Change::order);
+        Plan {
+            profile: profile.to_string(),
+            changes,
+        }
+    }
+
+    /// Whether applying the profile wouldn't change anything but run its hooks.
+    pub fn is_empty(&self) -> bool {
+        self.changes
+            .iter()
+            .all(|change| matches!(change, Change::RunHook { .. }))
+    }
+}
+
+fn resolution(width: u32, height: u32) -> String {
+    format!("{width}x{height}")
+}
+
+fn position(position: Position) -> String {
+    format!("({}, {})", position.x, position.y)
+}
+
+/// How a display that's turned on will look, e.g. `3840x2160 @ 60.00 Hz, (0, 0)`.
+pub(crate) fn describe_mode(
+    (width, height): (u32, u32),
+    refresh_rate: RefreshRate,
+    at: Position,
+    rotated: Rotation,
+) -> String {
+    let mut mode = resolution(width, height);
+    // The driver picks a rate if there's none
+    if refresh_rate.as_hz().is_some() {
+        mode += &format!(" @ {refresh_rate}");
+    }
+    mode += &format!(", {}", position(at));
+    if rotated != Rotation::Identity {
+        mode += &format!(", rotated {}", rotation(rotated));
+    }
+    mode
+}
+
+/// Changes of the display `name` from `live`, `None` if it's off, to `target`.
+fn display_changes(name: &str, live: Option<&Monitor>, target: &Monitor) -> Vec<Change> {
+    let mode = &target.mode;
+    let Some(live) = live else {
+        return vec![Change::TurnOn {
+            display: name.to_string(),
+            to: describe_mode(
+                (mode.width, mode.height),
+                mode.refresh_rate,
+                target.position,
+                target.rotation,
+            ),
+        }];
+    };
+
+    let display = Some(name);
+    let mut changes = vec![];
+    if (live.mode.width, live.mode.height) != (mode.width, mode.height) {
+        changes.push(Change::set(
+            display,
+            Setting::Resolution,
+            Some(resolution(live.mode.width, live.mode.height)),
+            resolution(mode.width, mode.height),
+        ));
+    }
+    // 0/0 lets the driver pick, that's no change
+    let hz = |rate: RefreshRate| rate.as_hz().map(Hz);
+    if hz(mode.refresh_rate).is_some() && hz(live.mode.refresh_rate) != hz(mode.refresh_rate) {
+        changes.push(Change::set(
+            display,
+            Setting::RefreshRate,
+            Some(live.mode.refresh_rate.to_string()),
+            mode.refresh_rate.to_string(),
+        ));
+    }
+    if live.position != target.position {
+        changes.push(Change::set(
+            display,
+            Setting::Position,
+            Some(position(live.position)),
+            position(target.position),
+        ));
+    }
+    if live.rotation != target.rotation {
+        changes.push(Change::set(
+            display,
+            Setting::Rotation,
+            Some(rotation(live.rotation)),
+            rotation(target.rotation),
+        ));
+    }
+    changes
+}
+
+/// Adds a change of `setting` to `to` if it's not `from` already.
+fn set_if_changed<T: PartialEq>(
+    changes: &mut Vec<Change>,
+    display: Option<&str>,
+    setting: Setting,
+    (from, to): (Option<T>, T),
+    format: impl Fn(&T) -> String,
+) {
+    if from.as_ref() != Some(&to) {
+        changes.push(Change::set(
+            display,
+            setting,
+            from.as_ref().map(&format),
+            format(&to),
+        ));
+    }
+}
+
+/// The changes that turn `live` into `topology`, the complete layout that will be set if
+/// the displays change at all, and apply the extras of `extras` enabled in
+/// `extras_config`, like [`crate::backend::DisplayBackend::apply_extras`] does. Both
+/// layouts must carry the current adapter LUIDs and target ids. `live` needs every extra
+/// captured to tell which ones change.
+pub fn plan_layout(
+    live: &DisplayLayout,
+    topology: Option<&DisplayLayout>,
+    extras: &DisplayLayout,
+    extras_config: &GlobalConfig,
+    inventory: &Inventory,
+) -> Vec<Change> {
+    let live_display = |display: &Display| live.displays.iter().find(|d| d.same_target(display));
+    let mut changes = vec![];
+
+    if let Some(topology) = topology {
+        for display in &live.displays {
+            if !topology.displays.iter().any(|d| d.same_target(display)) {
+                changes.push(Change::TurnOff {
+                    display: inventory.display_name(display),
+                });
+            }
+        }
+        for display in &topology.displays {
+            changes.extend(display_changes(
+                &inventory.display_name(display),
+                live_display(display).map(Monitor::from).as_ref(),
+                &Monitor::from(display),
+            ));
+        }
+    }
+
+    for display in &extras.displays {
+        let name = inventory.display_name(display);
+        let name = Some(name.as_str());
+        let (info, live_info) = (
+            &display.optionalInfo,
+            live_display(display).map(|d| d.optionalInfo),
+        );
+        if let (true, Some(dpi_scale)) = (extras_config.save_dpi_scale, info.dpiScale) {
+            set_if_changed(
+                &mut changes,
+                name,
+                Setting::DpiScale,
+                (live_info.and_then(|i| i.dpiScale), dpi_scale),
+                |dpi| format!("{dpi}%"),
+            );
+        }
+        if let (true, Some(enabled), Some(true)) = (
+            extras_config.save_hdr_state,
+            info.hdrEnabled,
+            info.hdrSupported,
+        ) {
+            set_if_changed(
+                &mut changes,
+                name,
+                Setting::Hdr,
+                (live_info.and_then(|i| i.hdrEnabled), enabled),
+                |&e| on_off(e),
+            );
+        }
+        if let (true, Some(white_level), Some(true)) = (
+            extras_config.save_sdr_white_level,
+            info.sdrWhiteLevel,
+            info.hdrEnabled,
+        ) {
+            set_if_changed(
+                &mut changes,
+                name,
+                Setting::SdrWhiteLevel,
+                (live_info.and_then(|i| i.sdrWhiteLevel), white_level),
+                |nits| format!("{nits} nits"),
+            );
+        }
+    }
+
+    let (global, live_global) = (&extras.globalInfo, &live.globalInfo);
+    if let (true, Some(icon_size)) = (extras_config.save_icon_size, global.iconSize) {
+        set_if_changed(
+            &mut changes,
+            None,
+            Setting::IconSize,
+            (live_global.iconSize, icon_size),
+            |size| size.to_string(),
+        );
+    }
+    if let (true, Some(wallpaper)) = (extras_config.save_wallpaper_info, &global.wallpaperInfo) {
+        let key = |w: &WallpaperInfo| (w.wallpaperPath.clone(), w.wallpaperPosition.clone());
+        set_if_changed(
+            &mut changes,
+            None,
+            Setting::Wallpaper,
+            (live_global.wallpaperInfo.as_ref().map(key), key(wallpaper)),
+            |(path, position)| format!("`{path}` ({position})"),
+        );
+    }
+    if let (true, Some(audio_output)) = (extras_config.save_audio_output, &global.audioOutput) {
+        set_if_changed(
+            &mut changes,
+            None,
+            Setting::AudioOutput,
+            (live_global.audioOutput.clone(), audio_output.clone()),
+            |audio| format!("`{audio}`"),
+        );
+    }
+
+    changes
+}
@@ -0,0 +1,166 @@
+use crate::error::{Result, WompError};
+use crate::layout_spec::LayoutSpec;
+use crate::serde_types::{DisplayLayout, config::Config};
+pub mod filesystem;
+pub mod memory;
+
+pub use filesystem::FsProfileStore;
+pub use memory::MemoryProfileStore;
+
+/// A profile with everything that belongs to it. Either part may be missing, e.g. a
+/// profile whose `profile.toml` was never written.
+#[derive(Clone, Default)]
+pub struct Profile {
+    pub name: String,
+    pub config: Option<Config>,
+    pub layout: Option<DisplayLayout>,
+}
+
+/// Where profiles are persisted.
+///
+/// [`FsProfileStore`] is the `profiles/<name>/profile.toml` + `displays.json` layout the
+/// apps use, [`MemoryProfileStore`] keeps everything in memory for tests and embedding.
+/// Every method validates the profile name with
+/// [`crate::config_manager::validate_profile_name`].
+pub trait ProfileStore {
+    /// Names of all stored profiles.
+    fn list(&self) -> Result<Vec<String>>;
+
+    fn exists(&self, name: &str) -> Result<bool>;
+
+    /// Fails with [`WompError::ProfileNotFound`] if the profile doesn't exist.
+    fn load_config(&self, name: &str) -> Result<Config>;
+
+    /// Fails with [`WompError::ProfileNotFound`] if the profile doesn't exist.
+    fn load_layout(&self, name: &str) -> Result<DisplayLayout>;
+
+    /// The hand-written `layout.toml` of the profile. Fails with
+    /// [`WompError::ProfileNotFound`] if the profile doesn't exist.
+    fn load_layout_spec(&self, name: &str) -> Result<LayoutSpec>;
+
+    /// Creates the profile if it doesn't exist yet.
+    fn save_config(&mut self, name: &str, config: &Config) -> Result<()>;
+
+    /// Creates the profile if it doesn't exist yet.
+    fn save_layout(&mut self, name: &str, layout: &DisplayLayout) -> Result<()>;
+
+    /// Creates the profile if it doesn't exist yet.
+    fn save_layout_spec(&mut self, name: &str, spec: &LayoutSpec) -> Result<()>;
+
+    /// Fails with [`WompError::ProfileExists`] if `new_name` is taken.
+    fn rename(&mut self, old_name: &str, new_name: &str) -> Result<()>;
+
+    /// Copies the profile to a free `<name>_clone[_n]` name and returns that name.
+    fn clone_profile(&mut self, name: &str) -> Result<String>;
+
+    fn delete(&mut self, name: &str) -> Result<()>;
+
+    /// All profiles with their configs. Profiles with an unreadable config are still
+    /// listed, just without one.
+    fn list_with_configs(&self) -> Result<Vec<(String, Option<Config>)>> {
+        let mut profiles = Vec::new();
+        for name in self.list()? {
+            let config = match self.load_config(&name) {
+                Ok(c) => Some(c),
+                Err(e @ WompError::Parse { .. }) => {
+                    log::warn!(
+                        profile = name.as_str(),
+                        error:% = e;
+                        "Skipping unreadable profile config"
+                    );
+                    None
+                }
+                Err(_) => None,
+            };
+            profiles.push((name, config));
+        }
+        Ok(profiles)
+    }
+
+    /// Loads both parts of a profile; missing or unreadable parts are left as `None`.
+    fn load(&self, name: &str) -> Result<Profile> {
+        if !self.exists(name)? {
+            return Err(WompError::ProfileNotFound(name.to_string()));
+        }
+        Ok(Profile {
+            name: name.to_string(),
+            config: self.load_config(name).ok(),
+            layout: self.load_layout(name).ok(),
+        })
+    }
+
+    /// Writes whichever parts of the profile are present.
+    fn save(&mut self, profile: &Profile) -> Result<()> {
+        if let Some(config) = &profile.config {
+            self.save_config(&profile.name, config)?;
+        }
+        if let Some(layout) = &profile.layout {
+            self.save_layout(&profile.name, layout)?;
+        }
+        Ok(())
+    }
+}
+
+/// First of `<name>_clone`, `<name>_clone_2`, ... that isn't taken yet.
+pub(crate) fn clone_name(name: &str, exists: impl Fn(&str) -> Result<bool>) -> Result<String> {
+    let mut suffix = 1;
+    loop {
+        let suffix_str = if suffix == 1 {
+            "".to_string()
+        } else {
+            format!("_{}", suffix)
+        };
+        let clone_name = format!("{}_clone{}", name, suffix_str);
+        if !exists(&clone_name)? {
+            return Ok(clone_name);
+        }
+        suffix += 1;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing::{layout, monitor};
+
+    /// Runs the same steps against `store`, which must start out empty, so every store
+    /// is held to the same behaviour.
+    fn behaves_like_a_store(store: &mut impl ProfileStore) {
+        assert!(store.list().unwrap().is_empty());
+        assert!(!store.exists("desk").unwrap());
+
+        store.save_config("tv", &Config::default()).unwrap();
+        store
+            .save_layout("desk", &layout(&[monitor(1, 0, 60)]))
+            .unwrap();
+        assert!(store.exists("desk").unwrap());
+        assert_eq!(store.list().unwrap(), ["desk", "tv"]);
+        assert_eq!(store.load_layout("desk").unwrap().displays.len(), 1);
+
+        store.delete("desk").unwrap();
+        assert!(!store.exists("desk").unwrap());
+        assert_eq!(store.list().unwrap(), ["tv"]);
+
+        for result in [
+            store.delete("desk").map(|_| ()),
+            store.load_config("desk").map(|_| ()),
+            store.load_layout("desk").map(|_| ()),
+            store.load("desk").map(|_| ()),
+        ] {
+            assert!(matches!(result, Err(WompError::ProfileNotFound(name)) if name == "desk"));
+        }
+    }
+
+    #[test]
+    fn memory_store() {
+        behaves_like_a_store(&mut MemoryProfileStore::new());
+    }
+
+    #[test]
+    fn filesystem_store() {
+        let dir = std::env::temp_dir().join(format!("womp-store-{}", std::process::id()));
+        let _ = std::fs::remove_dir_all(&dir);
+        behaves_like_a_store(&mut FsProfileStore::new(&dir));
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+}
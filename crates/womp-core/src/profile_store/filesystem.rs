@@ -0,0 +1,177 @@
+use super::{ProfileStore, clone_name};
+use crate::config_manager::{get_profiles_dir, validate_profile_name};
+use crate::error::{Result, WompError};
+use crate::layout_spec::LayoutSpec;
+use crate::migration::{Migration, migrate_config_file, migrate_layout_file};
+use crate::serde_types::{DisplayLayout, config::Config};
+use std::{
+    fs,
+    path::{Path, PathBuf},
+};
+
+pub const CONFIG_FILE_NAME: &str = "profile.toml";
+pub const LAYOUT_FILE_NAME: &str = "displays.json";
+pub const LAYOUT_SPEC_FILE_NAME: &str = "layout.toml";
+
+/// Stores every profile as a folder holding `profile.toml` and `displays.json` and/or
+/// `layout.toml`.
+pub struct FsProfileStore {
+    profiles_dir: PathBuf,
+}
+
+impl FsProfileStore {
+    pub fn new(profiles_dir: impl Into<PathBuf>) -> Self {
+        FsProfileStore {
+            profiles_dir: profiles_dir.into(),
+        }
+    }
+
+    /// The store in the profiles directory of the resolved config root.
+    pub fn open_default() -> Result<Self> {
+        Ok(FsProfileStore::new(get_profiles_dir()?))
+    }
+
+    pub fn profiles_dir(&self) -> &Path {
+        &self.profiles_dir
+    }
+
+    pub fn profile_dir(&self, name: &str) -> Result<PathBuf> {
+        validate_profile_name(name)?;
+        Ok(self.profiles_dir.join(name))
+    }
+
+    /// Like [`FsProfileStore::profile_dir`], but fails with [`WompError::ProfileNotFound`]
+    /// if the profile doesn't exist yet.
+    pub fn existing_profile_dir(&self, name: &str) -> Result<PathBuf> {
+        let profile_dir = self.profile_dir(name)?;
+        if !profile_dir.is_dir() {
+            return Err(WompError::ProfileNotFound(name.to_string()));
+        }
+        Ok(profile_dir)
+    }
+
+    /// Upgrades both files of the profile to the current format, returning what was changed.
+    pub fn migrate_profile(&self, name: &str) -> Result<Vec<Migration>> {
+        let profile_dir = self.existing_profile_dir(name)?;
+        let migrations = [
+            migrate_config_file(&profile_dir.join(CONFIG_FILE_NAME))?,
+            migrate_layout_file(&profile_dir.join(LAYOUT_FILE_NAME))?,
+        ];
+        Ok(migrations.into_iter().flatten().collect())
+    }
+
+    fn create_profile_dir(&self, name: &str) -> Result<PathBuf> {
+        let profile_dir = self.profile_dir(name)?;
+        fs::create_dir_all(&profile_dir).map_err(|e| WompError::io(&profile_dir, e))?;
+        Ok(profile_dir)
+    }
+}
+
+fn copy_dir_recursive(from: &Path, to: &Path) -> Result<()> {
+    fs::create_dir_all(to).map_err(|e| WompError::io(to, e))?;
+    let entries = fs::read_dir(from).map_err(|e| WompError::io(from, e))?;
+    for entry in entries {
+        let entry = entry.map_err(|e| WompError::io(from, e))?;
+        let source = entry.path();
+        let destination = to.join(entry.file_name());
+        if source.is_dir() {
+            copy_dir_recursive(&source, &destination)?;
+        } else {
+            fs::copy(&source, &destination).map_err(|e| WompError::io(&source, e))?;
+        }
+    }
+    Ok(())
+}
+
+impl ProfileStore for FsProfileStore {
+    fn list(&self) -> Result<Vec<String>> {
+        fs::create_dir_all(&self.profiles_dir).map_err(|e| WompError::io(&self.profiles_dir, e))?;
+        let mut profiles = Vec::new();
+        let entries =
+            fs::read_dir(&self.profiles_dir).map_err(|e| WompError::io(&self.profiles_dir, e))?;
+        for entry in entries {
+            let path = entry
+                .map_err(|e| WompError::io(&self.profiles_dir, e))?
+                .path();
+            if !path.is_dir() {
+                continue;
+            }
+            if let Some(profile_name) = path.file_name().map(|n| n.to_string_lossy().to_string()) {
+                profiles.push(profile_name);
+            }
+        }
+        // `read_dir` order depends on the file system
+        profiles.sort();
+        Ok(profiles)
+    }
+
+    fn exists(&self, name: &str) -> Result<bool> {
+        Ok(self.profile_dir(name)?.is_dir())
+    }
+
+    fn load_config(&self, name: &str) -> Result<Config> {
+        let config_file = self.existing_profile_dir(name)?.join(CONFIG_FILE_NAME);
+        migrate_config_file(&config_file)?;
+        let contents =
+            fs::read_to_string(&config_file).map_err(|e| WompError::io(&config_file, e))?;
+        toml::from_str(&contents).map_err(|e| WompError::parse(&config_file, e))
+    }
+
+    fn load_layout(&self, name: &str) -> Result<DisplayLayout> {
+        let layout_file = self.existing_profile_dir(name)?.join(LAYOUT_FILE_NAME);
+        migrate_layout_file(&layout_file)?;
+        let contents =
+            fs::read_to_string(&layout_file).map_err(|e| WompError::io(&layout_file, e))?;
+        serde_json::from_str(&contents).map_err(|e| WompError::parse(&layout_file, e))
+    }
+
+    fn load_layout_spec(&self, name: &str) -> Result<LayoutSpec> {
+        let spec_file = self.existing_profile_dir(name)?.join(LAYOUT_SPEC_FILE_NAME);
+        let contents = fs::read_to_string(&spec_file).map_err(|e| WompError::io(&spec_file, e))?;
+        LayoutSpec::parse(&spec_file, &contents)
+    }
+
+    fn save_config(&mut self, name: &str, config: &Config) -> Result<()> {
+        let config_str = toml::to_string(config)
+            .map_err(|e| WompError::serialize(format!("config of `{name}`"), e))?;
+        let config_file = self.create_profile_dir(name)?.join(CONFIG_FILE_NAME);
+        fs::write(&config_file, config_str).map_err(|e| WompError::io(&config_file, e))
+    }
+
+    fn save_layout(&mut self, name: &str, layout: &DisplayLayout) -> Result<()> {
+        let serialized = serde_json::to_string_pretty(layout)
+            .map_err(|e| WompError::serialize("display layout", e))?;
+        let layout_file = self.create_profile_dir(name)?.join(LAYOUT_FILE_NAME);
+        fs::write(&layout_file, serialized).map_err(|e| WompError::io(&layout_file, e))
+    }
+
+    fn save_layout_spec(&mut self, name: &str, spec: &LayoutSpec) -> Result<()> {
+        let serialized = toml::to_string(spec)
+            .map_err(|e| WompError::serialize(format!("layout of `{name}`"), e))?;
+        let spec_file = self.create_profile_dir(name)?.join(LAYOUT_SPEC_FILE_NAME);
+        fs::write(&spec_file, serialized).map_err(|e| WompError::io(&spec_file, e))
+    }
+
+    fn rename(&mut self, old_name: &str, new_name: &str) -> Result<()> {
+        let old_path = self.existing_profile_dir(old_name)?;
+        let new_path = self.profile_dir(new_name)?;
+        if new_path.exists() {
+            return Err(WompError::ProfileExists(new_name.to_string()));
+        }
+        fs::rename(&old_path, new_path).map_err(|e| WompError::io(&old_path, e))
+    }
+
+    fn clone_profile(&mut self, name: &str) -> Result<String> {
+        let profile_dir = self.existing_profile_dir(name)?;
+        let clone_name = clone_name(name, |candidate| {
+            Ok(self.profiles_dir.join(candidate).exists())
+        })?;
+        copy_dir_recursive(&profile_dir, &self.profiles_dir.join(&clone_name))?;
+        Ok(clone_name)
+    }
+
+    fn delete(&mut self, name: &str) -> Result<()> {
+        let profile_path = self.existing_profile_dir(name)?;
+        fs::remove_dir_all(&profile_path).map_err(|e| WompError::io(&profile_path, e))
+    }
+}
@@ -0,0 +1,133 @@
+use super::filesystem::{CONFIG_FILE_NAME, LAYOUT_FILE_NAME, LAYOUT_SPEC_FILE_NAME};
+use super::{Profile, ProfileStore, clone_name};
+use crate::config_manager::validate_profile_name;
+use crate::error::{Result, WompError};
+use crate::layout_spec::LayoutSpec;
+use crate::serde_types::{DisplayLayout, config::Config};
+use std::{collections::BTreeMap, path::Path};
+
+#[derive(Clone, Default)]
+struct StoredProfile {
+    config: Option<Config>,
+    layout: Option<DisplayLayout>,
+    layout_spec: Option<LayoutSpec>,
+}
+
+/// Keeps profiles in memory, sorted by name. Nothing is ever written to disk.
+#[derive(Clone, Default)]
+pub struct MemoryProfileStore {
+    profiles: BTreeMap<String, StoredProfile>,
+}
+
+impl MemoryProfileStore {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn with_profiles(profiles: impl IntoIterator<Item = Profile>) -> Self {
+        let mut store = Self::new();
+        for profile in profiles {
+            store.profiles.insert(
+                profile.name,
+                StoredProfile {
+                    config: profile.config,
+                    layout: profile.layout,
+                    layout_spec: None,
+                },
+            );
+        }
+        store
+    }
+
+    fn get(&self, name: &str) -> Result<&StoredProfile> {
+        validate_profile_name(name)?;
+        self.profiles
+            .get(name)
+            .ok_or_else(|| WompError::ProfileNotFound(name.to_string()))
+    }
+
+    fn get_or_create(&mut self, name: &str) -> Result<&mut StoredProfile> {
+        validate_profile_name(name)?;
+        Ok(self.profiles.entry(name.to_string()).or_default())
+    }
+}
+
+// Same error the filesystem store returns for a missing file
+fn missing(name: &str, file_name: &str) -> WompError {
+    WompError::io(
+        Path::new(name).join(file_name),
+        std::io::Error::from(std::io::ErrorKind::NotFound),
+    )
+}
+
+impl ProfileStore for MemoryProfileStore {
+    fn list(&self) -> Result<Vec<String>> {
+        Ok(self.profiles.keys().cloned().collect())
+    }
+
+    fn exists(&self, name: &str) -> Result<bool> {
+        validate_profile_name(name)?;
+        Ok(self.profiles.contains_key(name))
+    }
+
+    fn load_config(&self, name: &str) -> Result<Config> {
+        self.get(name)?
+            .config
+            .clone()
+            .ok_or_else(|| missing(name, CONFIG_FILE_NAME))
+    }
+
+    fn load_layout(&self, name: &str) -> Result<DisplayLayout> {
+        self.get(name)?
+            .layout
+            .clone()
+            .ok_or_else(|| missing(name, LAYOUT_FILE_NAME))
+    }
+
+    fn load_layout_spec(&self, name: &str) -> Result<LayoutSpec> {
+        self.get(name)?
+            .layout_spec
+            .clone()
+            .ok_or_else(|| missing(name, LAYOUT_SPEC_FILE_NAME))
+    }
+
+    fn save_config(&mut self, name: &str, config: &Config) -> Result<()> {
+        self.get_or_create(name)?.config = Some(config.clone());
+        Ok(())
+    }
+
+    fn save_layout(&mut self, name: &str, layout: &DisplayLayout) -> Result<()> {
+        self.get_or_create(name)?.layout = Some(layout.clone());
+        Ok(())
+    }
+
+    fn save_layout_spec(&mut self, name: &str, spec: &LayoutSpec) -> Result<()> {
+        self.get_or_create(name)?.layout_spec = Some(spec.clone());
+        Ok(())
+    }
+
+    fn rename(&mut self, old_name: &str, new_name: &str) -> Result<()> {
+        self.get(old_name)?;
+        validate_profile_name(new_name)?;
+        if self.profiles.contains_key(new_name) {
+            return Err(WompError::ProfileExists(new_name.to_string()));
+        }
+        if let Some(profile) = self.profiles.remove(old_name) {
+            self.profiles.insert(new_name.to_string(), profile);
+        }
+        Ok(())
+    }
+
+    fn clone_profile(&mut self, name: &str) -> Result<String> {
+        let profile = self.get(name)?.clone();
+        let clone_name = clone_name(name, |candidate| Ok(self.profiles.contains_key(candidate)))?;
+        self.profiles.insert(clone_name.clone(), profile);
+        Ok(clone_name)
+    }
+
+    fn delete(&mut self, name: &str) -> Result<()> {
+        self.get(name)?;
+        self.profiles.remove(name);
+        Ok(())
+    }
+}
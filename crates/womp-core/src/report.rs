@@ -0,0 +1,259 @@
+//! What applying a profile did, step by step.
+//!
+//! [`crate::apply_display_layout`] returns an [`ApplyReport`] listing every setting it
+//! made or left alone, how long each took, the `SetDisplayConfig` flags the displays were
+//! finally set with, whether they ended up matching the profile and what the `before` and
+//! `after` hooks printed.
+
+use crate::backend::ExtraStep;
+use crate::comparison::Setting;
+use crate::error::{AfterFailure, Result, WompError};
+use crate::serde_types::global_config::GlobalConfig;
+use crate::serde_types::{DisplayLayout, WallpaperInfo};
+use crate::verify::Verification;
+use crate::win32_types::*;
+use log::warn;
+use serde::{Serialize, Serializer};
+use std::fmt;
+use std::time::Duration;
+
+/// What came of a step of applying a profile.
+#[derive(Serialize, Debug)]
+#[serde(tag = "status", rename_all = "snake_case")]
+pub enum StepStatus {
+    Applied,
+    /// The setting already had the profile's value before applying.
+    Unchanged,
+    /// The extra is turned off in the settings or left out by the profile's scope.
+    Disabled,
+    Failed {
+        error: WompError,
+    },
+}
+
+/// A step of applying a profile.
+#[derive(Serialize, Debug)]
+pub struct StepReport {
+    /// What the step set, e.g. "the DPI scale of `DELL U2720Q`".
+    pub step: String,
+    #[serde(flatten)]
+    pub status: StepStatus,
+    #[serde(rename = "duration_ms", serialize_with = "millis")]
+    pub duration: Duration,
+}
+
+impl fmt::Display for StepReport {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let duration = self.duration.as_millis();
+        match &self.status {
+            StepStatus::Applied => write!(f, "Set {} ({duration} ms)", self.step),
+            StepStatus::Unchanged => {
+                write!(
+                    f,
+                    "Set {}, it already had that value ({duration} ms)",
+                    self.step
+                )
+            }
+            StepStatus::Disabled => write!(f, "Left {} alone, it's turned off", self.step),
+            StepStatus::Failed { error } => {
+                write!(f, "Failed to set {}: {error} ({duration} ms)", self.step)
+            }
+        }
+    }
+}
+
+/// A `before` or `after` hook that ran.
+#[derive(Serialize, Debug)]
+pub struct HookReport {
+    pub stage: String,
+    pub command: String,
+    /// Unset if the command didn't exit on its own, e.g. it was killed.
+    pub exit_code: Option<i32>,
+    pub stdout: String,
+    pub stderr: String,
+    /// Why the command couldn't be started, unset if it ran.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub error: Option<WompError>,
+    #[serde(rename = "duration_ms", serialize_with = "millis")]
+    pub duration: Duration,
+}
+
+impl HookReport {
+    pub fn succeeded(&self) -> bool {
+        self.exit_code == Some(0)
+    }
+}
+
+impl fmt::Display for HookReport {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if let Some(error) = &self.error {
+            return write!(f, "{error}");
+        }
+        write!(f, "Ran the {} hook `{}`, ", self.stage, self.command)?;
+        match self.exit_code {
+            Some(code) => write!(f, "exit code {code}")?,
+            None => write!(f, "no exit code")?,
+        }
+        write!(f, " ({} ms)", self.duration.as_millis())
+    }
+}
+
+/// What applying a profile did.
+#[derive(Serialize, Debug)]
+pub struct ApplyReport {
+    pub profile: String,
+    /// The hooks that ran, in the order they ran.
+    pub hooks: Vec<HookReport>,
+    /// The steps made, in the order they were made, followed by the disabled ones.
+    pub steps: Vec<StepReport>,
+    /// The `SetDisplayConfig` flags the displays were set with, unset if they weren't.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub display_config_flags: Option<Vec<&'static str>>,
+    /// How the displays looked once they settled, unset if they weren't set or couldn't
+    /// be checked.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub verification: Option<Verification>,
+    /// What was done after a step failed, unset if none did.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub after: Option<AfterFailure>,
+    #[serde(rename = "duration_ms", serialize_with = "millis")]
+    pub duration: Duration,
+}
+
+impl ApplyReport {
+    pub(crate) fn new(profile: &str) -> Self {
+        ApplyReport {
+            profile: profile.to_string(),
+            hooks: vec![],
+            steps: vec![],
+            display_config_flags: None,
+            verification: None,
+            after: None,
+            duration: Duration::ZERO,
+        }
+    }
+
+    /// Adds a step that was made, `unchanged` if the setting already had its value.
+    pub(crate) fn record(
+        &mut self,
+        step: String,
+        result: Result<()>,
+        unchanged: bool,
+        duration: Duration,
+    ) {
+        let status = match result {
+            Ok(()) if unchanged => StepStatus::Unchanged,
+            Ok(()) => StepStatus::Applied,
+            Err(error) => {
+                warn!(step = step.as_str(), error:% = error; "Step of applying the profile failed");
+                StepStatus::Failed { error }
+            }
+        };
+        self.steps.push(StepReport {
+            step,
+            status,
+            duration,
+        });
+    }
+
+    /// Adds the extras that `extras_config` leaves alone.
+    pub(crate) fn record_disabled(&mut self, extras_config: &GlobalConfig) {
+        let extras = [
+            (Setting::DpiScale, extras_config.save_dpi_scale),
+            (Setting::Hdr, extras_config.save_hdr_state),
+            (Setting::SdrWhiteLevel, extras_config.save_sdr_white_level),
+            (Setting::IconSize, extras_config.save_icon_size),
+            (Setting::Wallpaper, extras_config.save_wallpaper_info),
+            (Setting::AudioOutput, extras_config.save_audio_output),
+        ];
+        for (setting, _) in extras.into_iter().filter(|(_, enabled)| !enabled) {
+            self.steps.push(StepReport {
+                step: describe_setting(setting, None),
+                status: StepStatus::Disabled,
+                duration: Duration::ZERO,
+            });
+        }
+    }
+
+    /// The steps that failed.
+    pub fn failures(&self) -> impl Iterator<Item = (&str, &WompError)> {
+        self.steps.iter().filter_map(|step| match &step.status {
+            StepStatus::Failed { error } => Some((step.step.as_str(), error)),
+            _ => None,
+        })
+    }
+
+    pub fn failed(&self) -> bool {
+        self.failures().next().is_some()
+    }
+}
+
+/// What setting `setting` is called in reports and errors, e.g. "the DPI scale of `DELL`".
+pub(crate) fn describe_setting(setting: Setting, display: Option<&str>) -> String {
+    let setting = match setting {
+        Setting::Hdr => setting.to_string(),
+        _ => format!("the {setting}"),
+    };
+    match display {
+        Some(display) => format!("{setting} of {display}"),
+        None => setting,
+    }
+}
+
+/// Whether `previous` already had the value `step` set from `layout`.
+pub(crate) fn unchanged(
+    previous: &DisplayLayout,
+    layout: &DisplayLayout,
+    step: &ExtraStep,
+) -> bool {
+    let (global, previous_global) = (&layout.globalInfo, &previous.globalInfo);
+    let Some(display) = step.display else {
+        return match step.setting {
+            Setting::IconSize => global.iconSize == previous_global.iconSize,
+            Setting::Wallpaper => {
+                let key =
+                    |w: &WallpaperInfo| (w.wallpaperPath.clone(), w.wallpaperPosition.clone());
+                global.wallpaperInfo.as_ref().map(key)
+                    == previous_global.wallpaperInfo.as_ref().map(key)
+            }
+            Setting::AudioOutput => global.audioOutput == previous_global.audioOutput,
+            _ => false,
+        };
+    };
+    let Some(live) = previous.displays.iter().find(|d| d.same_target(display)) else {
+        return false;
+    };
+    let (info, live_info) = (&display.optionalInfo, &live.optionalInfo);
+    match step.setting {
+        Setting::DpiScale => info.dpiScale == live_info.dpiScale,
+        Setting::Hdr => info.hdrEnabled == live_info.hdrEnabled,
+        Setting::SdrWhiteLevel => info.sdrWhiteLevel == live_info.sdrWhiteLevel,
+        _ => false,
+    }
+}
+
+/// The names of the flags set in `flags`, e.g. `["SDC_APPLY", "SDC_ALLOW_CHANGES"]`.
+pub fn display_config_flag_names(flags: SET_DISPLAY_CONFIG_FLAGS) -> Vec<&'static str> {
+    [
+        (SDC_APPLY, "SDC_APPLY"),
+        (SDC_VALIDATE, "SDC_VALIDATE"),
+        (
+            SDC_USE_SUPPLIED_DISPLAY_CONFIG,
+            "SDC_USE_SUPPLIED_DISPLAY_CONFIG",
+        ),
+        (SDC_SAVE_TO_DATABASE, "SDC_SAVE_TO_DATABASE"),
+        (SDC_NO_OPTIMIZATION, "SDC_NO_OPTIMIZATION"),
+        (SDC_ALLOW_CHANGES, "SDC_ALLOW_CHANGES"),
+    ]
+    .into_iter()
+    .filter(|(flag, _)| flags.0 & flag.0 != 0)
+    .map(|(_, name)| name)
+    .collect()
+}
+
+pub(crate) fn millis<S: Serializer>(
+    duration: &Duration,
+    serializer: S,
+) -> std::result::Result<S::Ok, S::Error> {
+    serializer.serialize_u64(duration.as_millis() as u64)
+}
@@ -0,0 +1,51 @@
+use crate::error::{Result, WompError};
+use crate::inventory::Inventory;
+use crate::layout_spec::LayoutSpec;
+use crate::serde_types::{DisplayLayout, config::Config, global_config::GlobalConfig};
+use schemars::{Schema, schema_for};
+
+/// The files WOMP reads that have a schema.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SchemaKind {
+    /// `profiles/<name>/displays.json`
+    DisplayLayout,
+    /// `profiles/<name>/layout.toml`
+    LayoutSpec,
+    /// `profiles/<name>/profile.toml`
+    Config,
+    /// `settings.toml`
+    GlobalConfig,
+    /// `monitors.toml`
+    Inventory,
+}
+
+impl SchemaKind {
+    /// Name of the file the schema describes.
+    pub fn file_name(self) -> &'static str {
+        match self {
+            SchemaKind::DisplayLayout => "displays.json",
+            SchemaKind::LayoutSpec => "layout.toml",
+            SchemaKind::Config => "profile.toml",
+            SchemaKind::GlobalConfig => "settings.toml",
+            SchemaKind::Inventory => "monitors.toml",
+        }
+    }
+}
+
+pub fn schema(kind: SchemaKind) -> Schema {
+    let mut schema = match kind {
+        SchemaKind::DisplayLayout => schema_for!(DisplayLayout),
+        SchemaKind::LayoutSpec => schema_for!(LayoutSpec),
+        SchemaKind::Config => schema_for!(Config),
+        SchemaKind::GlobalConfig => schema_for!(GlobalConfig),
+        SchemaKind::Inventory => schema_for!(Inventory),
+    };
+    schema.insert("title".to_string(), kind.file_name().into());
+    schema
+}
+
+/// The schema as pretty-printed JSON, ready to be written to a `.schema.json` file.
+pub fn schema_json(kind: SchemaKind) -> Result<String> {
+    serde_json::to_string_pretty(&schema(kind))
+        .map_err(|e| WompError::serialize(format!("schema of {}", kind.file_name()), e))
+}
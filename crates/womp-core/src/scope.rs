@@ -0,0 +1,162 @@
+//! Partial profiles, see [`Scope`].
+//!
+//! A profile whose `profile.toml` has a `[scope]` only stores the monitors and extras it
+//! was told to, and applying it merges those onto the live layout instead of replacing it:
+//!
+//! ```toml
+//! [scope]
+//! displays = ["LG TV SSCR2"]
+//! extras = ["audio_output"]
+//! ```
+
+use crate::backend::renumber_mode_indices;
+use crate::error::{Result, WompError};
+use crate::identity::source_of;
+use crate::model::wide_to_string;
+use crate::serde_types::config::Scope;
+use crate::serde_types::{Display, DisplayLayout};
+use crate::win32_types::LUID;
+
+/// Whether `selector` is the friendly name or device path of the display.
+fn matches(display: &Display, selector: &str) -> bool {
+    let target = &display.additionalInfo.target;
+    [
+        wide_to_string(&target.monitorFriendlyDeviceName),
+        wide_to_string(&target.monitorDevicePath),
+    ]
+    .iter()
+    .any(|id| !id.is_empty() && id.eq_ignore_ascii_case(selector.trim()))
+}
+
+/// Drops the displays of a captured layout that aren't in `scope`. Fails if a display the
+/// scope names isn't part of the layout, as saving would silently lose it otherwise.
+pub fn select_displays(layout: &mut DisplayLayout, scope: &Scope) -> Result<()> {
+    let Some(selectors) = &scope.displays else {
+        return Ok(());
+    };
+    if let Some(missing) = selectors
+        .iter()
+        .find(|selector| !layout.displays.iter().any(|d| matches(d, selector)))
+    {
+        return Err(WompError::Validation(format!(
+            "No active display is called `{missing}`"
+        )));
+    }
+    layout
+        .displays
+        .retain(|d| selectors.iter().any(|selector| matches(d, selector)));
+    renumber_mode_indices(layout);
+    Ok(())
+}
+
+/// `live` with the displays of `partial` put in: displays both have are replaced by the
+/// ones in `partial`, displays only `partial` has are turned on, all others stay as they
+/// are. `partial` must already carry the current adapter LUIDs.
+pub fn merge_layouts(live: &DisplayLayout, partial: &DisplayLayout) -> DisplayLayout {
+    let mut merged = live.clone();
+    let mut from_partial = vec![];
+    for display in &partial.displays {
+        match merged.displays.iter().position(|d| d.same_target(display)) {
+            Some(i) => {
+                merged.displays[i] = display.clone();
+                from_partial.push(i);
+            }
+            None => {
+                merged.displays.push(display.clone());
+                from_partial.push(merged.displays.len() - 1);
+            }
+        }
+    }
+
+    // A source shared with a display we keep would turn the two into clones, move the
+    // display to a source nobody uses instead
+    let kept: Vec<(LUID, u32)> = (0..merged.displays.len())
+        .filter(|i| !from_partial.contains(i))
+        .map(|i| source_of(&merged.displays[i]))
+        .collect();
+    for &i in &from_partial {
+        let (adapter_id, source_id) = source_of(&merged.displays[i]);
+        if !kept.contains(&(adapter_id, source_id)) {
+            continue;
+        }
+        let used: Vec<(LUID, u32)> = merged.displays.iter().map(source_of).collect();
+        let free = (0..)
+            .find(|id| !used.contains(&(adapter_id, *id)))
+            .unwrap_or(source_id);
+        let display = &mut merged.displays[i];
+        display.pathInfo.sourceInfo.id = free;
+        display.modeInfo.sourceModeInfo.id = free;
+    }
+
+    renumber_mode_indices(&mut merged);
+    merged
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing::{layout, monitor};
+    use crate::validation::{validate_layout, validate_partial_layout};
+
+    fn scope(displays: &[&str]) -> Scope {
+        Scope {
+            displays: Some(displays.iter().map(|d| d.to_string()).collect()),
+            extras: None,
+        }
+    }
+
+    fn sources(layout: &DisplayLayout) -> Vec<u32> {
+        layout
+            .displays
+            .iter()
+            .map(|d| d.pathInfo.sourceInfo.id)
+            .collect()
+    }
+
+    #[test]
+    fn only_scoped_displays_are_kept() {
+        let mut captured = layout(&[monitor(1, 0, 60), monitor(2, 1920, 60)]);
+        select_displays(&mut captured, &scope(&["monitor 2"])).unwrap();
+        assert_eq!(captured.displays.len(), 1);
+        assert_eq!(captured.displays[0].describe(), "`Monitor 2`");
+        assert!(!validate_partial_layout(&captured).has_errors());
+    }
+
+    #[test]
+    fn scoped_display_that_isnt_connected_fails() {
+        let mut captured = layout(&[monitor(1, 0, 60)]);
+        let Err(WompError::Validation(message)) =
+            select_displays(&mut captured, &scope(&["Monitor 1", "LG TV SSCR2"]))
+        else {
+            panic!("saved a scope with a display that isn't connected");
+        };
+        assert!(message.contains("LG TV SSCR2"), "{message}");
+        assert_eq!(captured.displays.len(), 1);
+    }
+
+    #[test]
+    fn scoped_display_replaces_the_live_one() {
+        let live = layout(&[monitor(1, 0, 60), monitor(2, 1920, 60)]);
+        let partial = layout(&[monitor(2, 1920, 144)]);
+        let merged = merge_layouts(&live, &partial);
+        assert_eq!(merged.displays.len(), 2);
+        let rate = merged.displays[1].pathInfo.targetInfo.refreshRate;
+        assert_eq!((rate.Numerator, rate.Denominator), (144, 1));
+        assert_eq!(sources(&merged), [1, 2]);
+    }
+
+    #[test]
+    fn scoped_display_moves_off_a_source_the_live_layout_uses() {
+        let live = layout(&[monitor(1, 0, 60), monitor(2, 1920, 60)]);
+        // Saved while it was the only display on source 1
+        let mut tv = monitor(3, 3840, 60);
+        tv.source_id = 1;
+        tv.ccd.source_mode.id = 1;
+        let merged = merge_layouts(&live, &layout(&[tv]));
+
+        assert_eq!(sources(&merged), [1, 2, 0]);
+        let tv = &merged.displays[2];
+        assert_eq!(tv.modeInfo.sourceModeInfo.id, 0);
+        assert!(!validate_layout(&merged).has_errors());
+    }
+}
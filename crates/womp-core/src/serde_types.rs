@@ -1,10 +1,14 @@
 #![allow(non_snake_case)]
 
-use serde::{Deserialize, Serialize};
-use windows::Win32::Devices::Display::{
+use crate::edid::EdidInfo;
+use crate::migration::DISPLAY_LAYOUT_VERSION;
+use crate::model::wide_to_string;
+use crate::win32_types::{
     DISPLAYCONFIG_ADAPTER_NAME, DISPLAYCONFIG_MODE_INFO, DISPLAYCONFIG_PATH_INFO,
     DISPLAYCONFIG_TARGET_DEVICE_NAME,
 };
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
 pub mod config;
 pub mod optional_info;
 pub mod global_config;
@@ -20,14 +24,18 @@ pub mod win32_region;
 pub mod win32_video_signal_info_union;
 pub mod win32_widestring;
 
-#[derive(Serialize, Deserialize)]
+#[derive(Serialize, Deserialize, JsonSchema, Clone)]
 pub struct Display {
     #[serde(with = "win32_path_info::PathInfoDef")]
     pub pathInfo: DISPLAYCONFIG_PATH_INFO,
     pub modeInfo: win32_mode_info::ModeInfo,
     pub additionalInfo: win32_additional_info::AdditionalInfo,
-    #[serde(skip_serializing_if = "optional_info::OptionalInfo::is_empty")]
+    #[serde(default, skip_serializing_if = "optional_info::OptionalInfo::is_empty")]
     pub optionalInfo: optional_info::OptionalInfo,
+    /// Decoded from the EDID ids and, if the backend can read it, the raw EDID. Not used
+    /// when applying.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub edidInfo: Option<EdidInfo>,
 }
 
 impl Display {
@@ -50,9 +58,26 @@ impl Display {
                 adapter: *adapter_name,
             },
             optionalInfo: *optional_info,
+            edidInfo: None,
         }
     }
 
+    /// The monitor's friendly name in backticks, its target id if it has none.
+    pub fn describe(&self) -> String {
+        let name = wide_to_string(&self.additionalInfo.target.monitorFriendlyDeviceName);
+        if name.is_empty() {
+            format!("target {}", self.pathInfo.targetInfo.id)
+        } else {
+            format!("`{name}`")
+        }
+    }
+
+    /// Whether both displays are on the same adapter and target.
+    pub fn same_target(&self, other: &Display) -> bool {
+        self.pathInfo.targetInfo.adapterId == other.pathInfo.targetInfo.adapterId
+            && self.pathInfo.targetInfo.id == other.pathInfo.targetInfo.id
+    }
+
     pub fn to_windows_types(
         &self,
     ) -> (
@@ -72,13 +97,13 @@ impl Display {
     }
 }
 
-#[derive(Serialize, Deserialize)]
+#[derive(Serialize, Deserialize, JsonSchema, Clone)]
 pub struct WallpaperInfo {
     pub wallpaperPath: String,
     pub wallpaperPosition: String,
 }
 
-#[derive(Serialize, Deserialize)]
+#[derive(Serialize, Deserialize, JsonSchema, Clone)]
 pub struct GlobalInfo {
     pub iconSize: Option<i32>,
     pub wallpaperInfo: Option<WallpaperInfo>,
@@ -91,14 +116,16 @@ impl GlobalInfo {
     }
 }
 
-#[derive(Serialize, Deserialize)]
+#[derive(Serialize, Deserialize, JsonSchema, Clone)]
 pub struct DisplayLayout {
+    /// File format version, older files are upgraded when loaded
+    pub version: u32,
     pub globalInfo: GlobalInfo,
     pub displays: Vec<Display>,
 }
 
 impl DisplayLayout {
     pub fn from(displays: Vec<Display>, globalInfo: GlobalInfo) -> Self {
-        DisplayLayout { globalInfo, displays }
+        DisplayLayout { version: DISPLAY_LAYOUT_VERSION, globalInfo, displays }
     }
 }
\ No newline at end of file
@@ -1,30 +1,84 @@
+use crate::migration::CONFIG_VERSION;
+use crate::serde_types::global_config::GlobalConfig;
+use schemars::JsonSchema;
 use serde::{Deserialize, Serialize};
 
-#[derive(Serialize, Deserialize, Debug, Default, Clone)]
+#[derive(Serialize, Deserialize, JsonSchema, Debug, Clone)]
 pub struct Config {
+    /// File format version, older files are upgraded when loaded
+    #[serde(default = "current_version")]
+    pub version: u32,
+    /// Display name, the folder name is used if empty
     #[serde(default, skip_serializing_if = "String::is_empty")]
     pub name: String,
     #[serde(default, skip_serializing_if = "String::is_empty")]
     pub description: String,
+    /// Profile this one is based on. Its layout, extras, run commands, icon, scope and
+    /// `on_failure` are used for everything this profile doesn't set itself
+    #[serde(default, skip_serializing_if = "String::is_empty")]
+    pub extends: String,
+    /// Commands to run around applying the profile
     #[serde(default, skip_serializing_if = "Run::is_empty")]
     pub run: Run,
+    /// Icon shown for the profile in the app and tray menu
     #[serde(default, skip_serializing_if = "String::is_empty")]
     pub icon: String,
+    /// What applying the profile changes, everything if unset
+    #[serde(default, skip_serializing_if = "Scope::is_full")]
+    pub scope: Scope,
+    /// What to do when applying the profile fails halfway, `rollback` if unset
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub on_failure: Option<OnFailure>,
+}
+
+fn current_version() -> u32 {
+    CONFIG_VERSION
+}
+
+impl Default for Config {
+    fn default() -> Self {
+        Config {
+            version: CONFIG_VERSION,
+            name: String::new(),
+            description: String::new(),
+            extends: String::new(),
+            run: Run::default(),
+            icon: String::new(),
+            scope: Scope::default(),
+            on_failure: None,
+        }
+    }
 }
 
 impl Config {
     pub fn is_empty(&self) -> bool {
         self.name.is_empty()
             && self.description.is_empty()
+            && self.extends.is_empty()
             && self.run.is_empty()
             && self.icon.is_empty()
+            && self.scope.is_full()
+            && self.on_failure.is_none()
     }
 }
 
-#[derive(Serialize, Deserialize, Debug, Default, Clone)]
+/// What applying a profile does when one of its steps fails.
+#[derive(Serialize, Deserialize, JsonSchema, Debug, Default, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum OnFailure {
+    /// Set everything back to how it was before applying
+    #[default]
+    Rollback,
+    /// Apply every other step anyway
+    BestEffort,
+}
+
+#[derive(Serialize, Deserialize, JsonSchema, Debug, Default, Clone)]
 pub struct Run {
+    /// Runs before the display layout is applied
     #[serde(default, skip_serializing_if = "RunCommand::is_empty")]
     pub before: RunCommand,
+    /// Runs after the display layout is applied
     #[serde(default, skip_serializing_if = "RunCommand::is_empty")]
     pub after: RunCommand,
 }
@@ -35,10 +89,12 @@ impl Run {
     }
 }
 
-#[derive(Serialize, Deserialize, Debug, Default, Clone)]
+#[derive(Serialize, Deserialize, JsonSchema, Debug, Default, Clone)]
 pub struct RunCommand {
+    /// Program or shell command to run
     #[serde(default, skip_serializing_if = "String::is_empty")]
     pub target: String,
+    /// Arguments passed to `target`
     #[serde(default, skip_serializing_if = "String::is_empty")]
     pub args: String,
 }
@@ -47,4 +103,72 @@ impl RunCommand {
     pub fn is_empty(&self) -> bool {
         self.target.is_empty() && self.args.is_empty()
     }
-}
\ No newline at end of file
+
+    /// `target` and `args` as they'd be typed.
+    pub fn command_line(&self) -> String {
+        format!("{} {}", self.target, self.args).trim().to_string()
+    }
+}
+/// Which part of the system a profile changes. A profile with a partial scope is merged
+/// onto the live layout instead of replacing it.
+#[derive(Serialize, Deserialize, JsonSchema, Debug, Default, Clone, PartialEq, Eq)]
+pub struct Scope {
+    /// Monitors the profile changes, by friendly name or device path (case-insensitive).
+    /// All other monitors are left as they are, an empty list leaves the display layout
+    /// alone entirely. Unset replaces the whole layout
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub displays: Option<Vec<String>>,
+    /// Extras the profile saves and applies. Unset uses the ones enabled in settings
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub extras: Option<Vec<Extra>>,
+}
+
+impl Scope {
+    /// Whether the profile replaces the whole layout and follows the settings for extras.
+    pub fn is_full(&self) -> bool {
+        self.displays.is_none() && self.extras.is_none()
+    }
+
+    /// Whether the profile only changes some displays and leaves the others alone.
+    pub fn selects_displays(&self) -> bool {
+        self.displays.is_some()
+    }
+
+    /// `global_config` with the `save_*` switches set to the extras in scope.
+    pub fn effective_config(&self, global_config: &GlobalConfig) -> GlobalConfig {
+        let mut config = global_config.clone();
+        if let Some(extras) = &self.extras {
+            config.save_dpi_scale = extras.contains(&Extra::DpiScale);
+            config.save_hdr_state = extras.contains(&Extra::HdrState);
+            config.save_sdr_white_level = extras.contains(&Extra::SdrWhiteLevel);
+            config.save_icon_size = extras.contains(&Extra::IconSize);
+            config.save_wallpaper_info = extras.contains(&Extra::WallpaperInfo);
+            config.save_audio_output = extras.contains(&Extra::AudioOutput);
+        }
+        config
+    }
+}
+
+/// A setting besides the display topology that a profile can carry, named after its
+/// `save_*` switch in `settings.toml`.
+#[derive(Serialize, Deserialize, JsonSchema, Debug, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum Extra {
+    DpiScale,
+    HdrState,
+    SdrWhiteLevel,
+    IconSize,
+    WallpaperInfo,
+    AudioOutput,
+}
+
+impl Extra {
+    pub const ALL: [Extra; 6] = [
+        Extra::DpiScale,
+        Extra::HdrState,
+        Extra::SdrWhiteLevel,
+        Extra::IconSize,
+        Extra::WallpaperInfo,
+        Extra::AudioOutput,
+    ];
+}
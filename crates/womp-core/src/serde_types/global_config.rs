@@ -1,11 +1,15 @@
+use schemars::JsonSchema;
 use serde::{Deserialize, Serialize};
 
-#[derive(Serialize, Deserialize, Debug, Default, Clone)]
+#[derive(Serialize, Deserialize, JsonSchema, Debug, Default, Clone)]
 pub struct GlobalConfig {
+    /// Start WOMP with Windows
     pub autostart: bool,
     pub auto_update: bool,
+    /// `system`, `dark` or `light`
     pub theme: String,
     pub tray_icon: String,
+    /// Run the `before`/`after` commands of profiles
     pub run_commands: bool,
     pub save_dpi_scale: bool,
     pub save_icon_size: bool,
@@ -13,6 +17,50 @@ pub struct GlobalConfig {
     pub save_sdr_white_level: bool,
     pub save_wallpaper_info: bool,
     pub save_audio_output: bool,
+    #[serde(default)]
+    pub log_level: LogLevel,
+    /// Save `displays.json` sorted and without boot-specific values, so re-saving an
+    /// unchanged layout doesn't change the file
+    #[serde(default)]
+    pub canonical_layouts: bool,
+    /// Seconds to confirm a newly applied profile in before the previous layout is
+    /// restored, 0 doesn't ask
+    #[serde(default)]
+    pub confirm_timeout: u32,
+    /// Milliseconds to wait for the displays to settle after setting them before they're
+    /// checked against the profile, 0 checks them right away
+    #[serde(default = "default_settle_timeout_ms")]
+    pub settle_timeout_ms: u32,
+}
+
+fn default_settle_timeout_ms() -> u32 {
+    3000
+}
+
+/// How much womp-core logs, `settings.toml` key `log_level`.
+#[derive(Serialize, Deserialize, JsonSchema, Debug, Default, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum LogLevel {
+    Off,
+    Error,
+    Warn,
+    #[default]
+    Info,
+    Debug,
+    Trace,
+}
+
+impl From<LogLevel> for log::LevelFilter {
+    fn from(level: LogLevel) -> Self {
+        match level {
+            LogLevel::Off => log::LevelFilter::Off,
+            LogLevel::Error => log::LevelFilter::Error,
+            LogLevel::Warn => log::LevelFilter::Warn,
+            LogLevel::Info => log::LevelFilter::Info,
+            LogLevel::Debug => log::LevelFilter::Debug,
+            LogLevel::Trace => log::LevelFilter::Trace,
+        }
+    }
 }
 
 impl GlobalConfig {
@@ -29,6 +77,10 @@ impl GlobalConfig {
             save_sdr_white_level: false,
             save_wallpaper_info: false,
             save_audio_output: false,
+            log_level: LogLevel::Info,
+            canonical_layouts: false,
+            confirm_timeout: 0,
+            settle_timeout_ms: default_settle_timeout_ms(),
         }
     }
 }
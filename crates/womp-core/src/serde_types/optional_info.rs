@@ -1,6 +1,7 @@
+use schemars::JsonSchema;
 use serde::{Deserialize, Serialize};
 
-#[derive(Copy, Clone, Serialize, Deserialize)]
+#[derive(Copy, Clone, Default, Serialize, Deserialize, JsonSchema)]
 pub struct OptionalInfo {
     #[serde(skip_serializing_if = "Option::is_none")]
     pub dpiScale: Option<u32>,
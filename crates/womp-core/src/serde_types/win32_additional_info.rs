@@ -3,22 +3,27 @@
 use crate::serde_types::win32_i32_tuple_struct::{win32_device_info_type, win32_output};
 use crate::serde_types::win32_luid;
 use crate::serde_types::win32_widestring;
+use crate::win32_types::*;
+use schemars::JsonSchema;
 use serde::{Deserialize, Serialize};
-use windows::Win32::{Devices::Display::*, Foundation::LUID};
 
-#[derive(Serialize, Deserialize)]
+#[derive(Serialize, Deserialize, JsonSchema)]
 #[serde(remote = "DISPLAYCONFIG_DEVICE_INFO_HEADER")]
+#[schemars(rename = "DeviceInfoHeader")]
 pub struct DeviceInfoHeaderDef {
     #[serde(with = "win32_device_info_type")]
+    #[schemars(with = "i32")]
     r#type: DISPLAYCONFIG_DEVICE_INFO_TYPE,
     size: u32,
     #[serde(with = "win32_luid")]
+    #[schemars(with = "win32_luid::LuidDef")]
     adapterId: LUID,
     id: u32,
 }
 
-#[derive(Serialize, Deserialize)]
+#[derive(Serialize, Deserialize, JsonSchema)]
 #[serde(remote = "DISPLAYCONFIG_TARGET_DEVICE_NAME")]
+#[schemars(rename = "TargetName")]
 pub struct TargetNameDef {
     #[serde(with = "DeviceInfoHeaderDef")]
     header: DISPLAYCONFIG_DEVICE_INFO_HEADER,
@@ -26,29 +31,34 @@ pub struct TargetNameDef {
     #[serde(default)]
     flags: (),
     #[serde(with = "win32_output")]
+    #[schemars(with = "i32")]
     outputTechnology: DISPLAYCONFIG_VIDEO_OUTPUT_TECHNOLOGY,
     edidManufactureId: u16,
     edidProductCodeId: u16,
     connectorInstance: u32,
     #[serde(with = "win32_widestring")]
+    #[schemars(with = "String")]
     monitorFriendlyDeviceName: [u16; 64],
     #[serde(with = "win32_widestring")]
+    #[schemars(with = "String")]
     monitorDevicePath: [u16; 128],
 }
 
-#[derive(Serialize, Deserialize)]
+#[derive(Serialize, Deserialize, JsonSchema)]
 #[serde(remote = "DISPLAYCONFIG_ADAPTER_NAME")]
+#[schemars(rename = "AdapterName")]
 pub struct AdapterNameDef {
     #[serde(with = "DeviceInfoHeaderDef")]
     header: DISPLAYCONFIG_DEVICE_INFO_HEADER,
     #[serde(with = "win32_widestring")]
+    #[schemars(with = "String")]
     adapterDevicePath: [u16; 128],
 }
 
-#[derive(Serialize, Deserialize)]
+#[derive(Serialize, Deserialize, JsonSchema, Clone)]
 pub struct AdditionalInfo {
     #[serde(with = "TargetNameDef")]
     pub target: DISPLAYCONFIG_TARGET_DEVICE_NAME,
     #[serde(with = "AdapterNameDef")]
     pub adapter: DISPLAYCONFIG_ADAPTER_NAME,
-}
\ No newline at end of file
+}
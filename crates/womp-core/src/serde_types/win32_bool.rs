@@ -1,5 +1,5 @@
+use crate::win32_types::BOOL;
 use serde::{Deserialize, Deserializer, Serializer};
-use windows::core::BOOL;
 
 pub fn serialize<S>(b: &BOOL, serializer: S) -> Result<S::Ok, S::Error>
 where
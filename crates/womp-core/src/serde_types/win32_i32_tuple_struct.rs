@@ -23,29 +23,23 @@ macro_rules! define_tuple_struct_i32_serde_helper {
 
 define_tuple_struct_i32_serde_helper!(
     win32_output,
-    windows::Win32::Devices::Display::DISPLAYCONFIG_VIDEO_OUTPUT_TECHNOLOGY
-);
-define_tuple_struct_i32_serde_helper!(
-    win32_rotation,
-    windows::Win32::Devices::Display::DISPLAYCONFIG_ROTATION
-);
-define_tuple_struct_i32_serde_helper!(
-    win32_scaling,
-    windows::Win32::Devices::Display::DISPLAYCONFIG_SCALING
+    crate::win32_types::DISPLAYCONFIG_VIDEO_OUTPUT_TECHNOLOGY
 );
+define_tuple_struct_i32_serde_helper!(win32_rotation, crate::win32_types::DISPLAYCONFIG_ROTATION);
+define_tuple_struct_i32_serde_helper!(win32_scaling, crate::win32_types::DISPLAYCONFIG_SCALING);
 define_tuple_struct_i32_serde_helper!(
     win32_scanline,
-    windows::Win32::Devices::Display::DISPLAYCONFIG_SCANLINE_ORDERING
+    crate::win32_types::DISPLAYCONFIG_SCANLINE_ORDERING
 );
 define_tuple_struct_i32_serde_helper!(
     win32_mode_info_type,
-    windows::Win32::Devices::Display::DISPLAYCONFIG_MODE_INFO_TYPE
+    crate::win32_types::DISPLAYCONFIG_MODE_INFO_TYPE
 );
 define_tuple_struct_i32_serde_helper!(
     win32_pixel_format,
-    windows::Win32::Devices::Display::DISPLAYCONFIG_PIXELFORMAT
+    crate::win32_types::DISPLAYCONFIG_PIXELFORMAT
 );
 define_tuple_struct_i32_serde_helper!(
     win32_device_info_type,
-    windows::Win32::Devices::Display::DISPLAYCONFIG_DEVICE_INFO_TYPE
+    crate::win32_types::DISPLAYCONFIG_DEVICE_INFO_TYPE
 );
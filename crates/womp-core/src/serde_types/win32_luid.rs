@@ -1,8 +1,10 @@
+use crate::win32_types::LUID as Win32LUID;
+use schemars::JsonSchema;
 use serde::{Deserialize, Deserializer, Serialize, Serializer};
-use windows::Win32::Foundation::LUID as Win32LUID;
 
-#[derive(Serialize, Deserialize)]
-struct LuidDef {
+#[derive(Serialize, Deserialize, JsonSchema)]
+#[schemars(rename = "Luid")]
+pub(crate) struct LuidDef {
     lowPart: u32,
     highPart: i32,
 }
@@ -6,60 +6,77 @@ use crate::serde_types::win32_pointl;
 use crate::serde_types::win32_rational;
 use crate::serde_types::win32_region;
 use crate::serde_types::win32_video_signal_info_union::Win32VideoSignalInfoUnion;
+use crate::win32_types::DISPLAYCONFIG_MODE_INFO;
+use crate::win32_types::LUID as Win32LUID;
+use crate::win32_types::POINTL;
+use crate::win32_types::*;
+use schemars::JsonSchema;
 use serde::{Deserialize, Deserializer, Serialize, Serializer};
-use windows::Win32::Devices::Display::DISPLAYCONFIG_MODE_INFO;
-use windows::Win32::Devices::Display::*;
-use windows::Win32::Foundation::LUID as Win32LUID;
-use windows::Win32::Foundation::POINTL;
 
-#[derive(Serialize, Deserialize)]
+#[derive(Serialize, Deserialize, JsonSchema, Clone)]
 pub struct ModeInfo {
     #[serde(with = "ModeInfoDef")]
+    #[schemars(with = "ModeInfoSerializationHelper")]
     pub sourceModeInfo: DISPLAYCONFIG_MODE_INFO,
     #[serde(with = "ModeInfoDef")]
+    #[schemars(with = "ModeInfoSerializationHelper")]
     pub targetModeInfo: DISPLAYCONFIG_MODE_INFO,
 }
 
-#[derive(Serialize, Deserialize)]
+#[derive(Serialize, Deserialize, JsonSchema)]
 #[serde(remote = "DISPLAYCONFIG_VIDEO_SIGNAL_INFO")]
+#[schemars(rename = "VideoSignalInfo")]
 pub struct VideoSignalInfo {
     pub pixelRate: u64,
     #[serde(with = "win32_rational")]
+    #[schemars(with = "win32_rational::RationalDef")]
     pub hSyncFreq: DISPLAYCONFIG_RATIONAL,
     #[serde(with = "win32_rational")]
+    #[schemars(with = "win32_rational::RationalDef")]
     pub vSyncFreq: DISPLAYCONFIG_RATIONAL,
     #[serde(with = "win32_region")]
+    #[schemars(with = "win32_region::RegionDef")]
     pub activeSize: DISPLAYCONFIG_2DREGION,
     #[serde(with = "win32_region")]
+    #[schemars(with = "win32_region::RegionDef")]
     pub totalSize: DISPLAYCONFIG_2DREGION,
     #[serde(with = "Win32VideoSignalInfoUnion", rename = "videoStandard")]
+    #[schemars(with = "u32")]
     pub Anonymous: DISPLAYCONFIG_VIDEO_SIGNAL_INFO_0,
     #[serde(with = "win32_scanline")]
+    #[schemars(with = "i32")]
     pub scanLineOrdering: DISPLAYCONFIG_SCANLINE_ORDERING,
 }
 
-#[derive(Serialize, Deserialize)]
+#[derive(Serialize, Deserialize, JsonSchema)]
+#[schemars(rename = "TargetMode")]
 pub struct TargetModeSerdeRepr {
     #[serde(with = "VideoSignalInfo")]
     targetVideoSignalInfo: DISPLAYCONFIG_VIDEO_SIGNAL_INFO,
 }
 
-#[derive(Serialize, Deserialize)]
+#[derive(Serialize, Deserialize, JsonSchema)]
+#[schemars(rename = "SourceMode")]
 pub struct SourceModeSerdeRepr {
     pub width: u32,
     pub height: u32,
     #[serde(with = "win32_pixel_format")]
+    #[schemars(with = "i32")]
     pub pixelFormat: DISPLAYCONFIG_PIXELFORMAT,
     #[serde(with = "win32_pointl")]
+    #[schemars(with = "win32_pointl::PointLDef")]
     pub position: POINTL,
 }
 
-#[derive(Serialize, Deserialize)]
-struct ModeInfoSerializationHelper {
+#[derive(Serialize, Deserialize, JsonSchema)]
+#[schemars(rename = "Mode")]
+pub(crate) struct ModeInfoSerializationHelper {
     #[serde(with = "win32_mode_info_type")]
+    #[schemars(with = "i32")]
     infoType: DISPLAYCONFIG_MODE_INFO_TYPE, // Field names can be same as ModeInfoDef
     id: u32,
     #[serde(with = "win32_luid")]
+    #[schemars(with = "win32_luid::LuidDef")]
     adapterId: Win32LUID,
 
     #[serde(skip_serializing_if = "Option::is_none")]
@@ -2,14 +2,17 @@ use crate::serde_types::win32_bool;
 use crate::serde_types::win32_i32_tuple_struct::*;
 use crate::serde_types::win32_luid;
 use crate::serde_types::win32_rational;
+use crate::win32_types::BOOL;
+use crate::win32_types::LUID as Win32LUID;
+use crate::win32_types::*;
+use schemars::JsonSchema;
 use serde::{Deserialize, Deserializer, Serialize, Serializer};
-use windows::Win32::Devices::Display::*;
-use windows::Win32::Foundation::LUID as Win32LUID;
-use windows::core::BOOL;
 
-#[derive(Serialize, Deserialize)]
+#[derive(Serialize, Deserialize, JsonSchema)]
+#[schemars(rename = "SourceInfo")]
 pub struct SourceInfoHelper {
     #[serde(with = "win32_luid")]
+    #[schemars(with = "win32_luid::LuidDef")]
     pub adapterId: Win32LUID,
     pub id: u32,
     pub statusFlags: u32,
@@ -67,22 +70,30 @@ impl SourceInfoDef {
     }
 }
 
-#[derive(Serialize, Deserialize)]
+#[derive(Serialize, Deserialize, JsonSchema)]
+#[schemars(rename = "TargetInfo")]
 pub struct TargetInfoHelper {
     #[serde(with = "win32_luid")]
+    #[schemars(with = "win32_luid::LuidDef")]
     pub adapterId: Win32LUID,
     pub id: u32,
     #[serde(with = "win32_output")]
+    #[schemars(with = "i32")]
     pub outputTechnology: DISPLAYCONFIG_VIDEO_OUTPUT_TECHNOLOGY,
     #[serde(with = "win32_rotation")]
+    #[schemars(with = "i32")]
     pub rotation: DISPLAYCONFIG_ROTATION,
     #[serde(with = "win32_scaling")]
+    #[schemars(with = "i32")]
     pub scaling: DISPLAYCONFIG_SCALING,
     #[serde(with = "win32_rational")]
+    #[schemars(with = "win32_rational::RationalDef")]
     pub refreshRate: DISPLAYCONFIG_RATIONAL,
     #[serde(with = "win32_scanline")]
+    #[schemars(with = "i32")]
     pub scanLineOrdering: DISPLAYCONFIG_SCANLINE_ORDERING,
     #[serde(with = "win32_bool")]
+    #[schemars(with = "bool")]
     pub targetAvailable: BOOL,
     pub statusFlags: u32,
     #[serde(skip_serializing_if = "Option::is_none")]
@@ -151,12 +162,15 @@ impl TargetInfoDef {
     }
 }
 
-#[derive(Serialize, Deserialize)]
+#[derive(Serialize, Deserialize, JsonSchema)]
 #[serde(remote = "DISPLAYCONFIG_PATH_INFO")]
+#[schemars(rename = "PathInfo")]
 pub struct PathInfoDef {
     #[serde(with = "SourceInfoDef")]
+    #[schemars(with = "SourceInfoHelper")]
     sourceInfo: DISPLAYCONFIG_PATH_SOURCE_INFO,
     #[serde(with = "TargetInfoDef")]
+    #[schemars(with = "TargetInfoHelper")]
     targetInfo: DISPLAYCONFIG_PATH_TARGET_INFO,
     flags: u32,
 }
@@ -1,8 +1,10 @@
+use crate::win32_types::POINTL;
+use schemars::JsonSchema;
 use serde::{Deserialize, Deserializer, Serialize, Serializer};
-use windows::Win32::Foundation::POINTL;
 
-#[derive(Serialize, Deserialize)]
-struct PointLDef {
+#[derive(Serialize, Deserialize, JsonSchema)]
+#[schemars(rename = "Point")]
+pub(crate) struct PointLDef {
     x: i32,
     y: i32,
 }
@@ -1,8 +1,10 @@
+use crate::win32_types::DISPLAYCONFIG_RATIONAL;
+use schemars::JsonSchema;
 use serde::{Deserialize, Deserializer, Serialize, Serializer};
-use windows::Win32::Devices::Display::DISPLAYCONFIG_RATIONAL;
 
-#[derive(Serialize, Deserialize)]
-struct RationalDef {
+#[derive(Serialize, Deserialize, JsonSchema)]
+#[schemars(rename = "Rational")]
+pub(crate) struct RationalDef {
     numerator: u32,
     denominator: u32,
 }
@@ -1,8 +1,10 @@
+use crate::win32_types::DISPLAYCONFIG_2DREGION;
+use schemars::JsonSchema;
 use serde::{Deserialize, Deserializer, Serialize, Serializer};
-use windows::Win32::Devices::Display::DISPLAYCONFIG_2DREGION;
 
-#[derive(Serialize, Deserialize)]
-struct RegionDef {
+#[derive(Serialize, Deserialize, JsonSchema)]
+#[schemars(rename = "Region")]
+pub(crate) struct RegionDef {
     cx: u32,
     cy: u32,
 }
@@ -1,5 +1,5 @@
+use crate::win32_types::DISPLAYCONFIG_VIDEO_SIGNAL_INFO_0;
 use serde::{Deserialize, Deserializer, Serialize, Serializer};
-use windows::Win32::Devices::Display::DISPLAYCONFIG_VIDEO_SIGNAL_INFO_0;
 
 pub struct Win32VideoSignalInfoUnion;
 
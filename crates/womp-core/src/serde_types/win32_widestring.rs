@@ -1,11 +1,11 @@
 use serde::{Deserialize, Deserializer, Serializer};
-use widestring::WideCString;
+use widestring::U16CString;
 
 pub fn serialize<S, const N: usize>(wide_arr: &[u16; N], serializer: S) -> Result<S::Ok, S::Error>
 where
     S: Serializer,
 {
-    let serialized = WideCString::from_vec_truncate(wide_arr).to_string_lossy();
+    let serialized = U16CString::from_vec_truncate(wide_arr).to_string_lossy();
 
     serializer.serialize_str(&serialized)
 }
@@ -16,7 +16,7 @@ where
 {
     let s: String = Deserialize::deserialize(deserializer)?;
 
-    let widestring = WideCString::from_str(&s).map_err(serde::de::Error::custom)?;
+    let widestring = U16CString::from_str(&s).map_err(serde::de::Error::custom)?;
 
     let u16_vec = widestring.into_vec_with_nul();
 
@@ -0,0 +1,108 @@
+//! The layout from before applying a profile, to set things back when applying fails
+//! halfway or the new layout isn't confirmed in time.
+
+use crate::backend::DisplayBackend;
+use crate::error::{AfterFailure, Result, StepFailure, WompError};
+use crate::serde_types::DisplayLayout;
+use crate::serde_types::global_config::GlobalConfig;
+use log::{error, info};
+use std::fmt;
+use std::ops::ControlFlow;
+
+/// The layout from before applying a profile, see [`crate::apply_display_layout_unconfirmed`].
+#[derive(Clone)]
+pub struct Snapshot {
+    profile: String,
+    layout: DisplayLayout,
+    /// Whether applying changed the displays, otherwise only extras are set back.
+    topology: bool,
+    /// Which extras are set back.
+    extras_config: GlobalConfig,
+}
+
+// The layout is left out, it's long and only kept to be set back
+impl fmt::Debug for Snapshot {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Snapshot")
+            .field("profile", &self.profile)
+            .field("topology", &self.topology)
+            .finish_non_exhaustive()
+    }
+}
+
+impl Snapshot {
+    pub(crate) fn new(
+        profile: &str,
+        layout: DisplayLayout,
+        topology: bool,
+        extras_config: GlobalConfig,
+    ) -> Self {
+        Snapshot {
+            profile: profile.to_string(),
+            layout,
+            topology,
+            extras_config,
+        }
+    }
+
+    /// The profile that was applied after the snapshot was taken.
+    pub fn profile(&self) -> &str {
+        &self.profile
+    }
+
+    /// The layout from before applying, with every extra.
+    pub(crate) fn layout(&self) -> &DisplayLayout {
+        &self.layout
+    }
+
+    /// Sets the layout back to the snapshot, the displays only if applying changed them
+    /// and the extras applying set. Goes on when a step fails.
+    pub(crate) fn restore(&self, backend: &mut dyn DisplayBackend) -> AfterFailure {
+        info!(profile = self.profile.as_str(); "Restoring the layout from before applying");
+        let mut failures = vec![];
+        let restored = if self.topology {
+            backend.apply_topology(&self.layout, true).map(|_| ())
+        } else {
+            Ok(())
+        };
+        if let Err(error) = restored {
+            failures.push(StepFailure {
+                step: "the display layout".to_string(),
+                error,
+            });
+        }
+        let _ = backend.apply_extras_with(&self.layout, &self.extras_config, &mut |made| {
+            let step = made.describe();
+            if let Err(error) = made.result {
+                failures.push(StepFailure { step, error });
+            }
+            ControlFlow::Continue(())
+        });
+
+        if failures.is_empty() {
+            AfterFailure::RolledBack
+        } else {
+            error!(failed = failures.len(); "Could not restore the layout from before applying");
+            AfterFailure::RollbackFailed(failures)
+        }
+    }
+
+    /// Keeps the new layout, saving the displays as they are now for the next time these
+    /// monitors are connected. Applying left that to be done once the layout is confirmed.
+    pub fn confirm_with(&self, backend: &mut dyn DisplayBackend) -> Result<()> {
+        if !self.topology {
+            return Ok(());
+        }
+        info!(profile = self.profile.as_str(); "Saving the confirmed layout");
+        let current = backend.get_display_layout(&GlobalConfig::default())?;
+        backend.apply_topology(&current, true).map(|_| ())
+    }
+
+    /// Sets everything applying the profile changed back to how it was.
+    pub fn revert_with(&self, backend: &mut dyn DisplayBackend) -> Result<()> {
+        match self.restore(backend) {
+            AfterFailure::RollbackFailed(failures) => Err(WompError::RevertFailed(failures)),
+            AfterFailure::KeptGoing | AfterFailure::RolledBack => Ok(()),
+        }
+    }
+}
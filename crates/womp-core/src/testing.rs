@@ -0,0 +1,95 @@
+//! Monitors and layouts for tests, shaped like the ones `QueryDisplayConfig` reports.
+
+use crate::model::*;
+use crate::serde_types::{Display, DisplayLayout, GlobalInfo};
+
+pub(crate) const ADAPTER: AdapterLuid = AdapterLuid {
+    low_part: 0x1234,
+    high_part: 0,
+};
+
+/// An active 1920x1080 monitor on target `target_id` of [`ADAPTER`] at `(x, 0)`, running
+/// at `hz`. Every target id gets its own model and device path.
+pub(crate) fn monitor(target_id: u32, x: i32, hz: u32) -> Monitor {
+    Monitor {
+        adapter: Adapter {
+            luid: ADAPTER,
+            device_path: "\\\\?\\PCI#VEN_10DE&DEV_2684".to_string(),
+        },
+        source_id: target_id,
+        target_id,
+        name: format!("Monitor {target_id}"),
+        device_path: format!("\\\\?\\DISPLAY#DEL{target_id:04X}#5&1a2b3c&0&UID{target_id}"),
+        edid: EdidIds {
+            manufacturer_id: 0xAC10,
+            product_code: 0x4000 + target_id as u16,
+        },
+        edid_info: None,
+        connector_instance: 1,
+        output_technology: OutputTechnology::DisplayPort,
+        mode: Mode {
+            width: 1920,
+            height: 1080,
+            refresh_rate: RefreshRate::new(hz, 1),
+        },
+        position: Position { x, y: 0 },
+        rotation: Rotation::Identity,
+        extras: Default::default(),
+        ccd: CcdDetails {
+            // DISPLAYCONFIG_PATH_ACTIVE
+            path_flags: 1,
+            source_adapter: ADAPTER,
+            target_available: true,
+            // DISPLAYCONFIG_SCALING_IDENTITY, DISPLAYCONFIG_SCANLINE_ORDERING_PROGRESSIVE
+            scaling: 1,
+            scan_line_ordering: 1,
+            source_mode: ModeHeader {
+                info_type: 1,
+                id: target_id,
+                adapter: ADAPTER,
+            },
+            // DISPLAYCONFIG_PIXELFORMAT_32BPP
+            pixel_format: 4,
+            target_mode: ModeHeader {
+                info_type: 2,
+                id: target_id,
+                adapter: ADAPTER,
+            },
+            video_signal: VideoSignal {
+                pixel_rate: 148_500_000,
+                h_sync_freq: RefreshRate::new(67_500, 1),
+                v_sync_freq: RefreshRate::new(hz, 1),
+                active_size: Size { cx: 1920, cy: 1080 },
+                total_size: Size { cx: 2200, cy: 1125 },
+                video_standard: 255,
+                scan_line_ordering: 1,
+            },
+            // DISPLAYCONFIG_DEVICE_INFO_GET_TARGET_NAME
+            target_name_header: DeviceInfoHeader {
+                info_type: 2,
+                size: 420,
+                adapter: ADAPTER,
+                id: target_id,
+            },
+            target_name_output_technology: 10,
+            // DISPLAYCONFIG_DEVICE_INFO_GET_ADAPTER_NAME
+            adapter_name_header: DeviceInfoHeader {
+                info_type: 4,
+                size: 276,
+                adapter: ADAPTER,
+                id: target_id,
+            },
+            ..Default::default()
+        },
+    }
+}
+
+/// A layout with `monitors`, their mode indices numbered like `QueryDisplayConfig` does.
+pub(crate) fn layout(monitors: &[Monitor]) -> DisplayLayout {
+    let mut displays: Vec<Display> = monitors.iter().map(Into::into).collect();
+    for (i, display) in displays.iter_mut().enumerate() {
+        display.pathInfo.targetInfo.Anonymous.modeInfoIdx = 2 * i as u32;
+        display.pathInfo.sourceInfo.Anonymous.modeInfoIdx = 2 * i as u32 + 1;
+    }
+    DisplayLayout::from(displays, GlobalInfo::from(Some(48), None, None))
+}
@@ -0,0 +1,522 @@
+//! Checks a [`DisplayLayout`] for problems before it's handed to `SetDisplayConfig`, which
+//! only ever answers with a bare `ERROR_INVALID_PARAMETER`.
+
+use crate::backend::{DPI_VALS, WALLPAPER_POSITIONS};
+use crate::model::{Layout, Monitor, Position};
+use crate::serde_types::DisplayLayout;
+use crate::win32_types::{
+    DISPLAYCONFIG_MODE_INFO_TYPE_SOURCE, DISPLAYCONFIG_MODE_INFO_TYPE_TARGET,
+};
+use serde::Serialize;
+use std::fmt;
+
+#[derive(Serialize, Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+#[serde(rename_all = "lowercase")]
+pub enum Severity {
+    /// Applying will probably work, but not the way it was meant to.
+    Warning,
+    /// Applying will fail.
+    Error,
+}
+
+/// A single problem found in a layout.
+#[derive(Serialize, Debug, Clone)]
+pub struct Issue {
+    pub severity: Severity,
+    /// Stable identifier of the check, e.g. `overlapping-displays`.
+    pub code: &'static str,
+    pub message: String,
+    /// Index into `displays` of the display the issue is about, if it's about one.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub display: Option<usize>,
+}
+
+impl fmt::Display for Issue {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let severity = match self.severity {
+            Severity::Warning => "warning",
+            Severity::Error => "error",
+        };
+        write!(f, "{severity}[{}]: {}", self.code, self.message)
+    }
+}
+
+/// Everything [`validate_layout`] found, errors first.
+#[derive(Serialize, Debug, Clone, Default)]
+pub struct ValidationReport {
+    pub issues: Vec<Issue>,
+}
+
+impl ValidationReport {
+    pub fn errors(&self) -> impl Iterator<Item = &Issue> {
+        self.issues.iter().filter(|i| i.severity == Severity::Error)
+    }
+
+    pub fn warnings(&self) -> impl Iterator<Item = &Issue> {
+        self.issues
+            .iter()
+            .filter(|i| i.severity == Severity::Warning)
+    }
+
+    pub fn has_errors(&self) -> bool {
+        self.errors().next().is_some()
+    }
+
+    fn error(&mut self, code: &'static str, display: Option<usize>, message: String) {
+        self.push(Severity::Error, code, display, message);
+    }
+
+    fn warning(&mut self, code: &'static str, display: Option<usize>, message: String) {
+        self.push(Severity::Warning, code, display, message);
+    }
+
+    fn push(
+        &mut self,
+        severity: Severity,
+        code: &'static str,
+        display: Option<usize>,
+        message: String,
+    ) {
+        self.issues.push(Issue {
+            severity,
+            code,
+            message,
+            display,
+        });
+    }
+}
+
+/// Desktop rectangle of a display: left, top, right, bottom.
+#[derive(Clone, Copy)]
+struct Rect(i64, i64, i64, i64);
+
+impl Rect {
+    fn of(monitor: &Monitor) -> Self {
+        let Position { x, y } = monitor.position;
+        let (x, y) = (i64::from(x), i64::from(y));
+        Rect(
+            x,
+            y,
+            x + i64::from(monitor.mode.width),
+            y + i64::from(monitor.mode.height),
+        )
+    }
+
+    fn overlaps(self, other: Rect) -> bool {
+        self.0 < other.2 && other.0 < self.2 && self.1 < other.3 && other.1 < self.3
+    }
+
+    /// Whether the two share a piece of an edge, which is what Windows needs to let the
+    /// mouse cross over.
+    fn touches(self, other: Rect) -> bool {
+        let x_overlap = self.0 < other.2 && other.0 < self.2;
+        let y_overlap = self.1 < other.3 && other.1 < self.3;
+        ((self.2 == other.0 || other.2 == self.0) && y_overlap)
+            || ((self.3 == other.1 || other.3 == self.1) && x_overlap)
+    }
+}
+
+fn describe(index: usize, monitor: &Monitor) -> String {
+    if monitor.name.is_empty() {
+        format!("Display {index} (target {})", monitor.target_id)
+    } else {
+        format!("Display {index} (`{}`)", monitor.name)
+    }
+}
+
+/// Runs every check on `layout`.
+pub fn validate_layout(layout: &DisplayLayout) -> ValidationReport {
+    validate(layout, false)
+}
+
+/// Checks the displays of a partial profile on their own. Whether they fit in with the
+/// rest of the desktop can only be told once they're merged onto the live layout.
+pub fn validate_partial_layout(layout: &DisplayLayout) -> ValidationReport {
+    validate(layout, true)
+}
+
+fn validate(layout: &DisplayLayout, partial: bool) -> ValidationReport {
+    let mut report = ValidationReport::default();
+    let model = Layout::from(layout);
+    let monitors = &model.monitors;
+
+    if monitors.is_empty() && !partial {
+        report.error(
+            "no-displays",
+            None,
+            "The layout has no displays".to_string(),
+        );
+        return report;
+    }
+
+    for (i, monitor) in monitors.iter().enumerate() {
+        check_modes(&mut report, monitors, i, monitor);
+        check_extras(&mut report, i, monitor);
+        let duplicate = monitors[..i]
+            .iter()
+            .any(|m| m.adapter.luid == monitor.adapter.luid && m.target_id == monitor.target_id);
+        if duplicate {
+            report.error(
+                "duplicate-target",
+                Some(i),
+                format!(
+                    "{} uses target id {}, which an earlier display already uses",
+                    describe(i, monitor),
+                    monitor.target_id
+                ),
+            );
+        }
+    }
+
+    if !partial {
+        check_desktop(&mut report, monitors);
+    }
+
+    let wallpaper_position = model
+        .global
+        .wallpaperInfo
+        .as_ref()
+        .map(|info| info.wallpaperPosition.as_str());
+    if let Some(position) = wallpaper_position.filter(|p| !WALLPAPER_POSITIONS.contains(p)) {
+        report.error(
+            "wallpaper-position",
+            None,
+            format!(
+                "Unknown wallpaper position `{position}`, expected one of {}",
+                WALLPAPER_POSITIONS.join(", ")
+            ),
+        );
+    }
+
+    report
+        .issues
+        .sort_by_key(|issue| std::cmp::Reverse(issue.severity));
+    report
+}
+
+// Applying sends the modes as [target 0, source 0, target 1, source 1, ...], the indices
+// in the paths have to point at the display's own modes in that array
+fn check_modes(report: &mut ValidationReport, monitors: &[Monitor], i: usize, monitor: &Monitor) {
+    let ccd = &monitor.ccd;
+    let mode_count = monitors.len() * 2;
+    for (kind, idx, info_type, id) in [
+        (
+            "target",
+            ccd.target_mode_info_idx,
+            DISPLAYCONFIG_MODE_INFO_TYPE_TARGET,
+            monitor.target_id,
+        ),
+        (
+            "source",
+            ccd.source_mode_info_idx,
+            DISPLAYCONFIG_MODE_INFO_TYPE_SOURCE,
+            monitor.source_id,
+        ),
+    ] {
+        let idx = idx as usize;
+        if idx >= mode_count {
+            report.error(
+                "mode-index-out-of-range",
+                Some(i),
+                format!(
+                    "{} refers to {kind} mode {idx}, but there are only {mode_count} modes",
+                    describe(i, monitor)
+                ),
+            );
+            continue;
+        }
+        let owner = &monitors[idx / 2].ccd;
+        let mode = if idx.is_multiple_of(2) {
+            owner.target_mode
+        } else {
+            owner.source_mode
+        };
+        if mode.info_type != info_type.0 || mode.id != id {
+            report.error(
+                "mode-index-mismatch",
+                Some(i),
+                format!(
+                    "{} refers to mode {idx} as its {kind} mode, but that's not its {kind} mode",
+                    describe(i, monitor)
+                ),
+            );
+        }
+    }
+
+    let refresh_rate = monitor.mode.refresh_rate;
+    if refresh_rate.denominator == 0 && refresh_rate.numerator != 0 {
+        report.error(
+            "zero-denominator",
+            Some(i),
+            format!(
+                "{} has a refresh rate with a denominator of 0",
+                describe(i, monitor)
+            ),
+        );
+    }
+    if ccd.target_mode.info_type == DISPLAYCONFIG_MODE_INFO_TYPE_TARGET.0 {
+        let signal = &ccd.video_signal;
+        for (name, rate) in [
+            ("vSyncFreq", signal.v_sync_freq),
+            ("hSyncFreq", signal.h_sync_freq),
+        ] {
+            if rate.denominator == 0 {
+                report.error(
+                    "zero-denominator",
+                    Some(i),
+                    format!(
+                        "{} has a {name} with a denominator of 0",
+                        describe(i, monitor)
+                    ),
+                );
+            }
+        }
+    }
+}
+
+fn check_extras(report: &mut ValidationReport, i: usize, monitor: &Monitor) {
+    let extras = &monitor.extras;
+    if let Some(dpi_scale) = extras.dpiScale.filter(|dpi| !DPI_VALS.contains(dpi)) {
+        report.error(
+            "dpi-scale",
+            Some(i),
+            format!(
+                "{} has a DPI scale of {dpi_scale}%, supported are {}",
+                describe(i, monitor),
+                DPI_VALS.map(|v| v.to_string()).join(", ")
+            ),
+        );
+    }
+    if let Some(white_level) = extras.sdrWhiteLevel {
+        if extras.hdrEnabled != Some(true) {
+            report.warning(
+                "sdr-white-level-without-hdr",
+                Some(i),
+                format!(
+                    "{} sets an SDR white level but HDR is off, it won't be applied",
+                    describe(i, monitor)
+                ),
+            );
+        }
+        if !(80..=480).contains(&white_level) {
+            report.error(
+                "sdr-white-level",
+                Some(i),
+                format!(
+                    "{} has an SDR white level of {white_level} nits, it has to be 80 to 480",
+                    describe(i, monitor)
+                ),
+            );
+        }
+    }
+}
+
+fn check_desktop(report: &mut ValidationReport, monitors: &[Monitor]) {
+    // Clones share a source and are supposed to cover the same area
+    let mut desktops: Vec<(usize, Rect)> = vec![];
+    for (i, monitor) in monitors.iter().enumerate() {
+        let is_clone = monitors[..i].iter().any(|m| {
+            m.ccd.source_adapter == monitor.ccd.source_adapter && m.source_id == monitor.source_id
+        });
+        if !is_clone {
+            desktops.push((i, Rect::of(monitor)));
+        }
+    }
+
+    if !monitors.iter().any(|m| m.position == Position::default()) {
+        report.error(
+            "no-primary",
+            None,
+            "No display is at (0, 0), Windows needs one there as the primary display".to_string(),
+        );
+    }
+
+    for (n, &(i, rect)) in desktops.iter().enumerate() {
+        for &(j, other) in &desktops[..n] {
+            if rect.overlaps(other) {
+                report.error(
+                    "overlapping-displays",
+                    Some(i),
+                    format!(
+                        "{} overlaps {}",
+                        describe(i, &monitors[i]),
+                        describe(j, &monitors[j])
+                    ),
+                );
+            }
+        }
+    }
+
+    // Every desktop has to be reachable from the first one through shared edges
+    let mut reached = vec![false; desktops.len()];
+    let mut pending = vec![0];
+    reached[0] = true;
+    while let Some(n) = pending.pop() {
+        for (m, &(_, rect)) in desktops.iter().enumerate() {
+            let other = desktops[n].1;
+            // Overlaps are reported above, don't also call them disconnected
+            if !reached[m] && (rect.touches(other) || rect.overlaps(other)) {
+                reached[m] = true;
+                pending.push(m);
+            }
+        }
+    }
+    for (n, &(i, _)) in desktops.iter().enumerate() {
+        if !reached[n] {
+            report.warning(
+                "disconnected-display",
+                Some(i),
+                format!(
+                    "{} doesn't touch the rest of the desktop, Windows will move it",
+                    describe(i, &monitors[i])
+                ),
+            );
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::model::RefreshRate;
+    use crate::serde_types::WallpaperInfo;
+    use crate::testing::{layout, monitor};
+
+    fn codes(report: &ValidationReport) -> Vec<&'static str> {
+        report.issues.iter().map(|issue| issue.code).collect()
+    }
+
+    /// The layout of `monitors`, changed by `edit` after its mode indices were numbered.
+    fn edited(monitors: &[Monitor], edit: impl FnOnce(&mut Layout)) -> DisplayLayout {
+        let mut model = Layout::from(&layout(monitors));
+        edit(&mut model);
+        (&model).into()
+    }
+
+    #[test]
+    fn side_by_side_displays_are_fine() {
+        let report = validate_layout(&layout(&[monitor(1, 0, 60), monitor(2, 1920, 144)]));
+        assert!(report.issues.is_empty(), "{:?}", report.issues);
+    }
+
+    #[test]
+    fn no_displays() {
+        assert_eq!(codes(&validate_layout(&layout(&[]))), ["no-displays"]);
+        assert!(validate_partial_layout(&layout(&[])).issues.is_empty());
+    }
+
+    #[test]
+    fn duplicate_target() {
+        let mut second = monitor(1, 1920, 60);
+        second.source_id = 2;
+        second.ccd.source_mode.id = 2;
+        let report = validate_layout(&layout(&[monitor(1, 0, 60), second]));
+        assert_eq!(codes(&report), ["duplicate-target"]);
+        assert_eq!(report.issues[0].display, Some(1));
+    }
+
+    #[test]
+    fn mode_index_out_of_range() {
+        let layout = edited(&[monitor(1, 0, 60)], |layout| {
+            layout.monitors[0].ccd.source_mode_info_idx = 7;
+        });
+        assert_eq!(
+            codes(&validate_layout(&layout)),
+            ["mode-index-out-of-range"]
+        );
+    }
+
+    #[test]
+    fn mode_index_mismatch() {
+        // Each display points at the other one's modes
+        let layout = edited(&[monitor(1, 0, 60), monitor(2, 1920, 60)], |layout| {
+            layout.monitors[0].ccd.target_mode_info_idx = 2;
+            layout.monitors[1].ccd.target_mode_info_idx = 0;
+        });
+        assert_eq!(
+            codes(&validate_layout(&layout)),
+            ["mode-index-mismatch", "mode-index-mismatch"]
+        );
+    }
+
+    #[test]
+    fn zero_denominator() {
+        let mut zero = monitor(1, 0, 60);
+        zero.mode.refresh_rate = RefreshRate::new(60, 0);
+        zero.ccd.video_signal.v_sync_freq = RefreshRate::new(60, 0);
+        let report = validate_layout(&layout(&[zero]));
+        assert_eq!(codes(&report), ["zero-denominator", "zero-denominator"]);
+
+        // 0/0 lets the driver pick, that's fine
+        let mut unspecified = monitor(1, 0, 60);
+        unspecified.mode.refresh_rate = RefreshRate::default();
+        assert!(validate_layout(&layout(&[unspecified])).issues.is_empty());
+    }
+
+    #[test]
+    fn dpi_scale() {
+        let mut odd_dpi = monitor(1, 0, 60);
+        odd_dpi.extras.dpiScale = Some(130);
+        assert_eq!(codes(&validate_layout(&layout(&[odd_dpi]))), ["dpi-scale"]);
+    }
+
+    #[test]
+    fn sdr_white_level() {
+        let mut without_hdr = monitor(1, 0, 60);
+        without_hdr.extras.sdrWhiteLevel = Some(200);
+        assert_eq!(
+            codes(&validate_layout(&layout(&[without_hdr]))),
+            ["sdr-white-level-without-hdr"]
+        );
+
+        let mut too_bright = monitor(1, 0, 60);
+        too_bright.extras.hdrEnabled = Some(true);
+        too_bright.extras.sdrWhiteLevel = Some(500);
+        assert_eq!(
+            codes(&validate_layout(&layout(&[too_bright]))),
+            ["sdr-white-level"]
+        );
+    }
+
+    #[test]
+    fn no_primary() {
+        let report = validate_layout(&layout(&[monitor(1, 100, 60), monitor(2, 2020, 60)]));
+        assert_eq!(codes(&report), ["no-primary"]);
+    }
+
+    #[test]
+    fn overlapping_displays() {
+        let report = validate_layout(&layout(&[monitor(1, 0, 60), monitor(2, 1000, 60)]));
+        assert_eq!(codes(&report), ["overlapping-displays"]);
+        assert_eq!(report.issues[0].display, Some(1));
+    }
+
+    #[test]
+    fn disconnected_display() {
+        let layout = layout(&[monitor(1, 0, 60), monitor(2, 3000, 60)]);
+        let report = validate_layout(&layout);
+        assert_eq!(codes(&report), ["disconnected-display"]);
+        assert!(!report.has_errors());
+
+        // Where the displays of a partial profile end up is checked after merging
+        assert!(validate_partial_layout(&layout).issues.is_empty());
+    }
+
+    #[test]
+    fn wallpaper_position() {
+        let mut layout = layout(&[monitor(1, 0, 60)]);
+        layout.globalInfo.wallpaperInfo = Some(WallpaperInfo {
+            wallpaperPath: "C:\\wallpaper.jpg".to_string(),
+            wallpaperPosition: "sideways".to_string(),
+        });
+        assert_eq!(codes(&validate_layout(&layout)), ["wallpaper-position"]);
+    }
+
+    #[test]
+    fn errors_come_first() {
+        let mut odd_dpi = monitor(2, 3000, 60);
+        odd_dpi.extras.dpiScale = Some(130);
+        let report = validate_layout(&layout(&[monitor(1, 0, 60), odd_dpi]));
+        assert_eq!(codes(&report), ["dpi-scale", "disconnected-display"]);
+    }
+}
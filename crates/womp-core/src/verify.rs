@@ -0,0 +1,150 @@
+//! Checks the displays ended up the way a profile set them.
+//!
+//! `SetDisplayConfig` returns before drivers are done, and with `SDC_ALLOW_CHANGES` they
+//! may pick another mode than the one asked for without saying so. After setting the
+//! displays, applying waits for the layout to stop changing, compares it with the
+//! profile like [`crate::comparison::layouts_match`] does for telling the active profile
+//! and sets the displays once more if they differ. What still differs after that fails
+//! applying like any other step.
+
+use crate::backend::DisplayBackend;
+use crate::comparison::{self, Difference};
+use crate::error::Result;
+use crate::inventory::Inventory;
+use crate::report::display_config_flag_names;
+use crate::serde_types::DisplayLayout;
+use crate::serde_types::global_config::GlobalConfig;
+use crate::win32_types::SET_DISPLAY_CONFIG_FLAGS;
+use log::{debug, info, warn};
+use serde::Serialize;
+use std::fmt;
+use std::thread;
+use std::time::{Duration, Instant};
+
+/// How often the layout is queried while waiting for it to settle.
+const SETTLE_INTERVAL: Duration = Duration::from_millis(250);
+
+/// How the displays looked after applying a profile.
+#[derive(Serialize, Debug)]
+pub struct Verification {
+    /// Whether the layout stopped changing in time, unset if applying didn't wait.
+    pub settled: Option<bool>,
+    /// How long applying waited, for both tries if the displays were set again.
+    #[serde(rename = "waited_ms", serialize_with = "crate::report::millis")]
+    pub waited: Duration,
+    /// The `SetDisplayConfig` flags the displays were set with again, unset if they
+    /// matched the first time.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub retried_with: Option<Vec<&'static str>>,
+    /// What's still different from the profile, empty if the displays match it.
+    pub differences: Vec<Difference>,
+}
+
+impl Verification {
+    pub fn is_match(&self) -> bool {
+        self.differences.is_empty()
+    }
+}
+
+impl fmt::Display for Verification {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let waited = self.waited.as_millis();
+        match self.settled {
+            Some(true) => write!(f, "The displays settled after {waited} ms")?,
+            Some(false) => write!(f, "The displays were still changing after {waited} ms")?,
+            None => write!(f, "Checked the displays without waiting for them")?,
+        }
+        if self.retried_with.is_some() {
+            write!(f, ", they were set again")?;
+        }
+        match self.differences.len() {
+            0 => write!(f, " and match the profile"),
+            1 => write!(f, ", 1 setting differs from the profile"),
+            n => write!(f, ", {n} settings differ from the profile"),
+        }
+    }
+}
+
+/// Queries the layout until two queries in a row agree or `timeout` runs out. Returns
+/// the last layout and whether it settled, unset for a `timeout` of 0.
+pub fn wait_until_settled(
+    backend: &mut dyn DisplayBackend,
+    timeout: Duration,
+) -> Result<(DisplayLayout, Option<bool>)> {
+    // Extras aren't set yet, only the displays are compared
+    let topology_only = GlobalConfig::default();
+    let mut last = backend.get_display_layout(&topology_only)?;
+    if timeout.is_zero() {
+        return Ok((last, None));
+    }
+    let started = Instant::now();
+    loop {
+        let remaining = timeout.saturating_sub(started.elapsed());
+        if remaining.is_zero() {
+            return Ok((last, Some(false)));
+        }
+        thread::sleep(SETTLE_INTERVAL.min(remaining));
+        let current = backend.get_display_layout(&topology_only)?;
+        if comparison::layouts_match(&last, &current, false) {
+            debug!(waited_ms = started.elapsed().as_millis() as u64; "Displays settled");
+            return Ok((current, Some(true)));
+        }
+        last = current;
+    }
+}
+
+/// Waits up to `timeout` for the displays to settle after setting them to `requested`
+/// and compares them with it. If they differ, sets them again once, saving them like the
+/// first time with `save`, and checks again.
+pub(crate) fn verify_topology(
+    backend: &mut dyn DisplayBackend,
+    requested: &DisplayLayout,
+    timeout: Duration,
+    save: bool,
+) -> Result<Verification> {
+    verify_with(backend, requested, timeout, |backend| {
+        backend.apply_topology(requested, save)
+    })
+}
+
+/// Like [`verify_topology`], with `set_again` setting the displays the second time, e.g.
+/// by applying the `layout.toml` that `requested` was resolved from once more.
+pub(crate) fn verify_with(
+    backend: &mut dyn DisplayBackend,
+    requested: &DisplayLayout,
+    timeout: Duration,
+    mut set_again: impl FnMut(&mut dyn DisplayBackend) -> Result<SET_DISPLAY_CONFIG_FLAGS>,
+) -> Result<Verification> {
+    let started = Instant::now();
+    let differences = |current: &DisplayLayout| {
+        comparison::compare_layouts(requested, current, false, &Inventory::default()).differences
+    };
+    let (current, settled) = wait_until_settled(backend, timeout)?;
+    let mut verification = Verification {
+        settled,
+        waited: Duration::ZERO,
+        retried_with: None,
+        differences: differences(&current),
+    };
+
+    if !verification.is_match() {
+        warn!(
+            differences = verification.differences.len();
+            "The displays don't match the profile after setting them, setting them again"
+        );
+        let flags = set_again(backend)?;
+        verification.retried_with = Some(display_config_flag_names(flags));
+        let (current, settled) = wait_until_settled(backend, timeout)?;
+        verification.settled = settled;
+        verification.differences = differences(&current);
+    }
+    verification.waited = started.elapsed();
+
+    for difference in &verification.differences {
+        warn!(difference:% = difference; "The displays don't match the profile");
+    }
+    if verification.is_match() {
+        info!(waited_ms = verification.waited.as_millis() as u64; "The displays match the profile");
+    }
+    Ok(verification)
+}
@@ -0,0 +1,21 @@
+//! The Win32 display types profiles are stored as.
+//!
+//! On Windows these are re-exported from the `windows` crate. Everywhere else the
+//! `portable` module provides layout-compatible copies of the subset womp-core needs, so
+//! profiles can be read, validated and edited on any OS.
+
+#[cfg(windows)]
+pub use windows::Win32::Devices::Display::*;
+#[cfg(windows)]
+pub use windows::Win32::Foundation::{LUID, POINTL};
+#[cfg(windows)]
+pub use windows::Win32::Graphics::Gdi::{
+    DISPLAYCONFIG_PATH_ACTIVE, DISPLAYCONFIG_PATH_MODE_IDX_INVALID,
+};
+#[cfg(windows)]
+pub use windows::core::BOOL;
+
+#[cfg(not(windows))]
+mod portable;
+#[cfg(not(windows))]
+pub use portable::*;
@@ -0,0 +1,392 @@
+#![allow(non_camel_case_types)]
+#![allow(non_snake_case)]
+
+// Mirrors of the definitions in windows::Win32::Devices::Display, windows::Win32::Foundation
+// and windows::core. Field names, layouts and derives match the originals so code using them
+// compiles unchanged on every target.
+
+macro_rules! newtype_i32 {
+    ($($name:ident),* $(,)?) => {
+        $(
+            #[repr(transparent)]
+            #[derive(Clone, Copy, Debug, Default, Eq, PartialEq)]
+            pub struct $name(pub i32);
+        )*
+    };
+}
+
+newtype_i32!(
+    DISPLAYCONFIG_DEVICE_INFO_TYPE,
+    DISPLAYCONFIG_MODE_INFO_TYPE,
+    DISPLAYCONFIG_PIXELFORMAT,
+    DISPLAYCONFIG_ROTATION,
+    DISPLAYCONFIG_SCALING,
+    DISPLAYCONFIG_SCANLINE_ORDERING,
+    DISPLAYCONFIG_VIDEO_OUTPUT_TECHNOLOGY,
+);
+
+pub const DISPLAYCONFIG_MODE_INFO_TYPE_SOURCE: DISPLAYCONFIG_MODE_INFO_TYPE =
+    DISPLAYCONFIG_MODE_INFO_TYPE(1i32);
+pub const DISPLAYCONFIG_MODE_INFO_TYPE_TARGET: DISPLAYCONFIG_MODE_INFO_TYPE =
+    DISPLAYCONFIG_MODE_INFO_TYPE(2i32);
+pub const DISPLAYCONFIG_MODE_INFO_TYPE_DESKTOP_IMAGE: DISPLAYCONFIG_MODE_INFO_TYPE =
+    DISPLAYCONFIG_MODE_INFO_TYPE(3i32);
+
+pub const DISPLAYCONFIG_PIXELFORMAT_32BPP: DISPLAYCONFIG_PIXELFORMAT =
+    DISPLAYCONFIG_PIXELFORMAT(4i32);
+
+pub const DISPLAYCONFIG_PATH_ACTIVE: u32 = 1u32;
+pub const DISPLAYCONFIG_PATH_MODE_IDX_INVALID: u32 = 4294967295u32;
+
+#[repr(transparent)]
+#[derive(Clone, Copy, Debug, Default, Eq, PartialEq)]
+pub struct SET_DISPLAY_CONFIG_FLAGS(pub u32);
+
+impl core::ops::BitOr for SET_DISPLAY_CONFIG_FLAGS {
+    type Output = Self;
+    fn bitor(self, other: Self) -> Self {
+        Self(self.0 | other.0)
+    }
+}
+
+impl core::ops::BitAnd for SET_DISPLAY_CONFIG_FLAGS {
+    type Output = Self;
+    fn bitand(self, other: Self) -> Self {
+        Self(self.0 & other.0)
+    }
+}
+
+impl core::ops::BitOrAssign for SET_DISPLAY_CONFIG_FLAGS {
+    fn bitor_assign(&mut self, other: Self) {
+        self.0.bitor_assign(other.0)
+    }
+}
+
+impl core::ops::BitAndAssign for SET_DISPLAY_CONFIG_FLAGS {
+    fn bitand_assign(&mut self, other: Self) {
+        self.0.bitand_assign(other.0)
+    }
+}
+
+impl core::ops::Not for SET_DISPLAY_CONFIG_FLAGS {
+    type Output = Self;
+    fn not(self) -> Self {
+        Self(self.0.not())
+    }
+}
+
+pub const SDC_USE_SUPPLIED_DISPLAY_CONFIG: SET_DISPLAY_CONFIG_FLAGS =
+    SET_DISPLAY_CONFIG_FLAGS(32u32);
+pub const SDC_VALIDATE: SET_DISPLAY_CONFIG_FLAGS = SET_DISPLAY_CONFIG_FLAGS(64u32);
+pub const SDC_APPLY: SET_DISPLAY_CONFIG_FLAGS = SET_DISPLAY_CONFIG_FLAGS(128u32);
+pub const SDC_NO_OPTIMIZATION: SET_DISPLAY_CONFIG_FLAGS = SET_DISPLAY_CONFIG_FLAGS(256u32);
+pub const SDC_SAVE_TO_DATABASE: SET_DISPLAY_CONFIG_FLAGS = SET_DISPLAY_CONFIG_FLAGS(512u32);
+pub const SDC_ALLOW_CHANGES: SET_DISPLAY_CONFIG_FLAGS = SET_DISPLAY_CONFIG_FLAGS(1024u32);
+
+#[repr(transparent)]
+#[derive(Clone, Copy, Debug, Default, Eq, PartialEq)]
+pub struct BOOL(pub i32);
+
+impl BOOL {
+    pub fn as_bool(self) -> bool {
+        self.0 != 0
+    }
+}
+
+impl From<bool> for BOOL {
+    fn from(value: bool) -> Self {
+        if value { BOOL(1) } else { BOOL(0) }
+    }
+}
+
+#[repr(C)]
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct LUID {
+    pub LowPart: u32,
+    pub HighPart: i32,
+}
+
+#[repr(C)]
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct POINTL {
+    pub x: i32,
+    pub y: i32,
+}
+
+#[repr(C)]
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct RECTL {
+    pub left: i32,
+    pub top: i32,
+    pub right: i32,
+    pub bottom: i32,
+}
+
+#[repr(C)]
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct DISPLAYCONFIG_RATIONAL {
+    pub Numerator: u32,
+    pub Denominator: u32,
+}
+
+#[repr(C)]
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct DISPLAYCONFIG_2DREGION {
+    pub cx: u32,
+    pub cy: u32,
+}
+
+#[repr(C)]
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct DISPLAYCONFIG_DEVICE_INFO_HEADER {
+    pub r#type: DISPLAYCONFIG_DEVICE_INFO_TYPE,
+    pub size: u32,
+    pub adapterId: LUID,
+    pub id: u32,
+}
+
+#[repr(C)]
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct DISPLAYCONFIG_ADAPTER_NAME {
+    pub header: DISPLAYCONFIG_DEVICE_INFO_HEADER,
+    pub adapterDevicePath: [u16; 128],
+}
+
+impl Default for DISPLAYCONFIG_ADAPTER_NAME {
+    fn default() -> Self {
+        unsafe { core::mem::zeroed() }
+    }
+}
+
+#[repr(C)]
+#[derive(Clone, Copy)]
+pub struct DISPLAYCONFIG_TARGET_DEVICE_NAME {
+    pub header: DISPLAYCONFIG_DEVICE_INFO_HEADER,
+    pub flags: DISPLAYCONFIG_TARGET_DEVICE_NAME_FLAGS,
+    pub outputTechnology: DISPLAYCONFIG_VIDEO_OUTPUT_TECHNOLOGY,
+    pub edidManufactureId: u16,
+    pub edidProductCodeId: u16,
+    pub connectorInstance: u32,
+    pub monitorFriendlyDeviceName: [u16; 64],
+    pub monitorDevicePath: [u16; 128],
+}
+
+impl Default for DISPLAYCONFIG_TARGET_DEVICE_NAME {
+    fn default() -> Self {
+        unsafe { core::mem::zeroed() }
+    }
+}
+
+#[repr(C)]
+#[derive(Clone, Copy)]
+pub struct DISPLAYCONFIG_TARGET_DEVICE_NAME_FLAGS {
+    pub Anonymous: DISPLAYCONFIG_TARGET_DEVICE_NAME_FLAGS_0,
+}
+
+impl Default for DISPLAYCONFIG_TARGET_DEVICE_NAME_FLAGS {
+    fn default() -> Self {
+        unsafe { core::mem::zeroed() }
+    }
+}
+
+#[repr(C)]
+#[derive(Clone, Copy)]
+pub union DISPLAYCONFIG_TARGET_DEVICE_NAME_FLAGS_0 {
+    pub Anonymous: DISPLAYCONFIG_TARGET_DEVICE_NAME_FLAGS_0_0,
+    pub value: u32,
+}
+
+impl Default for DISPLAYCONFIG_TARGET_DEVICE_NAME_FLAGS_0 {
+    fn default() -> Self {
+        unsafe { core::mem::zeroed() }
+    }
+}
+
+#[repr(C)]
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct DISPLAYCONFIG_TARGET_DEVICE_NAME_FLAGS_0_0 {
+    pub _bitfield: u32,
+}
+
+#[repr(C)]
+#[derive(Clone, Copy)]
+pub struct DISPLAYCONFIG_PATH_INFO {
+    pub sourceInfo: DISPLAYCONFIG_PATH_SOURCE_INFO,
+    pub targetInfo: DISPLAYCONFIG_PATH_TARGET_INFO,
+    pub flags: u32,
+}
+
+impl Default for DISPLAYCONFIG_PATH_INFO {
+    fn default() -> Self {
+        unsafe { core::mem::zeroed() }
+    }
+}
+
+#[repr(C)]
+#[derive(Clone, Copy)]
+pub struct DISPLAYCONFIG_PATH_SOURCE_INFO {
+    pub adapterId: LUID,
+    pub id: u32,
+    pub Anonymous: DISPLAYCONFIG_PATH_SOURCE_INFO_0,
+    pub statusFlags: u32,
+}
+
+impl Default for DISPLAYCONFIG_PATH_SOURCE_INFO {
+    fn default() -> Self {
+        unsafe { core::mem::zeroed() }
+    }
+}
+
+#[repr(C)]
+#[derive(Clone, Copy)]
+pub union DISPLAYCONFIG_PATH_SOURCE_INFO_0 {
+    pub modeInfoIdx: u32,
+    pub Anonymous: DISPLAYCONFIG_PATH_SOURCE_INFO_0_0,
+}
+
+impl Default for DISPLAYCONFIG_PATH_SOURCE_INFO_0 {
+    fn default() -> Self {
+        unsafe { core::mem::zeroed() }
+    }
+}
+
+#[repr(C)]
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct DISPLAYCONFIG_PATH_SOURCE_INFO_0_0 {
+    pub _bitfield: u32,
+}
+
+#[repr(C)]
+#[derive(Clone, Copy)]
+pub struct DISPLAYCONFIG_PATH_TARGET_INFO {
+    pub adapterId: LUID,
+    pub id: u32,
+    pub Anonymous: DISPLAYCONFIG_PATH_TARGET_INFO_0,
+    pub outputTechnology: DISPLAYCONFIG_VIDEO_OUTPUT_TECHNOLOGY,
+    pub rotation: DISPLAYCONFIG_ROTATION,
+    pub scaling: DISPLAYCONFIG_SCALING,
+    pub refreshRate: DISPLAYCONFIG_RATIONAL,
+    pub scanLineOrdering: DISPLAYCONFIG_SCANLINE_ORDERING,
+    pub targetAvailable: BOOL,
+    pub statusFlags: u32,
+}
+
+impl Default for DISPLAYCONFIG_PATH_TARGET_INFO {
+    fn default() -> Self {
+        unsafe { core::mem::zeroed() }
+    }
+}
+
+#[repr(C)]
+#[derive(Clone, Copy)]
+pub union DISPLAYCONFIG_PATH_TARGET_INFO_0 {
+    pub modeInfoIdx: u32,
+    pub Anonymous: DISPLAYCONFIG_PATH_TARGET_INFO_0_0,
+}
+
+impl Default for DISPLAYCONFIG_PATH_TARGET_INFO_0 {
+    fn default() -> Self {
+        unsafe { core::mem::zeroed() }
+    }
+}
+
+#[repr(C)]
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct DISPLAYCONFIG_PATH_TARGET_INFO_0_0 {
+    pub _bitfield: u32,
+}
+
+#[repr(C)]
+#[derive(Clone, Copy)]
+pub struct DISPLAYCONFIG_MODE_INFO {
+    pub infoType: DISPLAYCONFIG_MODE_INFO_TYPE,
+    pub id: u32,
+    pub adapterId: LUID,
+    pub Anonymous: DISPLAYCONFIG_MODE_INFO_0,
+}
+
+impl Default for DISPLAYCONFIG_MODE_INFO {
+    fn default() -> Self {
+        unsafe { core::mem::zeroed() }
+    }
+}
+
+#[repr(C)]
+#[derive(Clone, Copy)]
+pub union DISPLAYCONFIG_MODE_INFO_0 {
+    pub targetMode: DISPLAYCONFIG_TARGET_MODE,
+    pub sourceMode: DISPLAYCONFIG_SOURCE_MODE,
+    pub desktopImageInfo: DISPLAYCONFIG_DESKTOP_IMAGE_INFO,
+}
+
+impl Default for DISPLAYCONFIG_MODE_INFO_0 {
+    fn default() -> Self {
+        unsafe { core::mem::zeroed() }
+    }
+}
+
+#[repr(C)]
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct DISPLAYCONFIG_SOURCE_MODE {
+    pub width: u32,
+    pub height: u32,
+    pub pixelFormat: DISPLAYCONFIG_PIXELFORMAT,
+    pub position: POINTL,
+}
+
+#[repr(C)]
+#[derive(Clone, Copy)]
+pub struct DISPLAYCONFIG_TARGET_MODE {
+    pub targetVideoSignalInfo: DISPLAYCONFIG_VIDEO_SIGNAL_INFO,
+}
+
+impl Default for DISPLAYCONFIG_TARGET_MODE {
+    fn default() -> Self {
+        unsafe { core::mem::zeroed() }
+    }
+}
+
+#[repr(C)]
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct DISPLAYCONFIG_DESKTOP_IMAGE_INFO {
+    pub PathSourceSize: POINTL,
+    pub DesktopImageRegion: RECTL,
+    pub DesktopImageClip: RECTL,
+}
+
+#[repr(C)]
+#[derive(Clone, Copy)]
+pub struct DISPLAYCONFIG_VIDEO_SIGNAL_INFO {
+    pub pixelRate: u64,
+    pub hSyncFreq: DISPLAYCONFIG_RATIONAL,
+    pub vSyncFreq: DISPLAYCONFIG_RATIONAL,
+    pub activeSize: DISPLAYCONFIG_2DREGION,
+    pub totalSize: DISPLAYCONFIG_2DREGION,
+    pub Anonymous: DISPLAYCONFIG_VIDEO_SIGNAL_INFO_0,
+    pub scanLineOrdering: DISPLAYCONFIG_SCANLINE_ORDERING,
+}
+
+impl Default for DISPLAYCONFIG_VIDEO_SIGNAL_INFO {
+    fn default() -> Self {
+        unsafe { core::mem::zeroed() }
+    }
+}
+
+#[repr(C)]
+#[derive(Clone, Copy)]
+pub union DISPLAYCONFIG_VIDEO_SIGNAL_INFO_0 {
+    pub AdditionalSignalInfo: DISPLAYCONFIG_VIDEO_SIGNAL_INFO_0_0,
+    pub videoStandard: u32,
+}
+
+impl Default for DISPLAYCONFIG_VIDEO_SIGNAL_INFO_0 {
+    fn default() -> Self {
+        unsafe { core::mem::zeroed() }
+    }
+}
+
+#[repr(C)]
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct DISPLAYCONFIG_VIDEO_SIGNAL_INFO_0_0 {
+    pub _bitfield: u32,
+}
@@ -1,6 +1,22 @@
 use serde::{Deserialize, Serialize};
-use tauri::command;
+use std::path::Path;
+use std::sync::Mutex;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::thread;
+use std::time::Duration;
+use tauri::{AppHandle, Emitter, Manager, command};
+use womp_core::comparison::ProfileMatch;
+use womp_core::error::WompError;
+use womp_core::inventory::InventoryMonitor;
+use womp_core::plan::Plan;
+use womp_core::report::ApplyReport;
 use womp_core::serde_types::{config::Config, global_config::GlobalConfig};
+use womp_core::snapshot::Snapshot;
+
+/// The layout from before the last applied profile until the new one is confirmed,
+/// with the id of the timer that restores it.
+static UNCONFIRMED: Mutex<Option<(u64, Snapshot)>> = Mutex::new(None);
+static NEXT_TIMER: AtomicU64 = AtomicU64::new(0);
 
 #[derive(Serialize, Deserialize)]
 pub struct Profile {
@@ -9,8 +25,8 @@ pub struct Profile {
 }
 
 #[command]
-pub fn get_profiles() -> Result<Vec<Profile>, String> {
-    let profiles = womp_core::config_manager::get_profiles_and_configs().unwrap();
+pub fn get_profiles() -> Result<Vec<Profile>, WompError> {
+    let profiles = womp_core::config_manager::get_profiles_and_configs()?;
     let profiles = profiles
         .into_iter()
         .map(|(name, config)| Profile { name, config })
@@ -19,116 +35,226 @@ pub fn get_profiles() -> Result<Vec<Profile>, String> {
 }
 
 #[command]
-pub fn get_active_profile() -> Result<Option<String>, String> {
-    let global_config = get_global_config().unwrap();
+pub fn get_active_profile() -> Result<Option<String>, WompError> {
+    let global_config = get_global_config()?;
     womp_core::get_active_profile(&global_config)
 }
 
+/// The active profile, or the one closest to the current layout and how it differs.
+#[command]
+pub fn get_closest_profile() -> Result<Option<ProfileMatch>, WompError> {
+    let global_config = get_global_config()?;
+    womp_core::get_closest_profile(&global_config)
+}
+
+/// Cycling starts from the closest profile, so it works even if none is active.
+fn closest_profile_index(profiles: &[Profile]) -> Result<Option<usize>, WompError> {
+    let closest_profile = get_closest_profile()?;
+    Ok(closest_profile.and_then(|closest| profiles.iter().position(|p| p.name == closest.profile)))
+}
+
+/// Every monitor a profile has captured.
+#[command]
+pub fn get_monitors() -> Result<Vec<InventoryMonitor>, WompError> {
+    Ok(womp_core::get_monitor_inventory()?.monitors)
+}
+
 #[command]
-pub fn next_profile() -> Result<(), String> {
-    let profiles = get_profiles().unwrap();
-    let active_profile = get_active_profile().unwrap();
-    if let Some(active_profile) = active_profile {
-        let active_profile_index = profiles
-            .iter()
-            .position(|p| p.name == active_profile)
-            .unwrap();
-        let next_profile_index = (active_profile_index + 1) % profiles.len();
+pub fn set_monitor_alias(monitor: String, alias: String) -> Result<(), WompError> {
+    womp_core::set_monitor_alias(&monitor, &alias)
+}
+
+#[command]
+pub fn next_profile(app: AppHandle) -> Result<Option<ApplyReport>, WompError> {
+    let profiles = get_profiles()?;
+    let current_profile_index = closest_profile_index(&profiles)?;
+    if let Some(current_profile_index) = current_profile_index {
+        let next_profile_index = (current_profile_index + 1) % profiles.len();
         let next_profile = profiles[next_profile_index].name.clone();
-        apply_display_layout(next_profile)
+        apply_display_layout(app, next_profile).map(Some)
     } else {
-        Ok(())
+        Ok(None)
     }
 }
 
 #[command]
-pub fn previous_profile() -> Result<(), String> {
-    let profiles = get_profiles().unwrap();
-    let active_profile = get_active_profile().unwrap();
-    if let Some(active_profile) = active_profile {
-        let active_profile_index = profiles
-            .iter()
-            .position(|p| p.name == active_profile)
-            .unwrap();
-        let previous_profile_index = (active_profile_index + profiles.len() - 1) % profiles.len();
+pub fn previous_profile(app: AppHandle) -> Result<Option<ApplyReport>, WompError> {
+    let profiles = get_profiles()?;
+    let current_profile_index = closest_profile_index(&profiles)?;
+    if let Some(current_profile_index) = current_profile_index {
+        let previous_profile_index = (current_profile_index + profiles.len() - 1) % profiles.len();
         let previous_profile = profiles[previous_profile_index].name.clone();
-        apply_display_layout(previous_profile)
+        apply_display_layout(app, previous_profile).map(Some)
     } else {
-        Ok(())
+        Ok(None)
+    }
+}
+
+#[command]
+pub fn get_global_config() -> Result<GlobalConfig, WompError> {
+    womp_core::get_global_config()
+}
+
+#[command]
+pub fn set_global_config(global_config: GlobalConfig) -> Result<(), WompError> {
+    womp_core::set_global_config(&global_config)?;
+    womp_core::logging::set_level(global_config.log_level);
+    Ok(())
+}
+
+#[command]
+pub fn apply_display_layout(
+    app: AppHandle,
+    profile_name: String,
+) -> Result<ApplyReport, WompError> {
+    let global_config = get_global_config()?;
+    if global_config.confirm_timeout == 0 {
+        return womp_core::apply_display_layout(&profile_name, &global_config);
+    }
+    match womp_core::apply_display_layout_unconfirmed(&profile_name, &global_config) {
+        Ok((report, snapshot)) => {
+            ask_to_confirm(&app, snapshot, global_config.confirm_timeout);
+            Ok(report)
+        }
+        // The displays were changed before a later step failed, they still need confirming
+        Err(WompError::ApplyFailed {
+            report,
+            snapshot: Some(snapshot),
+        }) => {
+            ask_to_confirm(&app, *snapshot, global_config.confirm_timeout);
+            Err(WompError::ApplyFailed {
+                report,
+                snapshot: None,
+            })
+        }
+        Err(e) => Err(e),
+    }
+}
+
+/// Shows the "Keep these display settings?" dialog and restores `snapshot` if it isn't
+/// answered within `seconds`. Applying another profile in the meantime keeps the layout
+/// that was waiting for confirmation.
+fn ask_to_confirm(app: &AppHandle, snapshot: Snapshot, seconds: u32) {
+    let timer = NEXT_TIMER.fetch_add(1, Ordering::Relaxed);
+    *UNCONFIRMED.lock().unwrap() = Some((timer, snapshot));
+
+    if let Some(dialog) = app.get_webview_window("dialog") {
+        let _ = dialog.emit("dialogType", format!("confirm-layout-{seconds}"));
+        let _ = dialog.show();
+        let _ = dialog.set_focus();
     }
+
+    let app = app.clone();
+    thread::spawn(move || {
+        thread::sleep(Duration::from_secs(seconds.into()));
+        let unconfirmed = UNCONFIRMED.lock().unwrap().take_if(|(id, _)| *id == timer);
+        let Some((_, snapshot)) = unconfirmed else {
+            return;
+        };
+        log::info!("Profile `{}` wasn't confirmed, reverting", snapshot.profile());
+        if let Err(e) = womp_core::revert_display_layout(&snapshot) {
+            log::error!("Failed to restore the previous layout: {}", e);
+        }
+        if let Some(dialog) = app.get_webview_window("dialog") {
+            let _ = dialog.hide();
+        }
+        let _ = app.emit_to("main", "event", "profiles_updated");
+    });
 }
 
+/// Keeps the layout of the profile that was just applied.
 #[command]
-pub fn get_global_config() -> Result<GlobalConfig, String> {
-    Ok(womp_core::get_global_config())
+pub fn confirm_display_layout() -> Result<(), WompError> {
+    let unconfirmed = UNCONFIRMED.lock().unwrap().take();
+    match unconfirmed {
+        Some((_, snapshot)) => womp_core::confirm_display_layout(&snapshot),
+        None => Ok(()),
+    }
 }
 
+/// Restores the layout from before the profile that was just applied.
 #[command]
-pub fn set_global_config(global_config: GlobalConfig) -> Result<(), String> {
-    womp_core::set_global_config(&global_config)
+pub fn revert_display_layout() -> Result<(), WompError> {
+    let unconfirmed = UNCONFIRMED.lock().unwrap().take();
+    match unconfirmed {
+        Some((_, snapshot)) => womp_core::revert_display_layout(&snapshot),
+        None => Ok(()),
+    }
 }
 
+/// What applying the profile would change, without applying it.
 #[command]
-pub fn apply_display_layout(profile_name: String) -> Result<(), String> {
-    let global_config = get_global_config().unwrap();
-    womp_core::apply_display_layout(&profile_name, &global_config, false)
+pub fn plan_display_layout(profile_name: String) -> Result<Plan, WompError> {
+    let global_config = get_global_config()?;
+    womp_core::plan_display_layout(&profile_name, &global_config)
 }
 
 #[command]
-pub fn save_current_display_layout(profile_name: String) -> Result<(), String> {
-    let global_config = get_global_config().unwrap();
-    womp_core::save_current_display_layout(&profile_name, &global_config, false)
+pub fn save_current_display_layout(profile_name: String) -> Result<(), WompError> {
+    let global_config = get_global_config()?;
+    womp_core::save_current_display_layout(&profile_name, &global_config)
 }
 
 #[command]
-pub fn get_config_dir() -> Result<String, String> {
-    let config_dir = womp_core::config_manager::get_config_dir().unwrap();
+pub fn get_config_dir() -> Result<String, WompError> {
+    let config_dir = womp_core::config_manager::get_config_dir()?;
     Ok(config_dir.to_string_lossy().to_string())
 }
 
 #[command]
-pub fn get_profiles_dir() -> Result<String, String> {
-    let profiles_dir = womp_core::config_manager::get_profiles_dir().unwrap();
+pub fn get_profiles_dir() -> Result<String, WompError> {
+    let profiles_dir = womp_core::config_manager::get_profiles_dir()?;
     Ok(profiles_dir.to_string_lossy().to_string())
 }
 
 #[command]
-pub fn get_profile_dir(profile_name: String) -> Result<String, String> {
-    let profile_dir = womp_core::config_manager::get_profile_dir(&profile_name).unwrap();
+pub fn get_profile_dir(profile_name: String) -> Result<String, WompError> {
+    let profile_dir = womp_core::config_manager::get_profile_dir(&profile_name)?;
     Ok(profile_dir.to_string_lossy().to_string())
 }
 
 #[command]
-pub fn read_display_config(profile_name: String) -> Result<Config, String> {
+pub fn read_display_config(profile_name: String) -> Result<Config, WompError> {
     womp_core::config_manager::read_display_config(&profile_name)
 }
 
 #[command]
-pub fn write_display_config(config: Config, profile_name: String) -> Result<(), String> {
+pub fn write_display_config(config: Config, profile_name: String) -> Result<(), WompError> {
     womp_core::config_manager::write_display_config(&config, &profile_name)
 }
 
 #[command]
-pub fn rename_profile(old_name: String, new_name: String) -> Result<(), String> {
+pub fn rename_profile(old_name: String, new_name: String) -> Result<(), WompError> {
     womp_core::config_manager::rename_profile_folder(&old_name, &new_name)
 }
 
 #[command]
-pub fn delete_profile(profile_name: String) -> Result<(), String> {
+pub fn delete_profile(profile_name: String) -> Result<(), WompError> {
     womp_core::config_manager::delete_profile_dir(&profile_name)
 }
 
 #[command]
-pub fn clone_profile(profile_name: String) -> Result<String, String> {
+pub fn clone_profile(profile_name: String) -> Result<String, WompError> {
     womp_core::config_manager::clone_profile_dir(&profile_name)
 }
 
 #[command]
-pub fn open_profile_dir(profile_name: String) -> Result<(), String> {
+pub fn export_profile(profile_name: String, destination: String) -> Result<(), WompError> {
+    womp_core::config_manager::export_profile_bundle(&profile_name, Path::new(&destination))?;
+    Ok(())
+}
+
+#[command]
+pub fn import_profile(source: String) -> Result<String, WompError> {
+    womp_core::config_manager::import_profile_bundle(Path::new(&source))
+}
+
+#[command]
+pub fn open_profile_dir(profile_name: String) -> Result<(), WompError> {
     womp_core::config_manager::open_profile_dir(&profile_name)
 }
 
 #[command]
-pub fn turn_off_all_displays() -> Result<(), String> {
-    womp_core::turn_off_all_displays(false)
+pub fn turn_off_all_displays() -> Result<(), WompError> {
+    womp_core::turn_off_all_displays()
 }
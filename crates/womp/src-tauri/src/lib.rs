@@ -13,6 +13,7 @@ use tauri_plugin_autostart::MacosLauncher;
 use tauri_plugin_updater::UpdaterExt;
 use time;
 use window_vibrancy::*;
+use womp_core::logging::LogTarget;
 use windows::Foundation::TypedEventHandler;
 use windows::UI::ViewManagement::{UIColorType, UISettings};
 
@@ -83,6 +84,8 @@ pub fn run() {
         );
     }
 
+    setup_logging();
+
     tauri::Builder::default()
         .plugin(tauri_plugin_updater::Builder::new().build())
         .plugin(tauri_plugin_dialog::init())
@@ -93,7 +96,7 @@ pub fn run() {
         ))
         .plugin(tauri_plugin_process::init())
         .plugin(tauri_plugin_single_instance::init(|app, argv, cwd| {
-            println!(
+            log::info!(
                 "Attempted to launch second instance with args: {:?} in {:?}",
                 argv, cwd
             );
@@ -101,21 +104,21 @@ pub fn run() {
             // Focus the main window when a second instance is launched
             if let Some(main_window) = app.get_webview_window("main") {
                 if let Err(e) = main_window.set_focus() {
-                    eprintln!("Failed to focus main window: {}", e);
+                    log::error!("Failed to focus main window: {}", e);
                 }
 
                 if let Err(e) = main_window.unminimize() {
-                    eprintln!("Failed to unminimize main window: {}", e);
+                    log::error!("Failed to unminimize main window: {}", e);
                 }
 
                 if let Err(e) = main_window.show() {
-                    eprintln!("Failed to show main window: {}", e);
+                    log::error!("Failed to show main window: {}", e);
                 }
 
                 // Optionally notify the user
                 app.emit_to("main", "second-instance", argv)
                     .unwrap_or_else(|e| {
-                        eprintln!("Failed to emit second-instance event: {}", e);
+                        log::error!("Failed to emit second-instance event: {}", e);
                     });
             }
         }))
@@ -173,9 +176,15 @@ pub fn run() {
             download_and_install_update,
             restart_app,
             external::apply_display_layout,
+            external::confirm_display_layout,
+            external::revert_display_layout,
+            external::plan_display_layout,
             external::save_current_display_layout,
             external::get_profiles,
             external::get_active_profile,
+            external::get_closest_profile,
+            external::get_monitors,
+            external::set_monitor_alias,
             external::next_profile,
             external::previous_profile,
             external::get_config_dir,
@@ -186,6 +195,8 @@ pub fn run() {
             external::rename_profile,
             external::delete_profile,
             external::clone_profile,
+            external::export_profile,
+            external::import_profile,
             external::open_profile_dir,
             external::turn_off_all_displays,
             external::get_global_config,
@@ -196,12 +207,23 @@ pub fn run() {
         .expect("error while running tauri application");
 }
 
+fn setup_logging() {
+    // The tray app has no console, so everything goes to the log file in the config directory
+    let log_level = womp_core::get_global_config()
+        .map(|global_config| global_config.log_level)
+        .unwrap_or_default();
+    let result = womp_core::logging::log_file_path()
+        .and_then(|path| womp_core::logging::init(LogTarget::File(path), log_level));
+    if let Err(e) = result {
+        eprintln!("Failed to set up logging: {e}");
+    }
+}
+
 fn setup_profiles_dir_watcher(app_handle: AppHandle) {
     // Create a thread-local watcher to avoid dropping it
     std::thread::spawn(move || {
-        // Get the config directory and join with "WOMP/profiles"
-        if let Ok(config_dir) = app_handle.path().config_dir() {
-            let profiles_dir = config_dir.join("WOMP").join("profiles");
+        // Use the same profiles directory womp-core resolves (env override, portable mode, ...)
+        if let Ok(profiles_dir) = womp_core::config_manager::get_profiles_dir() {
 
             // Create the debounced event handler
             let event_handler = move |res: DebounceEventResult| {
@@ -212,11 +234,11 @@ fn setup_profiles_dir_watcher(app_handle: AppHandle) {
                             app_handle
                                 .emit_to("main", "event", "profiles_updated")
                                 .unwrap_or_else(|e| {
-                                    eprintln!("Failed to emit profiles_updated event: {}", e);
+                                    log::error!("Failed to emit profiles_updated event: {}", e);
                                 });
                         }
                     }
-                    Err(e) => eprintln!("Watch error: {:?}", e),
+                    Err(e) => log::error!("Watch error: {:?}", e),
                 }
             };
 
@@ -227,16 +249,16 @@ fn setup_profiles_dir_watcher(app_handle: AppHandle) {
                     if let Err(e) =
                         debouncer.watch(Path::new(&profiles_dir), RecursiveMode::Recursive)
                     {
-                        eprintln!("Failed to watch profiles directory: {}", e);
+                        log::error!("Failed to watch profiles directory: {}", e);
                     } else {
                         // Keep the watcher alive
                         std::thread::park();
                     }
                 }
-                Err(e) => eprintln!("Failed to create debounced watcher: {}", e),
+                Err(e) => log::error!("Failed to create debounced watcher: {}", e),
             }
         } else {
-            eprintln!("Failed to get app config directory");
+            log::error!("Failed to get profiles directory");
         }
     });
 }
@@ -1,11 +1,12 @@
 import { getCurrentWindow } from "@tauri-apps/api/window";
 import { useEffect, useState } from "react";
+import ConfirmLayoutDialog from "./dialogs/ConfirmLayoutDialog";
 import DeleteProfileDialog from "./dialogs/DeleteProfileDialog";
 import NewProfileDialog from "./dialogs/NewProfileDialog";
 import OverwriteProfileDialog from "./dialogs/OverwriteProfileDialog";
 import { Titlebar } from "./components/Titlebar";
 import { useDialogStyles } from "./styles/dialog";
-type DialogType = "new-profile" | "save-profile" | "delete-profile" | null;
+type DialogType = "new-profile" | "save-profile" | "delete-profile" | "confirm-layout" | null;
 
 function Dialog() {
   const classes = useDialogStyles();
@@ -31,6 +32,11 @@ function Dialog() {
           setDialogType("delete-profile");
           window.setTitle("Delete Profile");
           window.emit("event", "title-changed");
+        } else if ((e.payload as string).startsWith("confirm-layout-")) {
+          setPayload((e.payload as string).split("-").pop());
+          setDialogType("confirm-layout");
+          window.setTitle("Keep Display Settings");
+          window.emit("event", "title-changed");
         } else {
           setDialogType(null);
         }
@@ -55,6 +61,8 @@ function Dialog() {
           <OverwriteProfileDialog profileName={payload} />
         ) : dialogType === "delete-profile" ? (
           <DeleteProfileDialog profileName={payload} />
+        ) : dialogType === "confirm-layout" ? (
+          <ConfirmLayoutDialog seconds={Number(payload)} />
         ) : null}
       </main>
     </>
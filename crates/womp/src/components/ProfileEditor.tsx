@@ -1,6 +1,7 @@
 import { ProfileName } from "@/components/ProfileName";
 import { useProfileStore } from "@/lib/profileStore";
-import type { Config, Profile, Run, RunCommand } from "@/lib/types";
+import { notify, notifyApplied } from "@/lib/notification";
+import type { ApplyReport, Config, Profile, Run, RunCommand } from "@/lib/types";
 import { useProfileEditorStyles } from "@/styles/profileEditor";
 import data from "@emoji-mart/data";
 import Picker from "@emoji-mart/react";
@@ -148,9 +149,13 @@ export function ProfileEditor() {
         profiles.find((profile) => profile.name === tempProfile.name) ?? null,
       );
     }
-    await invoke("apply_display_layout", {
-      profileName: tempProfile?.name || selectedProfile?.name || "",
-    });
+    const profileName = tempProfile?.name || selectedProfile?.name || "";
+    try {
+      const report = await invoke<ApplyReport>("apply_display_layout", { profileName });
+      notifyApplied(tempProfile?.config?.name || profileName, report);
+    } catch (error) {
+      notify("WOMP", `Failed to apply profile "${profileName}": ${error}`);
+    }
   };
 
   const handleEmojiSelect = (emojiData: EmojiData, _: Event) => {
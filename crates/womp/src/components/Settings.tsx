@@ -147,6 +147,12 @@ export function Settings() {
       case "save_audio_output":
         setGlobalConfig({ ...globalConfig, save_audio_output: data.checked });
         break;
+      case "canonical_layouts":
+        setGlobalConfig({ ...globalConfig, canonical_layouts: data.checked });
+        break;
+      case "confirm_timeout":
+        setGlobalConfig({ ...globalConfig, confirm_timeout: data.checked ? 15 : 0 });
+        break;
       case "auto_update":
         setGlobalConfig({ ...globalConfig, auto_update: data.checked });
         break;
@@ -204,6 +210,21 @@ export function Settings() {
           }
           description="Run before/after commands defined in profile config"
         />
+        <Card
+          header="Confirm new layouts"
+          icon={"\uE8FB"}
+          control={
+            <Switch
+              className={classes.switch}
+              id="confirm_timeout"
+              checked={globalConfig.confirm_timeout > 0}
+              onChange={handleOptionToggle}
+              label={globalConfig.confirm_timeout > 0 ? "On" : "Off"}
+              labelPosition="before"
+            />
+          }
+          description="Restore the previous layout unless a profile is confirmed within 15 seconds"
+        />
         <Card
           header="Auto update"
           icon={"\uE895"}
@@ -314,6 +335,21 @@ export function Settings() {
           }
           description="Store the default audio output for each profile"
         />
+        <Card
+          header="Diff-friendly Profiles"
+          icon={"\uE943"}
+          control={
+            <Switch
+              id="canonical_layouts"
+              className={classes.switch}
+              checked={globalConfig.canonical_layouts}
+              onChange={handleOptionToggle}
+              label={globalConfig.canonical_layouts ? "On" : "Off"}
+              labelPosition="before"
+            />
+          }
+          description="Save layouts in a stable form that only changes when the layout does"
+        />
       </div>
       <div className={classes.section}>
         <Label>About</Label>
@@ -0,0 +1,72 @@
+import { useDialogStyles } from "@/styles/dialog";
+import { Button, Text } from "@fluentui/react-components";
+import { invoke } from "@tauri-apps/api/core";
+import { getCurrentWindow } from "@tauri-apps/api/window";
+import { useCallback, useEffect, useState } from "react";
+
+function ConfirmLayoutDialog({ seconds }: { seconds: number }) {
+  const classes = useDialogStyles();
+  const [secondsLeft, setSecondsLeft] = useState(seconds);
+
+  const handleClose = async () => {
+    try {
+      const appWindow = getCurrentWindow();
+      await appWindow.hide();
+    } catch (error) {
+      console.error("Failed to close window:", error);
+    }
+    invoke("emit_to_window", {
+      windowName: "main",
+      event: "event",
+      payload: "profiles_updated",
+    });
+  };
+
+  const handleKeep = async () => {
+    try {
+      await invoke("confirm_display_layout");
+    } catch (error) {
+      console.error("Failed to save the new layout:", error);
+    }
+    handleClose();
+  };
+
+  const handleRevert = useCallback(async () => {
+    await invoke("revert_display_layout");
+    handleClose();
+  }, []);
+
+  // The previous layout is restored by the app when time runs out, this only counts down
+  useEffect(() => {
+    setSecondsLeft(seconds);
+    const timer = setInterval(() => {
+      setSecondsLeft((left) => Math.max(left - 1, 0));
+    }, 1000);
+    return () => clearInterval(timer);
+  }, [seconds]);
+
+  useEffect(() => {
+    const handleKeyDown = (event: KeyboardEvent) => {
+      if (event.key === 'Escape') {
+        handleRevert();
+      }
+    };
+
+    window.addEventListener('keydown', handleKeyDown);
+    return () => {
+      window.removeEventListener('keydown', handleKeyDown);
+    };
+  }, [handleRevert]);
+
+  return (
+    <div className={classes.content}>
+      <Text>Keep these display settings? Reverting to the previous layout in {secondsLeft} seconds.</Text>
+      <div className={classes.buttonContainer}>
+        <Button appearance="primary" onClick={handleKeep}>Keep changes</Button>
+        <Button appearance="subtle" className={classes.controlButton} onClick={handleRevert}>Revert</Button>
+      </div>
+    </div>
+  )
+}
+
+export default ConfirmLayoutDialog;
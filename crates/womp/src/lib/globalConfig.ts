@@ -4,6 +4,8 @@ import { create } from "zustand";
 
 export type ThemeOption = "system" | "dark" | "light";
 
+export type LogLevel = "off" | "error" | "warn" | "info" | "debug" | "trace";
+
 export interface GlobalConfig {
   autostart: boolean;
   auto_update: boolean;
@@ -16,6 +18,10 @@ export interface GlobalConfig {
   save_sdr_white_level: boolean;
   save_wallpaper_info: boolean;
   save_audio_output: boolean;
+  log_level: LogLevel;
+  canonical_layouts: boolean;
+  confirm_timeout: number;
+  settle_timeout_ms: number;
 }
 
 interface GlobalConfigStore {
@@ -37,6 +43,10 @@ export const useGlobalConfigStore = create<GlobalConfigStore>((set) => ({
     save_sdr_white_level: false,
     save_wallpaper_info: false,
     save_audio_output: false,
+    log_level: "info",
+    canonical_layouts: false,
+    confirm_timeout: 0,
+    settle_timeout_ms: 3000,
   },
   getGlobalConfig: async () => {
     const globalConfig = await invoke<GlobalConfig>("get_global_config");
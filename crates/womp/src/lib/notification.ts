@@ -3,6 +3,7 @@ import {
   requestPermission,
   sendNotification,
 } from '@tauri-apps/plugin-notification';
+import { ApplyReport } from './types';
 
 export async function notify(title: string, body?: string) {
   let permissionGranted = await isPermissionGranted();
@@ -13,4 +14,20 @@ export async function notify(title: string, body?: string) {
   if (permissionGranted) {
     sendNotification({ title, body });
   }
-}
\ No newline at end of file
+}
+/** Tells what applying a profile did, e.g. "3 settings changed, 1 already set". */
+export function notifyApplied(profileName: string, report: ApplyReport) {
+  const count = (status: string) => report.steps.filter((step) => step.status === status).length;
+  const details = [`${count("applied")} settings changed`];
+  if (count("unchanged") > 0) {
+    details.push(`${count("unchanged")} already set`);
+  }
+  for (const hook of report.hooks.filter((hook) => hook.exit_code !== 0)) {
+    if (hook.error) {
+      details.push(`the ${hook.stage} hook couldn't be run`);
+    } else {
+      details.push(`the ${hook.stage} hook exited with ${hook.exit_code ?? "no exit code"}`);
+    }
+  }
+  notify("WOMP", `Profile "${profileName}" applied: ${details.join(", ")}`);
+}
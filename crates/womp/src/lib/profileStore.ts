@@ -1,9 +1,10 @@
-import type { Profile } from "@/lib/types";
+import type { Profile, ProfileMatch } from "@/lib/types";
 import { invoke } from "@tauri-apps/api/core";
 import { create } from "zustand";
 
 interface ProfileStore {
   activeProfile: string | null;
+  closestProfile: ProfileMatch | null;
   profiles: Profile[];
   selectedProfile: Profile | "settings" | null;
   initialized: boolean;
@@ -14,6 +15,7 @@ interface ProfileStore {
 
 export const useProfileStore = create<ProfileStore>((set, _) => ({
   activeProfile: null,
+  closestProfile: null,
   profiles: [],
   selectedProfile: null,
   initialized: false,
@@ -23,8 +25,12 @@ export const useProfileStore = create<ProfileStore>((set, _) => ({
   initProfiles: async () => {
     const profiles: Profile[] = await invoke("get_profiles");
     set({ profiles });
-    const activeProfile: string | null = await invoke("get_active_profile");
-    set({ activeProfile, initialized: true });
+    const closestProfile: ProfileMatch | null = await invoke("get_closest_profile");
+    const activeProfile =
+      closestProfile && closestProfile.differences.length === 0
+        ? closestProfile.profile
+        : null;
+    set({ activeProfile, closestProfile, initialized: true });
     return profiles;
   }
 }));
@@ -4,10 +4,29 @@ export interface Profile {
 }
 
 export interface Config {
+  version: number | undefined;
   name: string | undefined;
   description: string | undefined;
+  extends: string | undefined;
   icon: string | undefined;
   run: Run | undefined;
+  scope: Scope | undefined;
+  on_failure: OnFailure | undefined;
+}
+
+export type OnFailure = "rollback" | "best_effort";
+
+export type Extra =
+  | "dpi_scale"
+  | "hdr_state"
+  | "sdr_white_level"
+  | "icon_size"
+  | "wallpaper_info"
+  | "audio_output";
+
+export interface Scope {
+  displays: string[] | undefined;
+  extras: Extra[] | undefined;
 }
 
 export interface Run {
@@ -18,4 +37,138 @@ export interface Run {
 export interface RunCommand {
   target: string | undefined;
   args: string | undefined;
-}
\ No newline at end of file
+}
+export type Setting =
+  | "enabled"
+  | "resolution"
+  | "refresh_rate"
+  | "position"
+  | "rotation"
+  | "dpi_scale"
+  | "hdr"
+  | "sdr_white_level"
+  | "icon_size"
+  | "wallpaper"
+  | "audio_output";
+
+export interface Difference {
+  display: string | undefined;
+  setting: Setting;
+  profile: string;
+  current: string;
+}
+
+export interface ProfileMatch {
+  profile: string;
+  partial: boolean;
+  score: number;
+  differences: Difference[];
+}
+
+export type Change =
+  | { action: "run_hook"; stage: "before" | "after"; command: string }
+  | { action: "turn_off"; display: string }
+  | { action: "turn_on"; display: string; to: string }
+  | {
+      action: "set";
+      display: string | undefined;
+      setting: Setting;
+      from: string | undefined;
+      to: string;
+    };
+
+export interface Plan {
+  profile: string;
+  changes: Change[];
+}
+
+export type StepReport = { step: string; duration_ms: number } & (
+  | { status: "applied" }
+  | { status: "unchanged" }
+  | { status: "disabled" }
+  | { status: "failed"; error: string }
+);
+
+export interface HookReport {
+  stage: "before" | "after";
+  command: string;
+  exit_code: number | null;
+  stdout: string;
+  stderr: string;
+  /** Why the command couldn't be started, only set if it couldn't. */
+  error?: string;
+  duration_ms: number;
+}
+
+export interface StepFailure {
+  step: string;
+  error: string;
+}
+
+export type AfterFailure =
+  | { outcome: "kept_going" }
+  | { outcome: "rolled_back" }
+  | { outcome: "rollback_failed"; failures: StepFailure[] };
+
+export interface Verification {
+  settled: boolean | null;
+  waited_ms: number;
+  retried_with: string[] | undefined;
+  differences: Difference[];
+}
+
+export interface ApplyReport {
+  profile: string;
+  hooks: HookReport[];
+  steps: StepReport[];
+  display_config_flags: string[] | undefined;
+  verification: Verification | undefined;
+  after: AfterFailure | undefined;
+  duration_ms: number;
+}
+
+export interface InventoryMonitor {
+  id: number;
+  alias: string | undefined;
+  name: string;
+  device_path: string;
+  manufacturer_id: number;
+  product_code: number;
+  connector_instance: number;
+  output_technology: OutputTechnology;
+  edid: EdidInfo | undefined;
+  target_id: number;
+  first_seen: string;
+  last_seen: string;
+  profiles: string[];
+}
+
+export interface EdidTiming {
+  width: number;
+  height: number;
+  refreshRate: number;
+}
+
+export interface EdidInfo {
+  manufacturer: string;
+  vendor: string | undefined;
+  productCode: number;
+  version: string | undefined;
+  serialNumber: number | undefined;
+  serial: string | undefined;
+  manufactureWeek: number | undefined;
+  manufactureYear: number | undefined;
+  modelYear: number | undefined;
+  size: { widthCm: number; heightCm: number } | undefined;
+  nativeMode: EdidTiming | undefined;
+  supportedTimings: EdidTiming[] | undefined;
+}
+
+export type OutputTechnology =
+  | "hd15"
+  | "dvi"
+  | "hdmi"
+  | "displayPort"
+  | "internal"
+  | "miracast"
+  | { other: number };
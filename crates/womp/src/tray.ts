@@ -1,6 +1,6 @@
 import { glyphToImage } from '@/lib/glyphToImage';
-import { notify } from '@/lib/notification';
-import { Profile } from '@/lib/types';
+import { notify, notifyApplied } from '@/lib/notification';
+import { ApplyReport, Profile } from '@/lib/types';
 import { invoke } from '@tauri-apps/api/core';
 import { Image } from '@tauri-apps/api/image';
 import { IconMenuItem, Menu, PredefinedMenuItem, Submenu } from '@tauri-apps/api/menu';
@@ -14,9 +14,11 @@ type ProfileAction = "apply" | "save" | "delete";
 async function createProfileMenuItems(profiles: Profile[], activeProfile: string | null, action: ProfileAction) {
     const actionHandlers = {
         apply: (profile: Profile) => () => {
-            invoke("apply_display_layout", { profileName: profile.name });
+            const profileName = profile.config?.name ?? profile.name;
+            invoke<ApplyReport>("apply_display_layout", { profileName: profile.name })
+                .then((report) => notifyApplied(profileName, report))
+                .catch((error) => notify("WOMP", `Failed to apply profile "${profileName}": ${error}`));
             setTimeout(() => {
-                notify("WOMP", `Profile "${profile.config?.name ?? profile.name}" applied`);
                 invoke("emit_to_window", {
                     windowName: "main",
                     event: "event",
@@ -163,9 +165,10 @@ async function createMenu(profiles: Profile[], activeProfile: string | null): Pr
         text: 'Next Profile',
         icon: await glyphToImage("\uE893"),
         action: () => {
-            invoke("next_profile");
+            invoke<ApplyReport | null>("next_profile")
+                .then((report) => report && notifyApplied(report.profile, report))
+                .catch((error) => notify("WOMP", `Failed to apply profile: ${error}`));
             setTimeout(() => {
-                notify("WOMP", `Profile "${activeProfile}" applied`);
                 invoke("emit_to_window", {
                     windowName: "main",
                     event: "event",
@@ -180,9 +183,10 @@ async function createMenu(profiles: Profile[], activeProfile: string | null): Pr
         text: 'Previous Profile',
         icon: await glyphToImage("\uE892"),
         action: () => {
-            invoke("previous_profile");
+            invoke<ApplyReport | null>("previous_profile")
+                .then((report) => report && notifyApplied(report.profile, report))
+                .catch((error) => notify("WOMP", `Failed to apply profile: ${error}`));
             setTimeout(() => {
-                notify("WOMP", `Profile "${activeProfile}" applied`);
                 invoke("emit_to_window", {
                     windowName: "main",
                     event: "event",